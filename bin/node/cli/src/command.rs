@@ -72,9 +72,15 @@ pub fn run() -> Result<()> {
 	match &cli.subcommand {
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
+			let verifier_urls = service::VerifierUrls {
+				tx: cli.verifier.tx_verifier_urls.clone(),
+				address: cli.verifier.address_verifier_urls.clone(),
+				btc: cli.verifier.btc_rpc_urls.clone(),
+				eth: cli.verifier.eth_rpc_urls.clone(),
+			};
 			runner.run_node_until_exit(|config| match config.role {
 				Role::Light => service::new_light(config),
-				_ => service::new_full(config),
+				_ => service::new_full_with_verifier_urls(config, verifier_urls),
 			})
 		}
 		Some(Subcommand::Inspect(cmd)) => {
@@ -35,10 +35,12 @@ use sp_inherents::InherentDataProviders;
 use sc_network::{Event, NetworkService};
 use sp_runtime::traits::Block as BlockT;
 use futures::prelude::*;
-use sc_client_api::{ExecutorProvider, RemoteBackend};
+use sc_client_api::{ExecutorProvider, RemoteBackend, Backend};
 use sp_core::traits::BareCryptoStorePtr;
+use sp_core::offchain::{OffchainStorage, STORAGE_PREFIX};
+use codec::Encode;
 use node_executor::Executor;
-use node_runtime::{tx_valid,address_valid};
+use node_runtime::{tx_valid,address_valid,offchain_common,chain_verifier};
 
 type FullClient = sc_service::TFullClient<Block, RuntimeApi, Executor>;
 type FullBackend = sc_service::TFullBackend<Block>;
@@ -163,9 +165,48 @@ pub fn new_full_params(config: Configuration) -> Result<(
 	Ok((params, import_setup, rpc_setup, select_chain, inherent_data_providers))
 }
 
+/// 链下验证服务的地址(为空时不修改链下存储里原来的设置)
+#[derive(Debug, Default, Clone)]
+pub struct VerifierUrls {
+	/// 交易验证服务
+	pub tx: Vec<String>,
+	/// 地址绑定验证服务
+	pub address: Vec<String>,
+	/// BTC节点的JSON-RPC
+	pub btc: Vec<String>,
+	/// ETH节点的JSON-RPC
+	pub eth: Vec<String>,
+}
+
+/// 把验证服务的地址写入链下存储, 链下工作机每次请求前读取(不需要升级runtime)
+fn set_verifier_urls(backend: &FullBackend, verifier_urls: &VerifierUrls) {
+	let mut storage = match backend.offchain_storage() {
+		Some(storage) => storage,
+		None => {
+			log::warn!("Offchain storage is not available, verifier urls are ignored.");
+			return;
+		}
+	};
+	for (key, urls) in [
+		(offchain_common::TX_VERIFIER_URLS_KEY, &verifier_urls.tx),
+		(offchain_common::ADDRESS_VERIFIER_URLS_KEY, &verifier_urls.address),
+		(chain_verifier::BTC_RPC_URLS_KEY, &verifier_urls.btc),
+		(chain_verifier::ETH_RPC_URLS_KEY, &verifier_urls.eth),
+	].iter() {
+		if urls.is_empty() {
+			continue;
+		}
+		// 地址里可能有RPC的用户名密码, 只打印个数
+		log::info!("{} verifier urls for {}", urls.len(), String::from_utf8_lossy(key));
+		let value = urls.iter().map(|url| url.as_bytes().to_vec()).collect::<Vec<Vec<u8>>>();
+		storage.set(STORAGE_PREFIX, key, &value.encode());
+	}
+}
+
 /// Creates a full service from the configuration.
 pub fn new_full_base(
 	config: Configuration,
+	verifier_urls: VerifierUrls,
 	with_startup_data: impl FnOnce(
 		&sc_consensus_babe::BabeBlockImport<Block, FullClient, FullGrandpaBlockImport>,
 		&sc_consensus_babe::BabeLink<Block>,
@@ -178,6 +219,8 @@ pub fn new_full_base(
 	let (params, import_setup, rpc_setup, select_chain, inherent_data_providers)
 		= new_full_params(config)?;
 
+	set_verifier_urls(&params.backend, &verifier_urls);
+
 	let (
 		role, force_authoring, name, enable_grandpa, prometheus_registry,
 		client, transaction_pool, keystore,
@@ -344,7 +387,13 @@ pub fn new_full_base(
 /// Builds a new service for a full client.
 pub fn new_full(config: Configuration)
 -> Result<TaskManager, ServiceError> {
-	new_full_base(config, |_, _| ()).map(|(task_manager, _, _, _, _)| {
+	new_full_with_verifier_urls(config, Default::default())
+}
+
+/// Builds a new service for a full client with the given verifier urls.
+pub fn new_full_with_verifier_urls(config: Configuration, verifier_urls: VerifierUrls)
+-> Result<TaskManager, ServiceError> {
+	new_full_base(config, verifier_urls, |_, _| ()).map(|(task_manager, _, _, _, _)| {
 		task_manager
 	})
 }
@@ -492,7 +541,7 @@ mod tests {
 			|config| {
 				let mut setup_handles = None;
 				let (keep_alive, inherent_data_providers, client, network, transaction_pool) =
-					new_full_base(config,
+					new_full_base(config, Default::default(),
 						|
 							block_import: &sc_consensus_babe::BabeBlockImport<Block, _, _>,
 							babe_link: &sc_consensus_babe::BabeLink<Block>,
@@ -655,7 +704,7 @@ mod tests {
 		sc_service_test::consensus(
 			crate::chain_spec::tests::integration_test_config_with_two_authorities(),
 			|config| {
-				let (keep_alive, _, client, network, transaction_pool) = new_full_base(config, |_, _| ())?;
+				let (keep_alive, _, client, network, transaction_pool) = new_full_base(config, Default::default(), |_, _| ())?;
 				Ok(sc_service_test::TestNetComponents::new(keep_alive, client, network, transaction_pool))
 			},
 			|config| {
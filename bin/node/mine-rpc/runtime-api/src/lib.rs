@@ -0,0 +1,71 @@
+//! 挖矿模块的 runtime api 定义
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::{Encode, Codec, Decode};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+
+/// 矿工的历史挖矿统计(不会被删除)
+#[derive(Eq, PartialEq, Encode, Decode, Default, Clone)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct MinerStatistics<BlockNumber, Balance> {
+	/// 总的挖矿次数
+	pub total_tx_count: u64,
+	/// 总的挖矿金额(以usdt计价)
+	pub total_usdt_nums: u64,
+	/// 矿工本人获得的总挖矿奖励(不包括上级与上上级的分成)
+	#[cfg_attr(feature = "std", serde(bound(serialize = "Balance: std::fmt::Display")))]
+	#[cfg_attr(feature = "std", serde(serialize_with = "serialize_as_string"))]
+	#[cfg_attr(feature = "std", serde(bound(deserialize = "Balance: std::str::FromStr")))]
+	#[cfg_attr(feature = "std", serde(deserialize_with = "deserialize_from_string"))]
+	pub total_reward: Balance,
+	/// 有挖矿记录的天数
+	pub days_active: u32,
+	/// 第一次挖矿的日期
+	pub first_day: BlockNumber,
+	/// 最近一次挖矿的日期
+	pub last_day: BlockNumber,
+}
+
+/// 金额超过了u64 json里用字符串表示
+#[cfg(feature = "std")]
+pub fn serialize_as_string<S: Serializer, T: std::fmt::Display>(t: &T, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.serialize_str(&t.to_string())
+}
+
+#[cfg(feature = "std")]
+pub fn deserialize_from_string<'de, D: Deserializer<'de>, T: std::str::FromStr>(deserializer: D) -> Result<T, D::Error> {
+	let s = String::deserialize(deserializer)?;
+	s.parse::<T>().map_err(|_| serde::de::Error::custom("Parse from string failed"))
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait MineApi<AccountId, BlockNumber, Balance, Moment, MineRecord> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+		Balance: Codec,
+		Moment: Codec,
+		MineRecord: Codec,
+	{
+		/// 某一天的算力排行榜(从大到小)
+		fn day_power_leaderboard(day: u32) -> Vec<(AccountId, u64)>;
+
+		/// 某一天的奖励排行榜(从大到小)
+		fn day_reward_leaderboard(day: u32) -> Vec<(AccountId, Balance)>;
+
+		/// 矿工的历史挖矿统计
+		fn miner_statistics(who: AccountId) -> MinerStatistics<BlockNumber, Balance>;
+
+		/// 归档周期的长度(挖矿记录按 区块高度/归档周期 分天保存)
+		fn archive_duration() -> BlockNumber;
+
+		/// 当前区块的时间戳
+		fn timestamp() -> Moment;
+
+		/// 矿工某一天还在链上的挖矿记录(挖矿时间, 记录)
+		fn miner_day_records(who: AccountId, day: BlockNumber) -> Vec<(Moment, MineRecord)>;
+	}
+}
@@ -0,0 +1,385 @@
+//! 挖矿模块的 RPC 接口
+//!
+//! 挖矿记录在链上只保存 `RemovePersonRecordDuration` 天, `mine_minerRecords` 会到每一天结束时的区块
+//! 上去查询当天的记录, 所以已经被删除的记录也能查到(需要节点保存了那个区块的状态, 即 `--pruning archive`)。
+//! 那个区块的runtime还没有 `MineApi` 时(升级之前), 直接读那个区块上的存储。
+//! 状态已经被节点删除的那几天查不到, 放在返回结果的 `unavailable_days` 里, 不会用最新区块上的记录代替。
+
+use std::sync::Arc;
+use codec::{Codec, Decode, Encode};
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::{twox_128, blake2_128}, storage::StorageKey};
+use sc_client_api::{StorageProvider, Backend};
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use serde::{Serialize, Deserialize};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, AtLeast32Bit, SaturatedConversion}};
+use sp_api::ProvideRuntimeApi;
+pub use node_mine_rpc_runtime_api::{MineApi as MineRuntimeApi, MinerStatistics};
+pub use self::gen_client::Client as MineClient;
+
+/// 每页最多返回的挖矿记录条数
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// 分页的挖矿记录
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MineRecordPage<MineRecord> {
+	/// 时间范围内的记录总数
+	pub total: u32,
+	/// 第几页(从0开始)
+	pub page: u32,
+	/// 每页的条数
+	pub page_size: u32,
+	/// 本页的记录(按挖矿时间从早到晚)
+	pub records: Vec<MineRecord>,
+	/// 节点上已经没有状态的天(区块高度/归档周期), 这几天的记录不在total与records里
+	pub unavailable_days: Vec<u32>,
+}
+
+/// 挖矿记录前面的几个字段(与runtime里PersonMineRecord的字段顺序一致) 直接读存储时用来按矿工与时间过滤
+#[derive(Decode)]
+struct RecordHead<Moment, BlockNumber, AccountId> {
+	_mine_tag: u8,
+	_mine_count: u16,
+	timestamp: Moment,
+	_blocknum: BlockNumber,
+	miner_address: AccountId,
+}
+
+/// 挖矿模块的存储前缀(decl_storage里的 `MineStorage`)
+const MINE_STORAGE_PREFIX: &[u8] = b"MineStorage";
+
+/// 挖矿类型 `MineTag::CLIENT` 与 `MineTag::WALLET` 编码后的值
+const MINE_TAGS: [u8; 2] = [0, 1];
+
+/// 挖矿模块的 map 的存储key(每个key都是 blake2_128_concat)
+fn storage_key(item: &[u8], keys: &[&[u8]]) -> StorageKey {
+	let mut key = twox_128(MINE_STORAGE_PREFIX).to_vec();
+	key.extend_from_slice(&twox_128(item));
+	for k in keys {
+		key.extend_from_slice(&blake2_128(k));
+		key.extend_from_slice(k);
+	}
+	StorageKey(key)
+}
+
+#[rpc]
+pub trait MineApi<BlockHash, AccountId, BlockNumber, Balance, Moment, MineRecord> {
+	/// 矿工在时间范围[from, to]内(毫秒时间戳)的挖矿记录
+	#[rpc(name = "mine_minerRecords")]
+	fn miner_records(
+		&self,
+		who: AccountId,
+		from: Moment,
+		to: Moment,
+		page: u32,
+		page_size: u32,
+		at: Option<BlockHash>
+	) -> Result<MineRecordPage<MineRecord>>;
+
+	/// 矿工的历史挖矿统计(金额用字符串表示)
+	#[rpc(name = "mine_minerStatistics")]
+	fn miner_statistics(
+		&self,
+		who: AccountId,
+		at: Option<BlockHash>
+	) -> Result<MinerStatistics<BlockNumber, String>>;
+
+	/// 某一天的算力排行榜
+	#[rpc(name = "mine_dayPowerLeaderboard")]
+	fn day_power_leaderboard(&self, day: u32, at: Option<BlockHash>) -> Result<Vec<(AccountId, u64)>>;
+
+	/// 某一天的奖励排行榜(金额用字符串表示)
+	#[rpc(name = "mine_dayRewardLeaderboard")]
+	fn day_reward_leaderboard(&self, day: u32, at: Option<BlockHash>) -> Result<Vec<(AccountId, String)>>;
+}
+
+/// A struct that implements the [`MineApi`].
+pub struct Mine<C, B, BE> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<(B, BE)>,
+}
+
+impl<C, B, BE> Mine<C, B, BE> {
+	/// Create new `Mine` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Mine { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// 参数错误
+	InvalidParams,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::InvalidParams => 2,
+		}
+	}
+}
+
+fn runtime_error<E: std::fmt::Debug>(message: &str, e: E) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+/// 区块范围[from_block, to_block]跨过的天数(区块高度/归档周期)
+fn record_days(from_block: u64, to_block: u64, archive_duration: u64) -> std::ops::RangeInclusive<u64> {
+	if archive_duration == 0 || from_block > to_block {
+		// 空的范围
+		return 1..=0;
+	}
+	from_block / archive_duration..=to_block / archive_duration
+}
+
+impl<C, Block, BE> Mine<C, Block, BE> where
+	Block: BlockT,
+	BE: Backend<Block>,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + StorageProvider<Block, BE>,
+{
+	/// 找到时间戳不早于`time`的第一个区块(没有的话返回best + 1)
+	fn first_block_not_before<AccountId, BlockNumber, Balance, Moment, MineRecord>(
+		&self,
+		best: u64,
+		time: Moment,
+	) -> Result<u64> where
+		C::Api: MineRuntimeApi<Block, AccountId, BlockNumber, Balance, Moment, MineRecord>,
+		AccountId: Codec,
+		BlockNumber: Codec,
+		Balance: Codec,
+		Moment: Codec + Ord,
+		MineRecord: Codec,
+	{
+		let api = self.client.runtime_api();
+		// 创世区块没有时间戳 从1开始找
+		let (mut low, mut high) = (1u64, best + 1);
+		while low < high {
+			let mid = low + (high - low) / 2;
+			let at = BlockId::number(mid.saturated_into());
+			let now = api.timestamp(&at)
+				.map_err(|e| runtime_error("Unable to query block timestamp.", e))?;
+			if now < time {
+				low = mid + 1;
+			} else {
+				high = mid;
+			}
+		}
+		Ok(low)
+	}
+
+	/// 直接读区块上的存储得到矿工某一天的挖矿记录(那个区块的runtime还没有 `MineApi`)
+	/// 节点上没有那个区块的状态时返回None
+	fn stored_day_records<AccountId, BlockNumber, Moment, MineRecord>(
+		&self,
+		at: &BlockId<Block>,
+		who: &AccountId,
+		day: BlockNumber,
+	) -> Result<Option<Vec<(Moment, MineRecord)>>> where
+		AccountId: Codec + PartialEq,
+		BlockNumber: Codec,
+		Moment: Codec + Ord,
+		MineRecord: Codec,
+	{
+		let txs = match self.client.storage(at, &storage_key(b"MinerAllDaysTx", &[&who.encode(), &day.encode()])) {
+			Ok(Some(data)) => Vec::<Vec<u8>>::decode(&mut &data.0[..])
+				.map_err(|e| runtime_error("Unable to decode mining txs.", e))?,
+			Ok(None) => return Ok(Some(vec![])),
+			Err(_) => return Ok(None),
+		};
+
+		let mut records = vec![];
+		for tx in txs {
+			for mine_tag in MINE_TAGS.iter() {
+				let data = match self.client.storage(at, &storage_key(b"OwnerMineRecord", &[&tx.encode(), &[*mine_tag]])) {
+					Ok(Some(data)) => data,
+					Ok(None) => continue,
+					Err(_) => return Ok(None),
+				};
+				let head = RecordHead::<Moment, BlockNumber, AccountId>::decode(&mut &data.0[..])
+					.map_err(|e| runtime_error("Unable to decode mining record.", e))?;
+				// 同一笔tx的另一种挖矿有可能是别人挖的
+				if &head.miner_address != who {
+					continue;
+				}
+				let record = MineRecord::decode(&mut &data.0[..])
+					.map_err(|e| runtime_error("Unable to decode mining record.", e))?;
+				records.push((head.timestamp, record));
+			}
+		}
+		records.sort_by(|a, b| a.0.cmp(&b.0));
+		Ok(Some(records))
+	}
+}
+
+impl<C, Block, BE, AccountId, BlockNumber, Balance, Moment, MineRecord>
+	MineApi<<Block as BlockT>::Hash, AccountId, BlockNumber, Balance, Moment, MineRecord> for Mine<C, Block, BE>
+where
+	Block: BlockT,
+	BE: Backend<Block> + Send + Sync + 'static,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + StorageProvider<Block, BE>,
+	C::Api: MineRuntimeApi<Block, AccountId, BlockNumber, Balance, Moment, MineRecord>,
+	AccountId: Codec + Clone + PartialEq,
+	BlockNumber: Codec + AtLeast32Bit + Copy,
+	Balance: Codec + std::fmt::Display,
+	Moment: Codec + Ord + Copy,
+	MineRecord: Codec,
+{
+	fn miner_records(
+		&self,
+		who: AccountId,
+		from: Moment,
+		to: Moment,
+		page: u32,
+		page_size: u32,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<MineRecordPage<MineRecord>> {
+		if from > to || page_size == 0 || page_size > MAX_PAGE_SIZE {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(Error::InvalidParams.into()),
+				message: "Invalid time range or page size.".into(),
+				data: Some(format!("page size must be in 1..={}", MAX_PAGE_SIZE).into()),
+			});
+		}
+
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash
+		);
+		let at = BlockId::hash(at_hash);
+		let best: u64 = self.client.number(at_hash)
+			.map_err(|e| runtime_error("Unable to query block number.", e))?
+			.ok_or_else(|| runtime_error("Unable to query block number.", at_hash))?
+			.saturated_into();
+
+		let archive_duration: u64 = api.archive_duration(&at)
+			.map_err(|e| runtime_error("Unable to query archive duration.", e))?
+			.saturated_into();
+
+		// 挖矿统计是后来才加的 以前的记录不在统计里 所以天数只按时间范围对应的区块来算
+		let from_block = self.first_block_not_before(best, from)?;
+		let to_block = self.first_block_not_before(best, to)?.min(best);
+		let mut records: Vec<(Moment, MineRecord)> = vec![];
+		let mut unavailable_days = vec![];
+		for day in record_days(from_block, to_block, archive_duration) {
+			// 在当天最后一个区块上查询 这时候当天的记录一定还没有被删除
+			let day_end = BlockId::number(((day + 1) * archive_duration - 1).min(best).saturated_into());
+			let day_records = match api.miner_day_records(&day_end, who.clone(), day.saturated_into()) {
+				Ok(day_records) => Some(day_records),
+				// 那个区块的runtime还没有这个接口 或是节点没有那个区块的状态
+				Err(_) => self.stored_day_records(&day_end, &who, BlockNumber::saturated_from(day))?,
+			};
+
+			match day_records {
+				Some(day_records) => records.extend(day_records.into_iter().filter(|r| r.0 >= from && r.0 <= to)),
+				None => unavailable_days.push(day.saturated_into()),
+			}
+		}
+
+		let total = records.len() as u32;
+		let records = records.into_iter()
+			.skip(page.saturating_mul(page_size) as usize)
+			.take(page_size as usize)
+			.map(|r| r.1)
+			.collect();
+
+		Ok(MineRecordPage { total, page, page_size, records, unavailable_days })
+	}
+
+	fn miner_statistics(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<MinerStatistics<BlockNumber, String>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.miner_statistics(&at, who)
+			.map(|s| MinerStatistics {
+				total_tx_count: s.total_tx_count,
+				total_usdt_nums: s.total_usdt_nums,
+				total_reward: s.total_reward.to_string(),
+				days_active: s.days_active,
+				first_day: s.first_day,
+				last_day: s.last_day,
+			})
+			.map_err(|e| runtime_error("Unable to query miner statistics.", e))
+	}
+
+	fn day_power_leaderboard(
+		&self,
+		day: u32,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<Vec<(AccountId, u64)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.day_power_leaderboard(&at, day)
+			.map_err(|e| runtime_error("Unable to query power leaderboard.", e))
+	}
+
+	fn day_reward_leaderboard(
+		&self,
+		day: u32,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<Vec<(AccountId, String)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.day_reward_leaderboard(&at, day)
+			.map(|board| board.into_iter().map(|(who, reward)| (who, reward.to_string())).collect())
+			.map_err(|e| runtime_error("Unable to query reward leaderboard.", e))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn record_days_follow_the_block_range() {
+		assert_eq!(record_days(1, 9, 10).collect::<Vec<_>>(), vec![0]);
+		assert_eq!(record_days(5, 25, 10).collect::<Vec<_>>(), vec![0, 1, 2]);
+		assert_eq!(record_days(30, 30, 10).collect::<Vec<_>>(), vec![3]);
+	}
+
+	#[test]
+	fn empty_block_range_has_no_days() {
+		// 结束时间早于第一个区块
+		assert_eq!(record_days(5, 4, 10).count(), 0);
+		assert_eq!(record_days(1, 100, 0).count(), 0);
+	}
+
+	#[test]
+	fn record_days_start_from_the_first_block_of_the_range() {
+		// 从创世区块开始的范围 第0天也要查(不管矿工的统计从哪一天开始)
+		assert_eq!(record_days(0, 59, 10).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+	}
+
+	#[test]
+	fn record_head_is_read_from_a_stored_record() {
+		// mine_tag, mine_count, timestamp, blocknum, miner_address, 后面是其他字段
+		let mut data = (1u8, 2u16, 1_600_000_000_000u64, 30u32, [7u8; 32]).encode();
+		data.extend(b"tx".to_vec().encode());
+		let head = RecordHead::<u64, u32, [u8; 32]>::decode(&mut &data[..]).unwrap();
+		assert_eq!((head.timestamp, head.miner_address), (1_600_000_000_000, [7u8; 32]));
+	}
+
+	#[test]
+	fn storage_key_hashes_each_key() {
+		let key = storage_key(b"MinerAllDaysTx", &[&[1u8; 32], &5u32.encode()]).0;
+		assert_eq!(&key[..16], &twox_128(b"MineStorage"));
+		assert_eq!(&key[16..32], &twox_128(b"MinerAllDaysTx"));
+		assert_eq!(&key[32..48], &blake2_128(&[1u8; 32]));
+		assert_eq!(&key[48..80], &[1u8; 32]);
+		assert_eq!(&key[80..96], &blake2_128(&5u32.encode()));
+		assert_eq!(&key[96..], &5u32.encode()[..]);
+	}
+}
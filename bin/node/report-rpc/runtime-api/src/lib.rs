@@ -0,0 +1,163 @@
+//! 举报模块的 runtime api 定义
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+use codec::{Encode, Codec, Decode};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+
+/// 金额超过了u64 json里用字符串表示
+#[cfg(feature = "std")]
+mod balance_as_string {
+	use serde::{Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer, T: std::fmt::Display>(t: &T, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&t.to_string())
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>, T: std::str::FromStr>(deserializer: D) -> Result<T, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		s.parse::<T>().map_err(|_| serde::de::Error::custom("Parse from string failed"))
+	}
+}
+
+/// 举报者的信誉
+#[derive(Eq, PartialEq, Encode, Decode, Default, Clone)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "std", serde(bound(serialize = "Balance: std::fmt::Display", deserialize = "Balance: std::str::FromStr")))]
+pub struct ReporterReputation<Balance> {
+	/// 作弊成立的举报次数
+	pub passed: u32,
+	/// 作弊不成立的举报次数(包括申诉成功的)
+	pub rejected: u32,
+	/// 取消的举报次数
+	pub cancelled: u32,
+	/// 信誉分(0~100, 没有举报过是50)
+	pub score: u32,
+	/// 现在举报需要抵押的金额
+	#[cfg_attr(feature = "std", serde(with = "balance_as_string"))]
+	pub reserve: Balance,
+	/// 作弊成立时能拿到的举报奖励
+	#[cfg_attr(feature = "std", serde(with = "balance_as_string"))]
+	pub reward: Balance,
+	/// 每天最多可以举报的次数
+	pub daily_limit: u32,
+	/// 今天已经举报的次数
+	pub reported_today: u32,
+}
+
+/// 申诉的状态
+#[derive(Eq, PartialEq, Encode, Decode, Clone)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum AppealStatus {
+	/// 等待技术委员会裁决
+	Voting,
+	/// 申诉成功
+	Accepted,
+	/// 申诉失败
+	Rejected,
+	/// 裁决期内没有裁决(退还抵押)
+	Expired,
+}
+
+/// 还没有处理完的举报
+#[derive(Eq, PartialEq, Encode, Decode, Clone)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ReportSummary<AccountId, BlockNumber> {
+	/// 举报编号
+	pub report_id: u32,
+	/// 被举报的交易
+	pub tx: Vec<u8>,
+	/// 币种
+	pub symbol: Vec<u8>,
+	/// 举报者
+	pub reporter: AccountId,
+	/// 被举报的矿工
+	pub illegal_man: AccountId,
+	/// 举报时的区块
+	pub start_block: BlockNumber,
+	/// 投票在哪个区块过期
+	pub expire_block: BlockNumber,
+	/// 离过期还有多少个区块(投票已经结束的是0)
+	pub remaining_blocks: BlockNumber,
+	/// 投票结果(None表示还在投票, Some(true)表示作弊成立, 等待统一处理金额)
+	pub result: Option<bool>,
+}
+
+/// 举报的票数
+#[derive(Eq, PartialEq, Encode, Decode, Clone)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "std", serde(bound(serialize = "AccountId: Serialize, Balance: std::fmt::Display", deserialize = "AccountId: Deserialize<'de>, Balance: std::str::FromStr")))]
+pub struct ReportTally<AccountId, Balance> {
+	/// 举报编号
+	pub report_id: u32,
+	/// 投赞成票的议员
+	pub approve_mans: Vec<AccountId>,
+	/// 投反对票的议员
+	pub reject_mans: Vec<AccountId>,
+	/// 议员人数
+	pub council_size: u32,
+	/// 一方的议员票数达到多少就结束投票
+	pub council_threshold: u32,
+	/// 抵押投票的赞成票(按conviction加权)
+	#[cfg_attr(feature = "std", serde(with = "balance_as_string"))]
+	pub stake_ayes: Balance,
+	/// 抵押投票的反对票(按conviction加权)
+	#[cfg_attr(feature = "std", serde(with = "balance_as_string"))]
+	pub stake_nays: Balance,
+	/// 抵押投票的人数
+	pub stake_voters: u32,
+	/// 抵押投票至少需要多少票(不接受抵押投票的裁决规则是0)
+	#[cfg_attr(feature = "std", serde(with = "balance_as_string"))]
+	pub min_turnout: Balance,
+	/// 议会直接裁决的结果
+	pub fast_tracked: Option<bool>,
+	/// 投票结果(None表示还在投票)
+	pub result: Option<bool>,
+}
+
+/// 矿工被惩罚的记录
+#[derive(Eq, PartialEq, Encode, Decode, Clone)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "std", serde(bound(serialize = "AccountId: Serialize, BlockNumber: Serialize, Balance: std::fmt::Display", deserialize = "AccountId: Deserialize<'de>, BlockNumber: Deserialize<'de>, Balance: std::str::FromStr")))]
+pub struct PunishmentRecord<AccountId, BlockNumber, Balance> {
+	/// 作弊的交易
+	pub tx: Vec<u8>,
+	/// 举报者
+	pub reporter: AccountId,
+	/// 扣款的区块(None表示还没有扣款)
+	pub punish_block: Option<BlockNumber>,
+	/// 实际扣除的金额
+	#[cfg_attr(feature = "std", serde(with = "balance_as_string"))]
+	pub slashed: Balance,
+	/// 申诉的状态(None表示没有申诉)
+	pub appeal: Option<AppealStatus>,
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait ReportApi<AccountId, BlockNumber, Balance> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+		Balance: Codec,
+	{
+		/// 举报者的信誉
+		fn reporter_reputation(who: AccountId) -> ReporterReputation<Balance>;
+
+		/// 所有还没有处理完的举报(按编号从小到大)
+		fn open_reports() -> Vec<ReportSummary<AccountId, BlockNumber>>;
+
+		/// 某个举报的票数
+		fn report_tally(report_id: u32) -> Option<ReportTally<AccountId, Balance>>;
+
+		/// 与某个账户有关(举报者或是被举报者)的举报
+		fn reports_of(who: AccountId) -> Vec<ReportSummary<AccountId, BlockNumber>>;
+
+		/// 矿工被惩罚的历史
+		fn punishment_history(who: AccountId) -> Vec<PunishmentRecord<AccountId, BlockNumber, Balance>>;
+	}
+}
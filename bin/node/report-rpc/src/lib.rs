@@ -0,0 +1,171 @@
+//! 举报模块的 RPC 接口
+//!
+//! 议员不用再去解码 `Reports`、`AllPunishmentInfo` 等存储, 直接查询正在投票的举报、票数以及个人相关的举报。
+//! 返回的金额都用字符串表示(与 `mine_dayRewardLeaderboard` 一样)。
+
+use std::sync::Arc;
+use codec::Codec;
+use sp_blockchain::HeaderBackend;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use sp_api::ProvideRuntimeApi;
+pub use node_report_rpc_runtime_api::{
+	ReportApi as ReportRuntimeApi, ReporterReputation, ReportSummary, ReportTally, PunishmentRecord, AppealStatus,
+};
+pub use self::gen_client::Client as ReportClient;
+
+/// `Balance` 是runtime里的金额类型, 返回时转成字符串
+#[rpc]
+pub trait ReportApi<BlockHash, AccountId, BlockNumber, Balance> {
+	/// 所有还没有处理完的举报(包括剩余的投票时间)
+	#[rpc(name = "report_openReports")]
+	fn open_reports(&self, at: Option<BlockHash>) -> Result<Vec<ReportSummary<AccountId, BlockNumber>>>;
+
+	/// 某个举报的票数以及结束投票需要的票数
+	#[rpc(name = "report_tally")]
+	fn report_tally(&self, report_id: u32, at: Option<BlockHash>) -> Result<Option<ReportTally<AccountId, String>>>;
+
+	/// 与某个账户有关(举报者或是被举报者)的举报
+	#[rpc(name = "report_reportsOf")]
+	fn reports_of(&self, who: AccountId, at: Option<BlockHash>) -> Result<Vec<ReportSummary<AccountId, BlockNumber>>>;
+
+	/// 矿工被惩罚的历史
+	#[rpc(name = "report_punishmentHistory")]
+	fn punishment_history(
+		&self,
+		who: AccountId,
+		at: Option<BlockHash>
+	) -> Result<Vec<PunishmentRecord<AccountId, BlockNumber, String>>>;
+
+	/// 举报者的信誉
+	#[rpc(name = "report_reporterReputation")]
+	fn reporter_reputation(&self, who: AccountId, at: Option<BlockHash>) -> Result<ReporterReputation<String>>;
+}
+
+/// A struct that implements the [`ReportApi`].
+pub struct Report<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Report<C, B> {
+	/// Create new `Report` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Report { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+fn runtime_error<E: std::fmt::Debug>(message: &str, e: E) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, Block, AccountId, BlockNumber, Balance>
+	ReportApi<<Block as BlockT>::Hash, AccountId, BlockNumber, Balance> for Report<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: ReportRuntimeApi<Block, AccountId, BlockNumber, Balance>,
+	AccountId: Codec,
+	BlockNumber: Codec,
+	Balance: Codec + std::fmt::Display,
+{
+	fn open_reports(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<ReportSummary<AccountId, BlockNumber>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.open_reports(&at)
+			.map_err(|e| runtime_error("Unable to query open reports.", e))
+	}
+
+	fn report_tally(
+		&self,
+		report_id: u32,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<Option<ReportTally<AccountId, String>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.report_tally(&at, report_id)
+			.map(|tally| tally.map(|t| ReportTally {
+				report_id: t.report_id,
+				approve_mans: t.approve_mans,
+				reject_mans: t.reject_mans,
+				council_size: t.council_size,
+				council_threshold: t.council_threshold,
+				stake_ayes: t.stake_ayes.to_string(),
+				stake_nays: t.stake_nays.to_string(),
+				stake_voters: t.stake_voters,
+				min_turnout: t.min_turnout.to_string(),
+				fast_tracked: t.fast_tracked,
+				result: t.result,
+			}))
+			.map_err(|e| runtime_error("Unable to query report tally.", e))
+	}
+
+	fn reports_of(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<Vec<ReportSummary<AccountId, BlockNumber>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.reports_of(&at, who)
+			.map_err(|e| runtime_error("Unable to query reports of account.", e))
+	}
+
+	fn punishment_history(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<Vec<PunishmentRecord<AccountId, BlockNumber, String>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.punishment_history(&at, who)
+			.map(|history| history.into_iter().map(|r| PunishmentRecord {
+				tx: r.tx,
+				reporter: r.reporter,
+				punish_block: r.punish_block,
+				slashed: r.slashed.to_string(),
+				appeal: r.appeal,
+			}).collect())
+			.map_err(|e| runtime_error("Unable to query punishment history.", e))
+	}
+
+	fn reporter_reputation(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>
+	) -> Result<ReporterReputation<String>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.reporter_reputation(&at, who)
+			.map(|r| ReporterReputation {
+				passed: r.passed,
+				rejected: r.rejected,
+				cancelled: r.cancelled,
+				score: r.score,
+				reserve: r.reserve.to_string(),
+				reward: r.reward.to_string(),
+				daily_limit: r.daily_limit,
+				reported_today: r.reported_today,
+			})
+			.map_err(|e| runtime_error("Unable to query reporter reputation.", e))
+	}
+}
@@ -32,8 +32,8 @@
 
 use std::sync::Arc;
 
-use node_primitives::{Block, BlockNumber, AccountId, Index, Balance, Hash};
-use node_runtime::UncheckedExtrinsic;
+use node_primitives::{Block, BlockNumber, AccountId, Index, Balance, Hash, Moment};
+use node_runtime::{UncheckedExtrinsic, Runtime, mine::OwnerMineRecordItem};
 use sp_api::ProvideRuntimeApi;
 use sp_transaction_pool::TransactionPool;
 use sp_blockchain::{Error as BlockChainError, HeaderMetadata, HeaderBackend};
@@ -98,15 +98,19 @@ pub struct FullDeps<C, P, SC> {
 pub type IoHandler = jsonrpc_core::IoHandler<sc_rpc::Metadata>;
 
 /// Instantiate all Full RPC extensions.
-pub fn create_full<C, P, M, SC>(
+pub fn create_full<C, P, M, SC, B>(
 	deps: FullDeps<C, P, SC>,
 ) -> jsonrpc_core::IoHandler<M> where
 	C: ProvideRuntimeApi<Block>,
 	C: HeaderBackend<Block> + HeaderMetadata<Block, Error=BlockChainError> + 'static,
+	C: sc_client_api::StorageProvider<Block, B>,
+	B: sc_client_api::Backend<Block> + Send + Sync + 'static,
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 	C::Api: pallet_contracts_rpc::ContractsRuntimeApi<Block, AccountId, Balance, BlockNumber>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance, UncheckedExtrinsic>,
+	C::Api: node_mine_rpc::MineRuntimeApi<Block, AccountId, BlockNumber, Balance, Moment, OwnerMineRecordItem<Runtime>>,
+	C::Api: node_report_rpc::ReportRuntimeApi<Block, AccountId, BlockNumber, Balance>,
 	C::Api: BabeApi<Block>,
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + 'static,
@@ -116,6 +120,8 @@ pub fn create_full<C, P, M, SC>(
 	use substrate_frame_rpc_system::{FullSystem, SystemApi};
 	use pallet_contracts_rpc::{Contracts, ContractsApi};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
+	use node_mine_rpc::{Mine, MineApi};
+	use node_report_rpc::{Report, ReportApi};
 
 	let mut io = jsonrpc_core::IoHandler::default();
 	let FullDeps {
@@ -148,6 +154,12 @@ pub fn create_full<C, P, M, SC>(
 	io.extend_with(
 		TransactionPaymentApi::to_delegate(TransactionPayment::new(client.clone()))
 	);
+	io.extend_with(
+		MineApi::to_delegate(Mine::new(client.clone()))
+	);
+	io.extend_with(
+		ReportApi::to_delegate(Report::new(client.clone()))
+	);
 	io.extend_with(
 		sc_consensus_babe_rpc::BabeApi::to_delegate(
 			BabeRpcHandler::new(
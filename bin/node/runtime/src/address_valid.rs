@@ -38,7 +38,8 @@ use app_crypto::{sr25519};
 //use crate::price_fetch::crypto::AuthorityId;
 
 use crate::register::{self,TokenStatus,TokenInfo,AddressOf,AddressUsedForMiner,ChangeAddressCount,
-                      PerMinerUsingAddress, TokenStatusLen, Trait as RegisterTrait};
+                      PerMinerUsingAddress, TokenStatusLen, TokenStatusPending, TokenStatusExpiry, TimeoutPolicy, Trait as RegisterTrait};
+use crate::report;
 use crate::offchain_common::*;
 
 /// Our local KeyType.
@@ -84,11 +85,12 @@ pub mod address_crypto {
 }
 
 
-// 请求的查询接口
+// 请求的查询接口(链下存储里没有设置验证服务地址时用这里的默认地址)
 pub const ADDRESS_FETCHED_CRYPTS: [(&[u8], &[u8]); 1] = [
     (b"localhost",b"http://localhost:8421/v1/account/verify"),
 ];
 
+#[derive(PartialEq)]
 enum VerifyStatus {
     Continue,  //  不做任何处理
     Failed,    // 注册失败
@@ -120,6 +122,8 @@ decl_event!(
         FetchedSuc(AccountId,BlockNumber, Vec<u8>, u64), // 当前address 状态记录事件
 
         FailedEvent(AccountId,BlockNumber,Vec<u8>), // 记录返回错误的情况
+
+        AddressVerifyTimeout(Vec<u8>, BlockNumber, TimeoutPolicy), // 验证超时(tx, 加入队列的区块, 处理方式)
   }
 );
 
@@ -151,7 +155,7 @@ decl_module! {
              <AddressFetchFailed<T>>::remove(&key);
             }
         }
-        0
+        Self::handle_timeout(block)
     }
 
     #[weight = 0]
@@ -168,43 +172,16 @@ decl_module! {
       let now = <timestamp::Module<T>>::get();
       let block_num = <system::Module<T>>::block_number();
       let duration = block_num / T::Duration::get();
-//        1000: 表示初始值
-//        1001: 表示验证1次，但是请求失败了
-//        130x: 终止，3个验证通过 pass
-//        1x7x: 终止，7个节点验证不通过,就failed
-//        1009: 终止，网络全部失败   todo: 怎么处理？ 目前按照 failed处理
-//        1109: pass 处理
-
        debug::info!("response status={:?}",status);
-       let (token_status,register_account,symbol) = <TokenStatus<T>>::get(tx.clone());
-       debug::info!("token_status={:?},register_account={:?},symbol={:?}",token_status,register_account,symbol);
        ensure!(<TokenStatus<T>>::contains_key(tx.clone()), "不需要再操作,tx已经从TokenStatus移除");
-       debug::info!("获取到了本地服务的返回信息,对状态位操作");
-      if status== 0{   // 20000
-        // 成功
-       <FetchRecord<T>>::mutate(
-        duration,account_id.clone(),
-        |val|{
-            val.0 = val.0.checked_add(1).unwrap();
-        });
-       <TokenStatus<T>>::mutate(&tx,|val|val.0 = val.0.checked_add(100).unwrap());//  通过次数加1,总次数加1
-      }else if status == 255{ // query  ./token-query 没有收到返回的消息
-         <FetchRecord<T>>::mutate(
-            duration,account_id.clone(),
-            |val|{
-                val.2 = val.2.checked_add(1).unwrap();
-//                val
-            });
-             <TokenStatus<T>>::mutate(&tx,|val|val.0 = val.0.checked_add(1).unwrap());// 仅仅对总次数加1
-      }else{
-        <FetchRecord<T>>::mutate( // 可能是200x 400x
-            duration,account_id.clone(),
-            |val|{
-                val.1 = val.1.checked_add(1).unwrap();
-//                val
-            });
-             <TokenStatus<T>>::mutate(&tx,|val|val.0 = val.0.checked_add(10).unwrap()); // 失败次数加1,总次数加1
-      }
+       let vote = register::VerifyVote::from_status(status);
+       // 同一个验证人只算一次(validate_unsigned 已经拒绝了重复的投票)
+       ensure!(<TokenStatus<T>>::mutate(&tx, |val| val.0.vote(&account_id, vote)), "已经投过票了");
+       <FetchRecord<T>>::mutate(duration, account_id.clone(), |val| match vote {
+           register::VerifyVote::Pass => val.0 = val.0.saturating_add(1),
+           register::VerifyVote::Fail => val.1 = val.1.saturating_add(1),
+           register::VerifyVote::Error => val.2 = val.2.saturating_add(1),
+       });
       Self::address_verify_handle(&tx);
       debug::info!("----上链成功: record_address:{:?}-----", duration);
       Ok(())
@@ -222,24 +199,16 @@ decl_module! {
         )->DispatchResult{
             ensure_none(_origin)?;
             let now = <timestamp::Module<T>>::get();
-            <TokenStatus<T>>::try_mutate(&tx,|val|{
-                if val.0 == 0 {
-                    debug::error!("当前 TokenStatus 状态为 0.不需要再操作,tx已经从TokenStatus移除");
-                    return Err("");
-                }
-                debug::info!("record_fail_verify 对状态位加 1");
-                val.0 = val.0.checked_add(1).unwrap();  // 无应答情况
-                return Ok(&tx)}
-            )?;
+            ensure!(<TokenStatus<T>>::contains_key(&tx), "不需要再操作,tx已经从TokenStatus移除");
+            // 没有结果 不算票, 同一个验证人只记一次; 本地服务没开的之后每次分到都再记一次没有应答
+            let voted = <TokenStatus<T>>::mutate(&tx, |val| val.0.vote(&account, register::VerifyVote::Error));
+            ensure!(voted || Self::is_repeated_downtime(&tx, &account, &err), "已经记录过了");
               // 记录获取fetch失败的信息
             let failed_struct = FetchFailedOf::<T> {
                     timestamp: now,
                     tx: tx.clone(),
                     err: err.clone()
             };
-            let status:u64 = <TokenStatus<T>>::get(tx.clone()).0;
-            debug::info!("------验证失败:status={:?},tx={:?}-------",status,hex::encode(&tx));
-            Self::address_verify_handle(&tx);
             <AddressFetchFailed<T>>::mutate(&account, |fetch_failed| {
             if fetch_failed.len()>50{  // 最多保留50个的长度
                 fetch_failed.pop();
@@ -248,7 +217,10 @@ decl_module! {
             });
 
             if err == WAIT_HTTP_CONVER_REPONSE.as_bytes().to_vec(){ // 本地服务没开起来
-               T::slash_validtor(account.clone());
+               T::note_verifier_downtime(account.clone());
+            }
+            if err == INVALID_RESPONSE_SIGNATURE.as_bytes().to_vec(){ // 验证服务的结果被篡改
+               T::report_verifier_fault(account.clone(), tx.clone());
             }
 
             Self::deposit_event(RawEvent::FailedEvent(account.clone(),block,tx));
@@ -273,54 +245,60 @@ decl_module! {
 
 impl<T: Trait> Module<T> {
     fn offchain(block_num: T::BlockNumber,key: <T as BaseLocalAuthorityTrait>::AuthorityId, account: &T::AccountId) -> DispatchResult{
+        // 只验证这个区块分给自己的地址绑定
+        let assignment = <register::AddressVerifyAssignment>::get();
+        let items = <TokenStatus<T>>::iter().map(|(tx, (tally, verify_account, symbol))| ((tx, verify_account, symbol), tally));
+        for (tx, verify_account, symbol) in T::assigned_items(block_num, account, assignment, items).iter() {
+            if let Err(e) = Self::verify_address(block_num, key.clone(), account, tx, verify_account, symbol) {
+                debug::error!("------verify address {:?} failed: {:?}------", hex::encode(tx), e);
+            }
+        }
+        Ok(())
+    }
 
+    fn verify_address(block_num: T::BlockNumber,key: <T as BaseLocalAuthorityTrait>::AuthorityId, account: &T::AccountId, tx: &[u8], verify_account: &T::AccountId, symbol: &[u8]) -> DispatchResult{
         for (remote_src, remote_url) in ADDRESS_FETCHED_CRYPTS.iter() {
-            // let (mut symbol, mut token_address,mut tx) = (vec![], vec![], vec![]);
-            for (iter_key,value) in <TokenStatus<T>>::iter().into_iter(){
-                let (status,verify_account,symbol) = value;   // 注册的账号名, eth
-                let tx= iter_key;  // 转账tx
-                let tx_hex = hex::encode(&tx);
-                let symbol_str = core::str::from_utf8(&symbol).map_err(|e|
-                    {debug::info!("symbol解析错误:{:?}",e);
-                    "from_utf8 error" })?;
-                debug::info!("迭代器获取 symbol = {:?}",&symbol_str);
-                debug::info!("迭代器获取 tx = {:?}",tx_hex);
-
-                // post json 构造
-                let body = Self::from_register_item(&tx,&symbol,&verify_account).ok_or("from_register_item error");
-                let body = match body{
-                    Ok(body) => body,
-                    Err(e) => {
-                        debug::error!("---------{:?}---------",e);
-                        Self::call_record_fail_verify(block_num,key.clone(),account,&tx,e)?;
-                        return Err(DispatchError::Other("from_register_item error"));
-                    }
-                };
-                // post请求,并结果上链   Self::fetch_address(block_num,key.clone(),account,*remote_src, *remote_url,&tx,body)
-                match T::fetch_status(*remote_src,*remote_url,body){
-                    Ok(status) => {
-                        let tx_hex = hex::encode(&tx);
-                        debug::info!("*** fetch ***: {:?}:{:?},{:?}",
-                            core::str::from_utf8(remote_src).unwrap(),
-                            core::str::from_utf8(remote_url).unwrap(),
-                            tx_hex,
-                        );
-                        Self::call_record_address(block_num, key.clone(), account, &tx, status)?;
-                    },
-                    Err(e) => {
-                        debug::info!("~~~~~~ Error address fetching~~~~~~~~:  {:?}: {:?}",tx_hex,e);
-                        Self::call_record_fail_verify(block_num,key.clone(),account,&tx,e)?;
-                        // 实现错误信息上链
-                    }
+            let tx_hex = hex::encode(&tx);
+            let symbol_str = core::str::from_utf8(&symbol).map_err(|e|
+                {debug::info!("symbol解析错误:{:?}",e);
+                "from_utf8 error" })?;
+            debug::info!("分到的 symbol = {:?}",&symbol_str);
+            debug::info!("分到的 tx = {:?}",tx_hex);
+
+            // post json 构造
+            let body = Self::from_register_item(&tx,&symbol,&verify_account).ok_or("from_register_item error");
+            let body = match body{
+                Ok(body) => body,
+                Err(e) => {
+                    debug::error!("---------{:?}---------",e);
+                    Self::call_record_fail_verify(block_num,key.clone(),account,&tx,e)?;
+                    return Ok(());
+                }
+            };
+            // post请求,并结果上链
+            let urls = T::verifier_urls(ADDRESS_VERIFIER_URLS_KEY, remote_url);
+            // 登记了验证服务公钥的, 结果要有正确的签名
+            let auth = T::response_auth(account, tx, symbol);
+            match T::fetch_status_failover(*remote_src,&urls,body,auth.as_ref()){
+                Ok(status) => {
+                    debug::info!("*** fetch ***: {:?}:{:?},{:?}",
+                        core::str::from_utf8(remote_src).unwrap(),
+                        urls.len(),
+                        tx_hex,
+                    );
+                    Self::call_record_address(block_num, key.clone(), account, &tx, status)?;
+                },
+                Err(e) => {
+                    debug::info!("~~~~~~ Error address fetching~~~~~~~~:  {:?}: {:?}",tx_hex,e);
+                    Self::call_record_fail_verify(block_num,key.clone(),account,&tx,e)?;
+                    // 实现错误信息上链
                 }
-                break;
             }
+            break;
         }
         Ok(())
     }
 
-
-
 //    fn fetch_address<'a>(remote_src:&'a [u8], remote_url:&'a [u8], body:Vec<u8>) -> StdResult<u64> {
 //        let json = T::fetch_json(remote_url,body)?; // http请求
 //        let status = match remote_src {
@@ -369,7 +347,7 @@ impl<T: Trait> Module<T> {
         e: &'a str,
     ) -> StrDispatchResult{
         // 实现错误信息上链
-        let signature = key.sign(&(block_num,account.clone(),tx.to_vec()).encode()).ok_or("signing failed!")?;
+        let signature = key.sign(&(block_num,account.clone(),tx.to_vec(),e.as_bytes().to_vec()).encode()).ok_or("signing failed!")?;
         debug::info!("record_fail_verify调用前签名,block_num = {:?},tx={:?}",block_num, hex::encode(&tx));
 
         let call = Call::record_fail_verify(block_num,account.clone(),key.clone(),tx.to_vec(), e.as_bytes().to_vec(),signature);
@@ -391,35 +369,13 @@ impl<T: Trait> Module<T> {
             return None;
         }
 
-        let keys:[&[u8];3] = [b"tx",b"symbol",b"account"];
-
-//        let tx_hex:&str = tx.to_hex();
-//        let tx_hex =  hex::encode(tx);   // tx_hex 是 16进制的字符串
-//        let tx_vec = &[hex_0x,tx_hex.as_bytes()].concat();
-
-//        let token_address_hex: &str = token_address.to_hex();
-//        let token_address_hex = hex::encode(token_address);   // tx_hex 是 16进制的字符串
-//        let token_address_vec = &[hex_0x,token_address_hex.as_bytes()].concat(); // token_address_hex.as_bytes();
-
-        let tx_vec= hex_to_u8(tx);
-        let token_address_vec = hex_to_u8(&token_address);
-        let vals:[&[u8];3] = [&tx_vec,symbol,&token_address_vec];
-
-        let mut json_val = vec![POST_KEYWORD[0]];
-        for (i,key) in keys.iter().enumerate(){
-            // 形如 "tx": "xxxx",
-            json_val.push(POST_KEYWORD[1]); //json_val.push("\"");
-            json_val.push(key);
-            json_val.push(POST_KEYWORD[2]);  //json_val.push("\":");
-            json_val.push(POST_KEYWORD[1]);  // json_val.push("\"");
-            json_val.push(vals[i]);
-            json_val.push(POST_KEYWORD[1]);  //json_val.push("\"");
-            json_val.push(POST_KEYWORD[3]);  //json_val.push(",");
-        }
-        json_val.pop();    // 移除最后一个 ","
-        json_val.push(POST_KEYWORD[4]);    //json_val.push("}");
-
-        let json_vec = json_val.concat().to_vec();
+        let request = AddressVerifyRequest {
+            version: VERIFY_SCHEMA_VERSION,
+            tx,
+            symbol: core::str::from_utf8(symbol).ok()?,
+            account: &token_address,
+        };
+        let json_vec = to_json_body(&request).ok()?;
         debug::info!("请求的json:{:?}",core::str::from_utf8(&json_vec).ok()?);
 
         Some(json_vec)
@@ -440,54 +396,76 @@ impl<T: Trait> Module<T> {
 //        Ok(val)
 //    }
 
-    fn address_verify_handle(tx: &[u8]) -> StdResult<VerifyStatus>{
-        // 是否举报, true 表示举报
-//        判断
-//        1.十位的数字 >=7 fail ,   记录失败个数
-//        2.个位数字 >=8, failed
-//        3.百位数字 >=3,pass
-        let mut verify_status:VerifyStatus = VerifyStatus::Continue;//初始化一个值
-        let (status,register_account,symbol) = <TokenStatus<T>>::get(tx);
-        let num = TokenStatusLen::get();
-        if status  < 1000{
-            debug::error!("=====绑定验证失败:当前的 tx={:?},状态为 {:?},=====",hex::encode(tx),status);
-            <TokenStatus<T>>::remove(tx);
-            if num > 0{
-                TokenStatusLen::mutate(|n|*n -= 1);
-            }
-            return Err("status 小于 1000");
+    /// 只有开启了ocw验证的验证人用自己的key才能投票, 并且同一个tx只能投一次
+    fn check_voter(
+        key: &<T as BaseLocalAuthorityTrait>::AuthorityId,
+        account: &T::AccountId,
+        tx: &[u8],
+        vote: register::VerifyVote,
+    ) -> core::result::Result<(), InvalidTransaction> {
+        if key.clone().into_account32() != *account || !T::is_ocw_validator(account) {
+            debug::warn!("-----{:?} 不是开启了ocw验证的验证人-----", account);
+            return Err(InvalidTransaction::BadProof);
         }
+        if !<TokenStatus<T>>::contains_key(tx) {
+            return Err(InvalidTransaction::Stale);
+        }
+        if !<TokenStatus<T>>::get(tx).0.can_vote(account, vote) {
+            debug::warn!("-----{:?} 已经投过票了-----", account);
+            return Err(InvalidTransaction::Stale);
+        }
+        Ok(())
+    }
 
-        debug::info!("当前状态 status={:?}",status);
-        let units_digit = status%10;     // 个位数
-        let tens_digit = status/10%10;   // 十位数
-        let hundreds_digit  = status/100%10;  // 百位数
-
-        if tens_digit >= 6 {    // 十位数
-            verify_status = VerifyStatus::Failed;
-        }else if hundreds_digit >=3 {   // 百位数大于3
-            verify_status = VerifyStatus::Pass;
-        } else if units_digit >= 8 {   // 个位数大于8
-            // 且十位数
-            if hundreds_digit >=2 {
-                verify_status = VerifyStatus::Pass;
-            }else{
-                verify_status = VerifyStatus::Failed;
-            }
+    /// 已经记过没有结果的验证人又报本地服务没开(不再记票, 只算一次没有应答, 同一个区块只算一次)
+    fn is_repeated_downtime(tx: &[u8], account: &T::AccountId, err: &[u8]) -> bool {
+        err == WAIT_HTTP_CONVER_REPONSE.as_bytes() && <TokenStatus<T>>::get(tx).0.errors.contains(account)
+    }
+
+    /// 按开启了ocw验证的验证人数判断是否结束验证, 结束了就移出队列
+    fn address_verify_handle(tx: &[u8]) -> StdResult<VerifyStatus>{
+        let (tally,_,_) = <TokenStatus<T>>::get(tx);
+        let quorum = <register::Module<T>>::address_verify_quorum();
+        let validators = T::ocw_validator_count();
+        debug::info!("当前投票 通过:{:?} 不通过:{:?} 没有结果:{:?}", tally.passes.len(), tally.fails.len(), tally.errors.len());
+
+        // 与结论一致的验证人记一票(用于分验证奖励)
+        let verify_status = if quorum.is_failed(&tally, validators) {
+            <register::Module<T>>::note_correct_votes(&tally.fails);
+            VerifyStatus::Failed
+        } else if quorum.is_passed(&tally, validators) {
+            <register::Module<T>>::note_correct_votes(&tally.passes);
+            VerifyStatus::Pass
         } else {
-             verify_status = VerifyStatus::Continue;  // 默认值
-        }
+            VerifyStatus::Continue
+        };
 
+        // 验证人判定不通过的算作弊
+        Self::finish_address(tx, &verify_status, verify_status == VerifyStatus::Failed);
+        return Ok(verify_status);
+    }
+
+    /// 结束验证 移出队列, 通过的激活地址, 不通过的设为未激活(report 为 true 时当作提交假地址举报)
+    fn finish_address(tx: &[u8], verify_status: &VerifyStatus, report: bool) {
+        let (_,register_account,symbol) = <TokenStatus<T>>::get(tx);
+        let num = TokenStatusLen::get();
         let (token_address, address_status, _,_) = <TokenInfo<T>>::get(register_account.clone(), symbol.clone());
         match verify_status{
             VerifyStatus::Failed => {  // 失败
                 debug::info!("--注册失败--");
                 <TokenStatus<T>>::remove(tx); // 移除掉
+                <TokenStatusPending<T>>::remove(tx);
                 debug::info!("移除 tx={:?},队列剩余:{:?} 个",hex::encode(tx.clone()),num);
                 if num > 0{
                     TokenStatusLen::mutate(|n|*n -= 1);
                 }
                 Self::insert_active_status(register_account.clone(), symbol.clone(),tx,token_address.clone(),register::AddressStatus::inActive);
+                if report {
+                    let reporter = <report::Module<T>>::get_treasury_id();
+                    if let Err(e) = <report::Module<T>>::report_misbehaviour(report::OffenceKind::FakeAddress, reporter, register_account.clone(), tx.to_vec()){
+                        debug::error!("-----fake address report failed: {:?}-----",e);
+                    }
+                }
 //                <AddressOf<T>>::mutate(register_account, |v|{
 //                    v.push((token_address,register::AddressStatus::inActive,tx.to_vec(),symbol.clone()));
 //                });
@@ -496,6 +474,7 @@ impl<T: Trait> Module<T> {
                 debug::info!("--注册成功--");
                 debug::info!("移除 tx={:?},队列剩余:{:?} 个",hex::encode(tx.clone()),num);
                 <TokenStatus<T>>::remove(tx); // 移除掉
+                <TokenStatusPending<T>>::remove(tx);
                 if num >0{
                     TokenStatusLen::mutate(|n|*n -= 1);
                 }
@@ -526,7 +505,45 @@ impl<T: Trait> Module<T> {
             }
             _ => {}
         }
-        return Ok(verify_status);
+    }
+
+    /// 处理这个区块超时的地址绑定 每个区块最多处理MAX_VERIFY_TIMEOUT_PER_BLOCK个 剩下的留到下一个区块
+    /// 返回按处理的个数算的weight
+    fn handle_timeout(block: T::BlockNumber) -> Weight {
+        let db = T::DbWeight::get();
+        let mut expiring = <TokenStatusExpiry<T>>::take(block);
+        if expiring.is_empty() {
+            return db.reads(1);
+        }
+        let mut weight = db.reads_writes(2, 1);
+        if expiring.len() > register::MAX_VERIFY_TIMEOUT_PER_BLOCK {
+            let rest = expiring.split_off(register::MAX_VERIFY_TIMEOUT_PER_BLOCK);
+            <TokenStatusExpiry<T>>::mutate(block + T::BlockNumber::from(1u32), |txs| txs.extend(rest));
+            weight = weight.saturating_add(db.reads_writes(1, 1));
+        }
+
+        let policy = <register::Module<T>>::address_verify_timeout().policy;
+        for tx in expiring {
+            // 已经有结论或是重新加入队列的跳过
+            weight = weight.saturating_add(db.reads(2));
+            let inserted = match <TokenStatusPending<T>>::get(&tx) {
+                Some((inserted, deadline)) if deadline <= block && <TokenStatus<T>>::contains_key(&tx) => inserted,
+                _ => continue,
+            };
+            debug::warn!("地址绑定 tx={:?} 验证超时", hex::encode(&tx));
+            // finish_address按绑定成功的分支算(读写最多) 再加上事件
+            weight = weight.saturating_add(db.reads_writes(7, 9));
+            match policy {
+                TimeoutPolicy::Pass => Self::finish_address(&tx, &VerifyStatus::Pass, false),
+                TimeoutPolicy::Fail => Self::finish_address(&tx, &VerifyStatus::Failed, false),
+                TimeoutPolicy::Report => {
+                    Self::finish_address(&tx, &VerifyStatus::Failed, true);
+                    weight = weight.saturating_add(<report::Module<T>>::report_misbehaviour_weight());
+                },
+            }
+            Self::deposit_event(RawEvent::AddressVerifyTimeout(tx, inserted, policy));
+        }
+        weight
     }
 
     fn insert_active_status(register_account: T::AccountId, symbol: Vec<u8>,tx:&[u8],token_address: Vec<u8>, active_status: register::AddressStatus){
@@ -575,6 +592,10 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
             Call::record_address(block_num,account_id,key,tx,status, signature) => {
                 debug::info!("############## record_address : now = {:?},block_num = {:?}##############",now,block_num);
 
+                if let Err(e) = Self::check_voter(key, account_id, tx, register::VerifyVote::from_status(*status)) {
+                    return e.into();
+                }
+
                 // check signature (this is expensive so we do it last).
                 let signature_valid = &(block_num,account_id,tx,status).using_encoded(|encoded_sign| {
                     key.verify(&encoded_sign, &signature)
@@ -588,7 +609,8 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
                 Ok(ValidTransaction {
                     priority: <T as Trait>::UnsignedPriority::get(),
                     requires: vec![],
-                    provides: vec![(block_num,account_id,tx,status).encode()],
+                    // 同一个验证人对同一个tx只能有一票(block_num 是验证人自己填的, 不能放进来)
+                    provides: vec![(b"record_address",account_id,tx).encode()],
                     longevity: TransactionLongevity::max_value(),
                     propagate: true,
                 })
@@ -596,8 +618,15 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
 
             Call::record_fail_verify(block,account,key,tx,err,signature) => {
                 debug::info!("############# record_fail_verify :block={:?},time={:?}##############",block,now);
+
+                match Self::check_voter(key, account, tx, register::VerifyVote::Error) {
+                    Err(InvalidTransaction::Stale) if Self::is_repeated_downtime(tx, account, err) => {},
+                    Err(e) => return e.into(),
+                    Ok(()) => {},
+                }
                 // check signature (this is expensive so we do it last).
-                let signature_valid = &(block,account,tx).using_encoded(|encoded_sign| {
+                // err 也要签名, 否则转发的节点可以改成本地服务没开或是签名错误来陷害验证人
+                let signature_valid = &(block,account,tx,err).using_encoded(|encoded_sign| {
                     key.verify(&encoded_sign, &signature)
                 });
                 if !signature_valid {
@@ -607,7 +636,7 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
                 Ok(ValidTransaction {
                     priority: <T as Trait>::UnsignedPriority::get(),
                     requires: vec![],
-                    provides: vec![(block,tx,err,account).encode()], // vec![(now).encode()],
+                    provides: vec![(b"record_fail_verify",account,tx).encode()],
                     longevity: TransactionLongevity::max_value(),
                     propagate: true,
                 })},
@@ -616,68 +645,101 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
     }
 }
 
-/// tests for this module
 #[cfg(test)]
 mod tests {
     use super::*;
+    use frame_support::{assert_ok, storage::migration, traits::{Get, OnInitialize, UnfilteredDispatchable}, unsigned::ValidateUnsigned};
+    use sp_core::Pair;
+    use crate::{Runtime, System, AddressValid, AccountId, BlockNumber};
+    use address_crypto::AuthorityPair;
 
-    use sp_core::H256;
-    use frame_support::{impl_outer_origin, assert_ok, parameter_types};
-    use sr_primitives::{
-        traits::{BlakeTwo256, IdentityLookup}, testing::Header, weights::Weight, Perbill,
-    };
+    const TX: &[u8] = b"0x0c8df6dfb99522d70d3247c4f56358ff23c0d810";
 
-    impl_outer_origin! {
-    pub enum Origin for Test {}
-  }
+    fn validator(i: u8) -> (AuthorityPair, AccountId) {
+        let pair = AuthorityPair::from_seed(&[i; 32]);
+        let account = pair.public().into_account32();
+        (pair, account)
+    }
 
-    // For testing the module, we construct most of a mock runtime. This means
-    // first constructing a configuration type (`Test`) which `impl`s each of the
-    // configuration traits of modules we want to use.
-    #[derive(Clone, Eq, PartialEq)]
-    pub struct Test;
-    parameter_types! {
-    pub const BlockHashCount: u64 = 250;
-    pub const MaximumBlockWeight: Weight = 1024;
-    pub const MaximumBlockLength: u32 = 2 * 1024;
-    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
-  }
-    impl system::Trait for Test {
-        type Origin = Origin;
-        type Call = ();
-        type Index = u64;
-        type BlockNumber = u64;
-        type Hash = H256;
-        type Hashing = BlakeTwo256;
-        type AccountId = u64;
-        type Lookup = IdentityLookup<Self::AccountId>;
-        type Header = Header;
-        type Event = ();
-        type BlockHashCount = BlockHashCount;
-        type MaximumBlockWeight = MaximumBlockWeight;
-        type MaximumBlockLength = MaximumBlockLength;
-        type AvailableBlockRatio = AvailableBlockRatio;
-        type Version = ();
+    /// validators 个开启了ocw验证的验证人, 一个待验证的地址绑定
+    fn new_test_ext(validators: u8) -> sp_io::TestExternalities {
+        let t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+        let mut t: sp_io::TestExternalities = t.into();
+        t.execute_with(|| {
+            System::set_block_number(1);
+            let accounts = (1..=validators).map(|i| validator(i).1).collect::<Vec<_>>();
+            for account in accounts.iter() {
+                <register::IsValidtorOcw<Runtime>>::insert(account, true);
+            }
+            migration::put_storage_value(b"Session", b"Validators", &[], accounts);
+            <TokenStatus<Runtime>>::insert(TX.to_vec(), (register::VerifyTally::default(), AccountId::from([99; 32]), b"eth".to_vec()));
+            TokenStatusLen::put(1);
+        });
+        t
     }
-    impl Trait for Test {
-        type Event = ();
+
+    fn record_address_as(signer: u8, account: AccountId, block: BlockNumber, status: u64) -> Call<Runtime> {
+        let (pair, _) = validator(signer);
+        let signature = pair.sign(&(block, &account, TX.to_vec(), status).encode());
+        Call::record_address(block, account, pair.public(), TX.to_vec(), status, signature)
+    }
+
+    fn record_fail_verify(i: u8, block: BlockNumber, err: &str) -> Call<Runtime> {
+        let (pair, account) = validator(i);
+        let signature = pair.sign(&(block, &account, TX.to_vec(), err.as_bytes().to_vec()).encode());
+        Call::record_fail_verify(block, account, pair.public(), TX.to_vec(), err.as_bytes().to_vec(), signature)
     }
-    type TemplateModule = Module<Test>;
 
-    // This function basically just builds a genesis storage key/value store according to
-    // our desired mockup.
-    fn new_test_ext() -> sp_io::TestExternalities {
-        system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+    fn validate(call: &Call<Runtime>) -> TransactionValidity {
+        <AddressValid as ValidateUnsigned>::validate_unsigned(TransactionSource::External, call)
     }
 
     #[test]
-    fn it_works_for_default_value() {
-        new_test_ext().execute_with(|| {
-            // Just a dummy test for the dummy funtion `do_something`
-            // calling the `do_something` function with a value 42
-            assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
-            // asserting that the stored value is equal to what we stored
-            assert_eq!(TemplateModule::something(), Some(42));
+    fn malicious_validator_votes_once() {
+        // 5个验证人 不通过需要3票
+        new_test_ext(5).execute_with(|| {
+            let (_, account) = validator(1);
+            let call = record_address_as(1, account.clone(), 1, 4001);
+            assert_ok!(validate(&call));
+            assert_ok!(call.dispatch_bypass_filter(system::RawOrigin::None.into()));
+            for block in 2..10 {
+                assert_eq!(validate(&record_address_as(1, account.clone(), block, 4001)), InvalidTransaction::Stale.into());
+            }
+            // 替别的验证人投票
+            assert_eq!(validate(&record_address_as(1, validator(2).1, 1, 4001)), InvalidTransaction::BadProof.into());
+
+            let (tally, _, _) = <TokenStatus<Runtime>>::get(TX);
+            assert_eq!(tally.fails, vec![account]);
+            assert_eq!(TokenStatusLen::get(), 1);
         });
     }
+
+    #[test]
+    fn failed_verify_error_is_signed() {
+        new_test_ext(3).execute_with(|| {
+            let forged = match record_fail_verify(1, 1, "timeout") {
+                Call::record_fail_verify(block, account, key, tx, _, signature) =>
+                    Call::record_fail_verify(block, account, key, tx, INVALID_RESPONSE_SIGNATURE.as_bytes().to_vec(), signature),
+                _ => unreachable!(),
+            };
+            assert_eq!(validate(&forged), InvalidTransaction::BadProof.into());
+            assert_ok!(validate(&record_fail_verify(1, 1, INVALID_RESPONSE_SIGNATURE)));
+        });
+    }
+
+    #[test]
+    fn timeout_weight_counts_processed_items() {
+        new_test_ext(3).execute_with(|| {
+            let db = <Runtime as system::Trait>::DbWeight::get();
+            <TokenStatusPending<Runtime>>::insert(TX.to_vec(), (1, 11));
+            // 第二个已经有结论了 只是读一下就跳过
+            <TokenStatusExpiry<Runtime>>::insert(11, vec![TX.to_vec(), b"decided".to_vec()]);
+            assert_eq!(<AddressValid as OnInitialize<BlockNumber>>::on_initialize(10), db.reads(1));
+
+            assert_eq!(<AddressValid as OnInitialize<BlockNumber>>::on_initialize(11), db.reads_writes(2, 1) + db.reads(4) + db.reads_writes(7, 9));
+            assert!(!<TokenStatus<Runtime>>::contains_key(TX));
+            assert_eq!(TokenStatusLen::get(), 0);
+        });
+    }
+
 }
@@ -17,10 +17,10 @@
 
 //! Some configurable implementations as associated type for the substrate runtime.
 
-use node_primitives::Balance;
+use node_primitives::{AccountId, Balance};
 use sp_runtime::traits::Convert;
-use frame_support::traits::{OnUnbalanced, Currency};
-use crate::{Balances, Authorship, NegativeImbalance};
+use frame_support::traits::{OnUnbalanced, Currency, IsStaker};
+use crate::{Balances, Authorship, Staking, NegativeImbalance};
 
 pub struct Author;
 impl OnUnbalanced<NegativeImbalance> for Author {
@@ -29,6 +29,14 @@ impl OnUnbalanced<NegativeImbalance> for Author {
 	}
 }
 
+/// staking里面已经绑定的stash账户
+pub struct StakingStakers;
+impl IsStaker<AccountId> for StakingStakers {
+	fn is_staker(who: &AccountId) -> bool {
+		Staking::bonded(who).is_some()
+	}
+}
+
 /// Struct that handles the conversion of Balance -> `u64`. This is used for staking's election
 /// calculation.
 pub struct CurrencyToVoteHandler;
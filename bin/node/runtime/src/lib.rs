@@ -92,6 +92,7 @@ pub mod report;
 pub mod address_valid;
 pub mod tx_valid;
 pub mod offchain_common;
+pub mod chain_verifier;
 
 // Make the WASM binary available.
 #[cfg(feature = "std")]
@@ -114,10 +115,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// and set impl_version to 0. If only runtime
 	// implementation changes and behavior does not, then leave spec_version as
 	// is and increment impl_version.
-	spec_version: 259,
+	spec_version: 260,
 	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 2,
 };
 
 /// Native version.
@@ -213,6 +214,7 @@ impl register::Trait for Runtime {
 	type TxsMaxCount = TxsMaxCount;
 	type UnBondTime = UnBondTime;
 	type RegisterSetOrigin = pallet_collective::EnsureProportionAtLeast<_1, _2, AccountId, TechnicalCollective>;
+	type TreasuryId = TreasuryModuleId;
 	}
 
 parameter_types! {
@@ -352,13 +354,16 @@ impl pallet_nicks::Trait for Runtime{
 impl report::Trait for Runtime {
 	type ConcilMembers = Council;
 	type ConcilCount = Council;
-	type ShouldAddOrigin = ();
-	type ShouldSubOrigin = ();
 	type ConcilOrigin = pallet_collective::EnsureMember<AccountId, CouncilCollective>;
 	type Event = Event;
 	type DeadOrigin = Balances;
 	type ReportSlash = Treasury;
 	type ReportSetOrigin = pallet_collective::EnsureProportionAtLeast<_1, _2, AccountId, TechnicalCollective>;
+	type AppealOrigin = pallet_collective::EnsureProportionAtLeast<_2, _3, AccountId, TechnicalCollective>;
+	type JudgementPolicy = report::SimpleMajority;
+	type Stakers = impls::StakingStakers;
+	type FastTrackOrigin = pallet_collective::EnsureProportionAtLeast<_1, _2, AccountId, CouncilCollective>;
+	type ReportOffence = Offences;
 
 }
 
@@ -822,6 +827,7 @@ parameter_types! {
 impl mine::Trait for Runtime {
 
 	type ReportedTxs = Report;
+	type SuspiciousTxs = Report;
 
 	type TechMmebersOrigin = TechnicalCommittee; // 获取所有技术委员会成员
 
@@ -1344,6 +1350,61 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl node_mine_rpc_runtime_api::MineApi<
+		Block,
+		AccountId,
+		BlockNumber,
+		Balance,
+		Moment,
+		mine::OwnerMineRecordItem<Runtime>,
+	> for Runtime {
+		fn day_power_leaderboard(day: u32) -> Vec<(AccountId, u64)> {
+			Mine::day_power_leaderboard(day)
+		}
+
+		fn day_reward_leaderboard(day: u32) -> Vec<(AccountId, Balance)> {
+			Mine::day_reward_leaderboard(day)
+		}
+
+		fn miner_statistics(who: AccountId) -> node_mine_rpc_runtime_api::MinerStatistics<BlockNumber, Balance> {
+			Mine::miner_statistics(who)
+		}
+
+		fn archive_duration() -> BlockNumber {
+			ArchiveDuration::get()
+		}
+
+		fn timestamp() -> Moment {
+			Timestamp::now()
+		}
+
+		fn miner_day_records(who: AccountId, day: BlockNumber) -> Vec<(Moment, mine::OwnerMineRecordItem<Runtime>)> {
+			Mine::miner_day_records(who, day)
+		}
+	}
+
+	impl node_report_rpc_runtime_api::ReportApi<Block, AccountId, BlockNumber, Balance> for Runtime {
+		fn reporter_reputation(who: AccountId) -> node_report_rpc_runtime_api::ReporterReputation<Balance> {
+			Report::reporter_reputation(who)
+		}
+
+		fn open_reports() -> Vec<node_report_rpc_runtime_api::ReportSummary<AccountId, BlockNumber>> {
+			Report::open_reports()
+		}
+
+		fn report_tally(report_id: u32) -> Option<node_report_rpc_runtime_api::ReportTally<AccountId, Balance>> {
+			Report::report_tally(report_id)
+		}
+
+		fn reports_of(who: AccountId) -> Vec<node_report_rpc_runtime_api::ReportSummary<AccountId, BlockNumber>> {
+			Report::reports_of(who)
+		}
+
+		fn punishment_history(who: AccountId) -> Vec<node_report_rpc_runtime_api::PunishmentRecord<AccountId, BlockNumber, Balance>> {
+			Report::punishment_history(who)
+		}
+	}
+
 	impl sp_session::SessionKeys<Block> for Runtime {
 		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
 			SessionKeys::generate(seed)
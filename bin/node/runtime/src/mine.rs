@@ -1,7 +1,7 @@
 //! ## Genesis config
 use frame_support::{debug,decl_storage, decl_module,decl_event, decl_error, StorageValue, StorageMap,Parameter, IterableStorageMap,
 			   weights::{Weight},Blake2_256, ensure,dispatch::Vec,traits::Currency, StorageDoubleMap, IterableStorageDoubleMap};
-use frame_support::traits::{Get, ReservableCurrency, OnUnbalanced, GetMembers, ReportedTxs, EnsureOrigin};
+use frame_support::traits::{Get, ReservableCurrency, OnUnbalanced, GetMembers, ReportedTxs, SuspiciousTxs, EnsureOrigin, ExistenceRequirement};
 use frame_system as system;
 use system::{ensure_signed, ensure_root};
 use pallet_balances as balances;
@@ -10,7 +10,8 @@ use sp_runtime::traits::{Hash, AtLeast32Bit, Bounded, One, Member, CheckedAdd, C
 use sp_runtime::{Permill, ModuleId, DispatchResult, DispatchError, Percent};
 use codec::{Encode, Decode};
 use crate::mine_linked::{PersonMineWorkForce,PersonMine,MineParm,PersonMineRecord, MineTag};
-use crate::register::{self,MinersCount,AllMiners, TokenInfo, AddressOf, Trait as RegisterTrait, AddressStatus};
+use crate::register::{self,MinersCount,AllMiners, TokenInfo, AddressOf, Trait as RegisterTrait, AddressStatus, VerifyTally};
+use frame_support::storage::{migration, StoragePrefixedMap};
 use crate::mine_power::{PowerInfo, MinerPowerInfo, TokenPowerInfo, PowerInfoStore, MinerPowerInfoStore, TokenPowerInfoStore};
 use node_primitives::{Count, USD, Balance};
 use sp_std::{result, collections::btree_set::BTreeSet};
@@ -19,11 +20,15 @@ use pallet_timestamp as timestamp;
 use crate::constants::{symbol::{USDT, BTC, EOS, ETH, ECAP}, currency::*, genesis_params::*, time::*};
 
 use crate::report::{self, VoteRewardPeriodEnum, BeingReportedTxsOf};
+pub use node_mine_rpc_runtime_api::MinerStatistics;
 use crate::constants::{time::{MINUTES, DAYS, HOURS}, genesis_params::*};
 use sp_std::prelude::*;
 
 const MODULE_ID: ModuleId = ModuleId(*b"py/trsry");
 
+// 矿池账号 挖矿奖励都从这里转出
+const MINE_POT_ID: ModuleId = ModuleId(*b"tx/minep");
+
 // 算力相对于金额或是次数的倍数（为了让计算更加精确）
 // 具体的算力数值大概也是金额与次数的Multiple倍
 pub const Multiple: u64 = 1_0000;
@@ -48,6 +53,7 @@ type MinerPowerInfoItem<T> = MinerPowerInfo<<T as system::Trait>::AccountId, <T
 type PowerInfoStoreItem<T> = PowerInfoStore<PowerInfoList<T>, <T as system::Trait>::BlockNumber>;
 type TokenPowerInfoStoreItem<T> = TokenPowerInfoStore<TokenPowerInfoList<T>, <T as system::Trait>::BlockNumber>;
 type MinerPowerInfoStoreItem<T> = MinerPowerInfoStore<MinerPowerInfoDict<T>, <T as system::Trait>::AccountId, <T as system::Trait>::BlockNumber>;
+pub type MinerStatisticsItem<T> = MinerStatistics<<T as system::Trait>::BlockNumber, BalanceOf<T>>;
 
 
 #[cfg_attr(feature = "std", derive())]
@@ -120,13 +126,54 @@ pub enum MR {
 }
 
 
+/// 对敲检测的相关参数
+#[cfg_attr(feature = "std", derive())]
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub enum WashTradeParam<BlockNumber> {
+	// 检测窗口的长度
+	Window(BlockNumber),
+	// 同一地址对第n次挖矿的算力倍数
+	Multipliers(Vec<Permill>),
+	// 同一地址对挖矿多少次判定为可疑
+	ReportThreshold(u32),
+}
+
+
+/// 暂停挖矿的范围
+#[cfg_attr(feature = "std", derive())]
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub enum MinePauseTarget {
+	// 全网暂停
+	Global,
+	// 暂停某个币种
+	Symbol(Vec<u8>),
+	// 暂停某种挖矿类型(客户端或是钱包)
+	Tag(MineTag),
+}
+
+
+/// 熔断的相关参数
+#[cfg_attr(feature = "std", derive())]
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub enum CircuitBreakerParam {
+	// 当天的次数或是算力超过前一天的多少倍就熔断(0表示关闭熔断)
+	Multiple(u32),
+	// 当天的挖矿次数达到这个数才开始检查
+	MinCount(u64),
+}
+
+
 // 继承 register 模块,方便调用register里面的 store
 pub trait Trait: balances::Trait + RegisterTrait {
 
 	type ReportedTxs: ReportedTxs<Self::AccountId>;
 
+	// 发现可疑挖矿时通知举报模块
+	type SuspiciousTxs: SuspiciousTxs<Self::AccountId, MineTag>;
+
 	type TechMmebersOrigin: GetMembers<Self::AccountId>;
 
+	// 每天增发进矿池的挖矿奖励
 	type ShouldAddOrigin: OnUnbalanced<PositiveImbalanceOf<Self>>;
 
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
@@ -186,6 +233,7 @@ decl_event!(
         <T as system::Trait>::AccountId,
         <T as system::Trait>::Hash,
 		<T as system::Trait>::BlockNumber,
+		Balance = BalanceOf<T>,
     {
         Created(AccountId, Hash),
         Mined(AccountId,u64),  // 挖矿成功的事件
@@ -202,6 +250,25 @@ decl_event!(
         SetTLC,
         SetTLA,
         SetMR,
+        SetWashTradeParam,
+        SetLeaderboardSize,
+        SetCircuitBreakerParam,
+        // 矿池已注入本周期的预算(周期, 金额)
+        MinePotFunded(BlockNumber, Balance),
+        // 矿池余额不足 由国库转账发放的奖励(账号, 金额)
+        RewardShortfall(AccountId, Balance),
+        // 矿池与国库都不够 没有发放的奖励(账号, 金额)
+        RewardUnpaid(AccountId, Balance),
+        // 暂停挖矿
+        MinePaused(MinePauseTarget),
+        // 恢复挖矿
+        MineResumed(MinePauseTarget),
+        // 币种触发熔断(币种, 当天挖矿次数, 前一天挖矿次数, 当天算力, 前一天算力)
+        CircuitBreakerTripped(Vec<u8>, u64, u64, u64, u64),
+        // 排行榜已生成(归档周期编号, 上榜人数)
+        LeaderboardArchived(u32, u32),
+        // 可疑的对敲挖矿(矿工, tx, 窗口内该地址对的挖矿次数)
+        SuspiciousPattern(AccountId, Vec<u8>, u32),
 
     }
 );
@@ -212,12 +279,18 @@ decl_storage! {
     	/// tx与minetype作为key对应的挖矿记录
     	pub OwnerMineRecord get(fn mine_record): double_map hasher(blake2_128_concat) Vec<u8>, hasher(blake2_128_concat) MineTag => Option<OwnerMineRecordItem<T>>;
 
-        /// 设置状态位  u32表现形式1xxx.初始值为 1000,低3位分别表示 验证通过次数,验证失败次数,非正常情况返回次数, tx => 1xxx,AccountId, symbol
-		pub TxVerifyMap get(fn tx_verify_map): map hasher(blake2_128_concat) (Vec<u8>,MineTag) => u64;
+		/// 待验证的挖矿tx (tx, MineTag) => 投票情况
+		pub TxVerifyMap get(fn tx_verify_map): map hasher(blake2_128_concat) (Vec<u8>,MineTag) => VerifyTally<T::AccountId>;
 
 		/// 记录 TxVerifyMap 的长度
 		pub LenOfTxVerify : u32;
 
+		/// 待验证的挖矿tx什么时候加入队列, 什么时候超时 (tx, MineTag) => (加入队列的区块, 超时的区块)
+		pub TxVerifyPending get(fn tx_verify_pending): map hasher(blake2_128_concat) (Vec<u8>,MineTag) => Option<(T::BlockNumber, T::BlockNumber)>;
+
+		/// 挖矿tx在哪个区块超时 (区块高度 => (tx, MineTag))
+		pub TxVerifyExpiry get(fn tx_verify_expiry): map hasher(twox_64_concat) T::BlockNumber => Vec<(Vec<u8>,MineTag)>;
+
     	/// 个人挖矿数据每天汇总
     	OwnedDayWorkForce get(fn person_workforce): map  hasher(blake2_128_concat) (T::AccountId,BlockNumberOf<T>) => Option<OwnerMineWorkForce<T>>;
 
@@ -297,6 +370,70 @@ decl_storage! {
 		/// ecap单次转账的金额硬顶
 		MLAecap get(fn mla_ecap): USD = 5000 * 2 * USDT_DECIMALS * Multiple;
 
+		/// 对敲检测: 检测窗口内 (symbol, 地址, 地址) 之间的挖矿次数 (地址排过序, 来回转账算同一对)
+		PairMineCount get(fn pair_mine_count): double_map hasher(twox_64_concat) T::BlockNumber, hasher(blake2_128_concat) (Vec<u8>, Vec<u8>, Vec<u8>) => u32;
+
+		/// 有记录的对敲检测窗口编号(归档时清理过期的窗口)
+		PairWindows get(fn pair_windows): Vec<T::BlockNumber>;
+
+		/// 对敲检测窗口的长度
+		WashTradeWindow get(fn wash_trade_window): T::BlockNumber = T::BlockNumber::from(1 * DAYS);
+
+		/// 同一地址对第n次挖矿的算力倍数(超过长度的用最后一个)
+		WashTradeMultipliers get(fn wash_trade_multipliers): Vec<Permill> = vec![
+			Permill::from_percent(100), Permill::from_percent(50), Permill::from_percent(25), Permill::from_percent(10)];
+
+		/// 同一地址对在窗口内挖矿达到这个次数就判定为可疑
+		WashTradeReportThreshold get(fn wash_trade_report_threshold): u32 = 5;
+
+		/// 每个归档周期的算力排行榜(从大到小, 只保留前LeaderboardSize名)  key是归档周期编号(区块高度/归档周期)
+		pub DayPowerLeaderboard get(fn day_power_leaderboard): map hasher(twox_64_concat) u32 => Vec<(T::AccountId, u64)>;
+
+		/// 每个归档周期的奖励排行榜(从大到小, 只保留前LeaderboardSize名)
+		pub DayRewardLeaderboard get(fn day_reward_leaderboard): map hasher(twox_64_concat) u32 => Vec<(T::AccountId, BalanceOf<T>)>;
+
+		/// 本周期的算力排行榜(挖矿时更新, 从大到小, 最多LeaderboardSize名)  归档时存到DayPowerLeaderboard
+		pub CurrentPowerLeaderboard get(fn current_power_leaderboard): Vec<(T::AccountId, u64)>;
+
+		/// 本周期的奖励排行榜(挖矿时更新, 从大到小, 最多LeaderboardSize名)  归档时存到DayRewardLeaderboard
+		pub CurrentRewardLeaderboard get(fn current_reward_leaderboard): Vec<(T::AccountId, BalanceOf<T>)>;
+
+		/// 存储的版本(用于升级时迁移数据)
+		pub StorageVersion get(fn storage_version): u32;
+
+		/// 排行榜的上榜人数
+		pub LeaderboardSize get(fn leaderboard_size): u32 = 100;
+
+		/// 矿工本周期获得的挖矿奖励(归档时清掉)
+		ThisArchiveDurationMinerReward get(fn this_duration_miner_reward): map hasher(blake2_128_concat) T::AccountId => BalanceOf<T>;
+
+		/// 本周期ThisArchiveDurationMinerReward的条数(用来计算归档时清掉它的weight)
+		ThisArchiveDurationMinerCount get(fn this_duration_miner_count): u32;
+
+		/// 矿工的历史挖矿统计(不会被删除)
+		pub MinerLifetimeStatistics get(fn miner_statistics): map hasher(blake2_128_concat) T::AccountId => MinerStatisticsItem<T>;
+
+		/// 被暂停挖矿的范围(全网、币种、挖矿类型)
+		pub MinePausedOf get(fn mine_paused): map hasher(blake2_128_concat) MinePauseTarget => bool;
+
+		/// 熔断倍数: 币种当天的挖矿次数或是算力超过前一天的多少倍就自动暂停该币种(0表示关闭)
+		pub CircuitBreakerMultiple get(fn circuit_breaker_multiple): u32 = 5;
+
+		/// 币种当天挖矿次数达到这个数才做熔断检查(样本太少不检查)
+		pub CircuitBreakerMinCount get(fn circuit_breaker_min_count): u64 = 100;
+
+		/// 本周期的挖矿预算(矿池在周期开始时注入)
+		pub MineBudget get(fn mine_budget): BalanceOf<T>;
+
+		/// 矿池最近一次注入预算的周期
+		pub MinePotFundedDay get(fn mine_pot_funded_day): Option<T::BlockNumber>;
+
+		/// 矿池余额不足时累计由国库转账发放的奖励
+		pub RewardShortfallTotal get(fn reward_shortfall_total): BalanceOf<T>;
+
+		/// 矿池与国库都不够时累计没有发放的奖励
+		pub RewardUnpaidTotal get(fn reward_unpaid_total): BalanceOf<T>;
+
 		/// 本周期的奖励总金额
 		ThisArchiveDurationTotalReward get(fn this_duration_reward): BalanceOf<T>;
 
@@ -452,6 +589,9 @@ decl_error! {
 		/// 输入了空值
 		EmptyParam,
 
+		/// 挖矿已暂停
+		MinePaused,
+
 	}
 }
 
@@ -644,6 +784,83 @@ decl_module! {
 		}
 
 
+		/// 暂停挖矿(全网、币种或是挖矿类型)
+		#[weight = 50_000]
+		fn pause_mine(origin, target: MinePauseTarget) -> DispatchResult {
+			T::MineSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			<MinePausedOf>::insert(target.clone(), true);
+			Self::deposit_event(RawEvent::MinePaused(target));
+			Ok(())
+		}
+
+
+		/// 恢复挖矿(熔断暂停的币种也用这个方法恢复)
+		#[weight = 50_000]
+		fn resume_mine(origin, target: MinePauseTarget) -> DispatchResult {
+			T::MineSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			<MinePausedOf>::remove(target.clone());
+			Self::deposit_event(RawEvent::MineResumed(target));
+			Ok(())
+		}
+
+
+		/// 设置熔断参数
+		#[weight = 50_000]
+		fn set_circuit_breaker_param(origin, param: CircuitBreakerParam) -> DispatchResult {
+			T::MineSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			match param {
+				CircuitBreakerParam::Multiple(x) => <CircuitBreakerMultiple>::put(x),
+				CircuitBreakerParam::MinCount(x) => <CircuitBreakerMinCount>::put(x),
+			}
+			Self::deposit_event(RawEvent::SetCircuitBreakerParam);
+			Ok(())
+		}
+
+
+		/// 设置排行榜的上榜人数
+		#[weight = 50_000]
+		fn set_leaderboard_size(origin, size: u32) -> DispatchResult {
+			T::MineSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			ensure!(size != 0, Error::<T>::BoundError);
+			<LeaderboardSize>::put(size);
+			Self::deposit_event(RawEvent::SetLeaderboardSize);
+			Ok(())
+		}
+
+
+		/// 设置对敲检测的参数
+		#[weight = 50_000]
+		fn set_wash_trade_param(origin, param: WashTradeParam<T::BlockNumber>) -> DispatchResult {
+			T::MineSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			match param {
+				WashTradeParam::Window(x) => {
+					ensure!(!x.is_zero(), Error::<T>::BoundError);
+					<WashTradeWindow<T>>::put(x);
+				},
+				WashTradeParam::Multipliers(x) => {
+					ensure!(x.len() != 0, Error::<T>::EmptyParam);
+					<WashTradeMultipliers>::put(x);
+				},
+				WashTradeParam::ReportThreshold(x) => {
+					ensure!(x != 0, Error::<T>::BoundError);
+					<WashTradeReportThreshold>::put(x);
+				},
+			}
+			Self::deposit_event(RawEvent::SetWashTradeParam);
+			Ok(())
+		}
+
+
 		/// 挖矿
 		#[weight = 50_000]
         pub fn create_mine(
@@ -654,6 +871,9 @@ decl_module! {
 			{debug::info!("开始挖矿!")}
         	let sender = ensure_signed(origin)?;
 
+        	// 挖矿被暂停(全网、币种或是挖矿类型)
+        	ensure!(!Self::is_mine_paused(&symbol, &mine_tag), Error::<T>::MinePaused);
+
         	// 如果自己在举报队列 则不能挖矿
         	ensure!(!(T::ReportedTxs::is_reported(sender.clone())),  Error::<T>::BeingReported);
 
@@ -662,7 +882,7 @@ decl_module! {
         	ensure!(address.clone() != to_address.clone(),Error::<T>::TransferToYourself);
         	ensure!(usdt_nums <= u64::max_value(),Error::<T>::Overfolw);  // 这个不可能溢出的
         	ensure!(usdt_nums >= 5 * USDT_DECIMALS, Error::<T>::AmountTooLow);  // 前端需要乘于100
-        	ensure!(!<TxVerifyMap>::contains_key(&(tx.clone(),mine_tag.clone())), Error::<T>::InUsingTxAndMinetype);
+        	ensure!(!<TxVerifyMap<T>>::contains_key(&(tx.clone(),mine_tag.clone())), Error::<T>::InUsingTxAndMinetype);
 			{debug::info!("挖矿金额足够！")}
 
         	// 删除过期的交易tx（为了减轻存储负担）
@@ -773,6 +993,21 @@ decl_module! {
         }
 
 
+		fn on_runtime_upgrade() -> Weight {
+			Self::migrate()
+		}
+
+
+		fn on_initialize(block_number: T::BlockNumber) -> Weight {
+			// 归档在on_finalize里做, 这里先把它的weight算上
+			if (block_number % T::ArchiveDuration::get()).is_zero() {
+				Self::archive_leaderboard_weight()
+			} else {
+				0
+			}
+		}
+
+
 		fn on_finalize(block_number: T::BlockNumber) {
 
             if (block_number % T::ArchiveDuration::get()).is_zero() {
@@ -785,6 +1020,59 @@ decl_module! {
 
 impl<T: Trait> Module<T> {
 
+	/// 返回迁移用掉的weight(按读写的次数算)
+	fn migrate() -> Weight {
+		let version = <StorageVersion>::get();
+		let mut weight = T::DbWeight::get().reads(1);
+		if version < 1 {
+			weight = weight.saturating_add(Self::migrate_v1());
+		}
+		if version < 2 {
+			weight = weight.saturating_add(Self::migrate_v2());
+		}
+		weight
+	}
+
+
+	/// 旧的TxVerifyMap是1000开始的计数 改成VerifyTally
+	/// 旧的计数不知道是哪个验证人投的 迁移后重新投票. 投不通过的验证人记在VerifyTally里
+	fn migrate_v1() -> Weight {
+		let translated = sp_std::cell::Cell::new(0u64);
+		<TxVerifyMap<T>>::translate::<u64, _>(|_key, status| {
+			translated.set(translated.get() + 1);
+			if status < 1000 {
+				return None;
+			}
+			Some(VerifyTally::default())
+		});
+		LenOfTxVerify::put(<TxVerifyMap<T>>::iter().count() as u32);
+		<StorageVersion>::put(1);
+		// 转换时每条读写1次 重新计数时每条再读1次 另外写LenOfTxVerify与StorageVersion
+		T::DbWeight::get().reads_writes(2 * translated.get(), translated.get() + 2)
+	}
+
+
+	/// 以前的挖矿tx没有超时 从升级的区块开始算
+	fn migrate_v2() -> Weight {
+		let now = <system::Module<T>>::block_number();
+		let mut count = 0u64;
+		for (key, _) in <TxVerifyMap<T>>::iter() {
+			count += 1;
+			Self::schedule_tx_timeout(key, now);
+		}
+		<StorageVersion>::put(2);
+		T::DbWeight::get().reads_writes(3 * count + 2, 2 * count + 1)
+	}
+
+
+	/// 记下挖矿tx加入队列的区块和超时的区块
+	fn schedule_tx_timeout(key: (Vec<u8>,MineTag), now: T::BlockNumber) {
+		let deadline = now.saturating_add(<register::TxVerifyTimeout>::get().blocks.into());
+		<TxVerifyPending<T>>::insert(&key, (now, deadline));
+		<TxVerifyExpiry<T>>::mutate(deadline, |keys| keys.push(key));
+	}
+
+
 	fn check_amount(amount: Vec<u8>) -> bool{
 		let mut all = true;
 		let len = amount.len() as u32;
@@ -847,6 +1135,12 @@ impl<T: Trait> Module<T> {
 		// 获取昨天的总次数算力
 		let prev_total_count = workforce.3;
 
+		// 对敲检测: 同一地址对在窗口内重复挖矿, 算力按倍数递减
+		let (pair_window, pair_key, pair_count) = Self::pair_mine_info(&mine_parm, block_num)?;
+		let wash_multiplier = Self::wash_trade_multiplier(pair_count);
+		amount_workforce = wash_multiplier * amount_workforce;
+		count_workforce = wash_multiplier * count_workforce;
+
 		// 计算总算力占比（这个占比是乘于精度过的）
 		//  结果： 真实算力占比*100亿
 		let workforce_ratio = Self::calculate_workforce_ratio(
@@ -864,7 +1158,9 @@ impl<T: Trait> Module<T> {
 			None => return Err(Error::<T>::MineCountTooMore)?,
 		};
 
-		let today_reward = Self::per_day_mine_reward_token()?;
+		// 本周期第一次挖矿时给矿池注入预算
+		Self::fund_mine_pot(now_day)?;
+		let today_reward = <MineBudget<T>>::get();
 
 		// 计算这一次的总挖矿奖励
 		let thistime_reward = today_reward * workforce_ratio_change_into_balance/decimal;
@@ -887,6 +1183,9 @@ impl<T: Trait> Module<T> {
 			count_workforce, mine_parm.usdt_nums.clone(),
 			amount_workforce, block_num);
 
+		// 熔断检查(触发后该币种后面的挖矿会被拒绝)
+		Self::check_circuit_breaker(symbol, block_num);
+
 		// 矿工个人算力存储
 		let curr_point = Self::miner_power_info_point().1;
 		<MinerPowerInfoStoreItem<T>>::add_miner_power(
@@ -913,8 +1212,12 @@ impl<T: Trait> Module<T> {
 			println!("-----------OwnedMineIndex:{:?}------------",new_owned_mineindex);
 		}
 		// tx 验证初始化
-		<TxVerifyMap>::insert(&(mine_parm.tx,mine_tag),1000);
+		<TxVerifyMap<T>>::insert(&(mine_parm.tx.clone(),mine_tag.clone()),VerifyTally::default());
 		LenOfTxVerify::mutate(|n|*n += 1);
+		Self::schedule_tx_timeout((mine_parm.tx.clone(),mine_tag.clone()), block_num);
+
+		// 记录地址对的挖矿次数
+		Self::add_pair_mine_count(sender.clone(), &mine_parm, pair_window, pair_key, pair_count);
 
 		// 奖励统计
 		<ThisArchiveDurationTotalReward<T>>::mutate(|a| *a += thistime_reward.clone());
@@ -924,6 +1227,14 @@ impl<T: Trait> Module<T> {
 		// 把矿工添加到集合中
 		<LastTimeMiners<T>>::mutate(|h| h.insert(sender.clone()));
 
+		// 排行榜与历史统计
+		if !<ThisArchiveDurationMinerReward<T>>::contains_key(&sender) {
+			ThisArchiveDurationMinerCount::mutate(|n| *n = n.saturating_add(1));
+		}
+		<ThisArchiveDurationMinerReward<T>>::mutate(sender.clone(), |h| *h = h.saturating_add(per_one_reward.0));
+		Self::add_miner_statistics(sender.clone(), now_day, mine_parm.usdt_nums, per_one_reward.0);
+		Self::update_leaderboard(&sender, block_num);
+
 		Self::deposit_event(RawEvent::Mined(sender, new_owned_mineindex));
 
 		Ok(())
@@ -955,12 +1266,18 @@ impl<T: Trait> Module<T> {
 		<PowerInfoStoreItem<T>>::archive(block_number.clone()).unwrap();
 		Self::deposit_event(RawEvent::PowerInfoArchived(block_number.clone()));
 
+		// 生成本周期的排行榜(要在清掉本周期矿工名单之前)
+		Self::archive_leaderboard(block_number);
+
 		// 初始化昨天的挖矿算力
 		Self::init_yesterday_total_power(block_number);
 
 		<TokenPowerInfoStoreItem<T>>::archive(block_number.clone()).unwrap();
 		Self::deposit_event(RawEvent::TokenPowerInfoArchived(block_number.clone()));
 
+		// 清理过期的对敲检测窗口
+		Self::remove_expire_pair_windows(block_number);
+
 		// 对矿工的挖矿信息进行归档
 		let (prev_point, curr_point) = Self::miner_power_info_point();
 		if curr_point == 0 {
@@ -1061,6 +1378,147 @@ impl<T: Trait> Module<T> {
 	}
 
 
+	/// 挖矿是否被暂停
+	pub fn is_mine_paused(symbol: &Vec<u8>, mine_tag: &MineTag) -> bool {
+		<MinePausedOf>::get(MinePauseTarget::Global)
+			|| <MinePausedOf>::get(MinePauseTarget::Symbol(symbol.clone()))
+			|| <MinePausedOf>::get(MinePauseTarget::Tag(mine_tag.clone()))
+	}
+
+
+	/// 币种当天的(挖矿次数, 算力)
+	fn token_count_and_power(info: &TokenPowerInfoItem<T>, symbol: &'static str) -> (u64, u64) {
+		match symbol {
+			BTC => (info.btc_total_count, info.btc_total_power),
+			ETH => (info.eth_total_count, info.eth_total_power),
+			USDT => (info.usdt_total_count, info.usdt_total_power),
+			EOS => (info.eos_total_count, info.eos_total_power),
+			ECAP => (info.ecap_total_count, info.ecap_total_power),
+			_ => (0u64, 0u64),
+		}
+	}
+
+
+	/// 熔断检查: 币种当天的挖矿次数或是算力(奖励占比)超过前一天的倍数, 就暂停该币种的挖矿
+	fn check_circuit_breaker(symbol: &'static str, block_number: T::BlockNumber) {
+		let multiple = <CircuitBreakerMultiple>::get() as u64;
+		if multiple == 0 {
+			return;
+		}
+
+		let (today_count, today_power) = Self::token_count_and_power(
+			&<TokenPowerInfoStoreItem<T>>::get_curr_token_power(block_number), symbol);
+		let (prev_count, prev_power) = Self::token_count_and_power(
+			&<TokenPowerInfoStoreItem<T>>::get_prev_token_power(block_number), symbol);
+
+		// 样本太少或是没有前一天的数据 不做检查
+		if today_count < <CircuitBreakerMinCount>::get() || prev_count == 0 {
+			return;
+		}
+
+		if today_count > prev_count.saturating_mul(multiple) || today_power > prev_power.saturating_mul(multiple) {
+			let target = MinePauseTarget::Symbol(symbol.as_bytes().to_vec());
+			if <MinePausedOf>::get(&target) {
+				return;
+			}
+			<MinePausedOf>::insert(&target, true);
+			Self::deposit_event(RawEvent::CircuitBreakerTripped(
+				symbol.as_bytes().to_vec(), today_count, prev_count, today_power, prev_power));
+		}
+	}
+
+
+	/// 矿工某一天还在链上的挖矿记录(挖矿时间, 记录)  提供给runtime api
+	pub fn miner_day_records(who: T::AccountId, day: T::BlockNumber) -> Vec<(T::Moment, OwnerMineRecordItem<T>)> {
+		let mut records = vec![];
+		for tx in <MinerAllDaysTx<T>>::get(who.clone(), day).iter() {
+			for mine_tag in [MineTag::WALLET, MineTag::CLIENT].iter() {
+				if let Some(record) = <OwnerMineRecord<T>>::get(tx.clone(), mine_tag.clone()) {
+					// 同一笔tx的另一种挖矿有可能是别人挖的
+					if record.miner_address == who {
+						records.push((record.timestamp.clone(), record));
+					}
+				}
+			}
+		}
+		records.sort_by(|a, b| a.0.cmp(&b.0));
+		records
+	}
+
+
+	/// 把本周期的算力排行榜与奖励排行榜存档
+	fn archive_leaderboard(block_number: T::BlockNumber) {
+		let day: u32 = match (block_number / T::ArchiveDuration::get()).try_into().ok() {
+			Some(d) => d,
+			None => return,
+		};
+		let size = <LeaderboardSize>::get() as usize;
+
+		let mut power_board = <CurrentPowerLeaderboard<T>>::take();
+		let mut reward_board = <CurrentRewardLeaderboard<T>>::take();
+		// 周期内上榜人数可能被调小了
+		power_board.truncate(size);
+		reward_board.truncate(size);
+		<ThisArchiveDurationMinerReward<T>>::remove_all();
+		ThisArchiveDurationMinerCount::kill();
+
+		let len = power_board.len() as u32;
+		<DayPowerLeaderboard<T>>::insert(day, power_board);
+		<DayRewardLeaderboard<T>>::insert(day, reward_board);
+		Self::deposit_event(RawEvent::LeaderboardArchived(day, len));
+	}
+
+
+	/// 排行榜存档的weight(排行榜在挖矿时已经排好, 另外每个本周期挖过矿的矿工要删除一条奖励统计)
+	fn archive_leaderboard_weight() -> Weight {
+		let miners = ThisArchiveDurationMinerCount::get() as u64;
+		T::DbWeight::get().reads_writes(4, 7 + miners)
+	}
+
+
+	/// 挖矿后更新本周期的排行榜(只保留前LeaderboardSize名, 每次挖矿的计算量有上限)
+	fn update_leaderboard(who: &T::AccountId, block_number: T::BlockNumber) {
+		let size = <LeaderboardSize>::get() as usize;
+		let curr_point = Self::miner_power_info_point().1;
+
+		let power = <MinerPowerInfoStoreItem<T>>::get_miner_power_info(curr_point, who, block_number).total_power;
+		<CurrentPowerLeaderboard<T>>::mutate(|board| Self::insert_into_board(board, who, power, size));
+
+		let reward = <ThisArchiveDurationMinerReward<T>>::get(who);
+		<CurrentRewardLeaderboard<T>>::mutate(|board| Self::insert_into_board(board, who, reward, size));
+	}
+
+
+	/// 把矿工本周期的最新数值放进排行榜(从大到小, 数值相同的先上榜的排在前面)
+	/// 周期内的算力与奖励只增不减, 所以被挤出榜的矿工下次挖矿时再比较一次就行
+	fn insert_into_board<V: Ord + Copy>(board: &mut Vec<(T::AccountId, V)>, who: &T::AccountId, value: V, size: usize) {
+		board.retain(|(a, _)| a != who);
+		let index = board.iter().position(|(_, v)| *v < value).unwrap_or(board.len());
+		if index < size {
+			board.insert(index, (who.clone(), value));
+		}
+		board.truncate(size);
+	}
+
+
+	/// 更新矿工的历史挖矿统计
+	fn add_miner_statistics(who: T::AccountId, now_day: T::BlockNumber, usdt_nums: u64, reward: BalanceOf<T>) {
+		<MinerLifetimeStatistics<T>>::mutate(who, |h| {
+			if h.days_active == 0 {
+				h.first_day = now_day;
+				h.days_active = 1;
+			}
+			else if h.last_day != now_day {
+				h.days_active = h.days_active.saturating_add(1);
+			}
+			h.last_day = now_day;
+			h.total_tx_count = h.total_tx_count.saturating_add(1);
+			h.total_usdt_nums = h.total_usdt_nums.saturating_add(usdt_nums);
+			h.total_reward = h.total_reward.saturating_add(reward);
+		});
+	}
+
+
 	/// 计算总算力占比(这里也附带了一个算力检查功能: 如果本次挖的算力过大, 是有可能不给挖矿的)
 	fn calculate_workforce_ratio(
 		amount_workforce: u64, count_workforce: u64, pre_amount_workfore: u64, pre_count_workforce: u64)
@@ -1299,8 +1757,8 @@ impl<T: Trait> Module<T> {
 	}
 
 
-	/// 计算今天的挖矿奖励
-	fn per_day_mine_reward_token() -> result::Result<BalanceOf<T>, DispatchError>{
+	/// 计算今天的挖矿奖励  第二个参数为true表示使用了最低奖励(从国库出, 不增发)
+	fn per_day_mine_reward_token() -> result::Result<(BalanceOf<T>, bool), DispatchError>{
 		if T::ArchiveDuration::get() == T::BlockNumber::from(0u32) {
 			return Err(Error::<T>::DivZero)?;
 		}
@@ -1308,40 +1766,107 @@ impl<T: Trait> Module<T> {
 
 		let mut per_day_tokens = T::PerDayMinReward::get();
 
-		// 国库可以使用的钱
-		let useable_balance = Self::pot();
-
-		// 如果国库剩余的钱小与最小要求的奖励金额  那么用国库剩余的来计算
-		if per_day_tokens > useable_balance{
-			// 取更小
-			per_day_tokens = useable_balance.clone();
-		}
-
 		let e: u32 = ((100 as u64).checked_mul(<<T as system::Trait>::BlockNumber as TryInto<u64>>::try_into(block_num).ok().unwrap()).ok_or(Error::<T>::Overfolw)? /((36525*SubHalfDuration).checked_mul(<<T as system::Trait>::BlockNumber as TryInto<u64>>::try_into(T::ArchiveDuration::get()).ok().unwrap()).
 		ok_or(Error::<T>::Overfolw)?)) as u32;
 
 		// 128年之后的挖矿奖励基本为0 所以这时候可以使用最低奖励了
 		if e > 32{
-			T::Currency3::make_free_balance_be(&MODULE_ID.into_account(), useable_balance.saturating_sub(per_day_tokens.clone()) + T::Currency3::minimum_balance());
+			return Ok((per_day_tokens, true));
 		}
 
-		else{
+		let num = 2_u32.pow(e);  // 意味着e最大值是32  运行32*4 = 128年
+		per_day_tokens = <BalanceOf<T> as TryFrom::<Balance>>::try_from(FirstYearPerDayMineRewardToken).ok().unwrap()/<BalanceOf<T>>::from(num);
+
+		// 如果奖励数过低  那么启用最低奖励
+		if per_day_tokens < T::PerDayMinReward::get(){
+			return Ok((T::PerDayMinReward::get(), true));
+		}
+
+		Ok((per_day_tokens, false))
+	}
+
+
+	/// 矿池账号
+	pub fn mine_pot_account() -> T::AccountId {
+		MINE_POT_ID.into_account()
+	}
 
-			let num = 2_u32.pow(e);  // 意味着e最大值是32  运行32*4 = 128年
-			per_day_tokens = <BalanceOf<T> as TryFrom::<Balance>>::try_from(FirstYearPerDayMineRewardToken).ok().unwrap()/<BalanceOf<T>>::from(num);
 
-			// 如果奖励数过低  那么启用最低奖励
-			if per_day_tokens < T::PerDayMinReward::get(){
+	/// 矿池可用余额(保证矿池账号存活)
+	fn mine_pot() -> BalanceOf<T> {
+		T::Currency3::free_balance(&Self::mine_pot_account())
+			.saturating_sub(T::Currency3::minimum_balance())
+	}
+
+
+	/// 每个周期给矿池注入一次预算
+	/// 上个周期没有发完的留在矿池里算进今天的预算, 只补足到当天的挖矿奖励
+	/// 正常情况下增发补足的部分; 使用最低奖励时从国库转入(国库不够就用国库剩下的), 不增发
+	fn fund_mine_pot(now_day: T::BlockNumber) -> DispatchResult {
+		if <MinePotFundedDay<T>>::get() == Some(now_day) {
+			return Ok(());
+		}
 
-				per_day_tokens = T::PerDayMinReward::get();
-				T::Currency3::make_free_balance_be(&MODULE_ID.into_account(), useable_balance.saturating_sub(per_day_tokens) + T::Currency3::minimum_balance());
+		let (per_day_tokens, from_treasury) = Self::per_day_mine_reward_token()?;
+		let pot = Self::mine_pot_account();
+		let leftover = Self::mine_pot();
+		let top_up = per_day_tokens.saturating_sub(leftover);
+
+		let budget = if from_treasury {
+			let amount = top_up.min(Self::pot());
+			if amount.is_zero() || T::Currency3::transfer(
+				&MODULE_ID.into_account(), &pot, amount, ExistenceRequirement::KeepAlive).is_err() {
+				per_day_tokens.min(leftover)
+			} else {
+				per_day_tokens.min(leftover.saturating_add(amount))
 			}
+		} else {
+			if !top_up.is_zero() {
+				T::ShouldAddOrigin::on_unbalanced(T::Currency3::deposit_creating(&pot, top_up));
+			}
+			per_day_tokens
+		};
 
+		<MineBudget<T>>::put(budget);
+		<ThisDayReward<T>>::put(budget);
+		<MinePotFundedDay<T>>::put(now_day);
+		Self::deposit_event(RawEvent::MinePotFunded(now_day, budget));
+		Ok(())
+	}
+
+
+	/// 从矿池转出奖励  矿池不够的部分从国库转(国库也不够的部分不发放, 不增发)
+	fn pay_from_pot(who: &T::AccountId, amount: BalanceOf<T>) {
+		if amount.is_zero() {
+			return;
 		}
 
-		<ThisDayReward<T>>::put(per_day_tokens.clone());
+		let paid = amount.min(Self::mine_pot());
+		if !paid.is_zero() && T::Currency3::transfer(
+			&Self::mine_pot_account(), who, paid, ExistenceRequirement::KeepAlive).is_err() {
+			// 对方账号不存在并且金额小于最小存活金额, 不发放(和直接增发时的结果一样)
+			return;
+		}
+
+		let shortfall = amount.saturating_sub(paid);
+		if shortfall.is_zero() {
+			return;
+		}
+		let mut covered = shortfall.min(Self::pot());
+		if !covered.is_zero() && T::Currency3::transfer(
+			&MODULE_ID.into_account(), who, covered, ExistenceRequirement::KeepAlive).is_err() {
+			covered = Zero::zero();
+		}
+		if !covered.is_zero() {
+			<RewardShortfallTotal<T>>::mutate(|h| *h = h.saturating_add(covered));
+			Self::deposit_event(RawEvent::RewardShortfall(who.clone(), covered));
+		}
 
-		Ok(per_day_tokens)
+		let unpaid = shortfall.saturating_sub(covered);
+		if !unpaid.is_zero() {
+			<RewardUnpaidTotal<T>>::mutate(|h| *h = h.saturating_add(unpaid));
+			Self::deposit_event(RawEvent::RewardUnpaid(who.clone(), unpaid));
+		}
 	}
 
 
@@ -1550,8 +2075,7 @@ impl<T: Trait> Module<T> {
 			let per_founder_reward = founders_total_reward.clone()/<BalanceOf<T>>::from(member_count);
 			// 奖励每一个创始团队成员
 			for i in fouders.iter(){
-				// 用方法deposit_creating就可以  不要损害到矿工的利益
-				T::ShouldAddOrigin::on_unbalanced(T::Currency3::deposit_creating(&i, per_founder_reward));
+				Self::pay_from_pot(i, per_founder_reward);
 				<CommissionAmount<T>>::mutate(i.clone(), |h| {h.0 += per_founder_reward.clone(); h.1 = per_founder_reward.clone(); h.2 = time.clone();});
 		}
 
@@ -1559,18 +2083,18 @@ impl<T: Trait> Module<T> {
 
 		// 奖励上级
 		if let Some(father_address) = <AllMiners<T>>::get(who.clone()).father_address{
-			T::ShouldAddOrigin::on_unbalanced(T::Currency3::deposit_creating(&father_address, fa_reward.clone()));
+			Self::pay_from_pot(&father_address, fa_reward.clone());
 			<CommissionAmount<T>>::mutate(father_address.clone(), |h| {h.0 += fa_reward.clone(); h.1 = fa_reward.clone(); h.2 = time.clone();});
 		};
 
 		// 奖励上上级
 		if let Some(grandpa_address) = <AllMiners<T>>::get(who.clone()).grandpa_address{
-			T::ShouldAddOrigin::on_unbalanced(T::Currency3::deposit_creating(&grandpa_address, gr_reward.clone()));
+			Self::pay_from_pot(&grandpa_address, gr_reward.clone());
 			<CommissionAmount<T>>::mutate(grandpa_address.clone(), |h| {h.0 += gr_reward.clone(); h.1 = gr_reward.clone(); h.2 = time.clone();});
 		};
 
 		// 奖励矿工
-		T::ShouldAddOrigin::on_unbalanced(T::Currency3::deposit_creating(&who, miner_reward.clone()));
+		Self::pay_from_pot(&who, miner_reward.clone());
 
 		<MineReward<T>>::put((miner_reward, fa_reward, gr_reward, founders_total_reward));
 		Ok(())
@@ -1648,9 +2172,15 @@ impl<T: Trait> Module<T> {
 			MineTag::CLIENT => MineTag::WALLET,
 		};
 
-		if <OwnerMineRecord<T>>::contains_key(tx.clone(), mine_tag){
+		// 另外一种挖矿是自己挖的 说明今天的记录里已经有这笔tx
+		let is_added = match <OwnerMineRecord<T>>::get(tx.clone(), mine_tag){
+			Some(record) => record.miner_address == sender,
+			None => false,
+		};
+
+		if is_added{
 		}
-		// 如果是第一次添加该比交易 则去添加今天的日期进队列   如果已经存在不需要添加
+		// 如果是第一次添加该比交易(或是另一种挖矿是别人挖的) 则去添加今天的日期进队列   如果已经存在不需要添加
 		else{
 			 // 获取区块的高度
 			let now_day = block_num/T::ArchiveDuration::get();
@@ -1680,6 +2210,83 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
+	/// 获取本次挖矿所在的对敲检测窗口、地址对以及该地址对最近已经挖矿的次数
+	/// 次数是本窗口加上一个窗口的和(滑动窗口), 刚过窗口边界时不会清零
+	fn pair_mine_info(mine_parm: &MineParm, block_num: T::BlockNumber)
+		-> result::Result<(T::BlockNumber, (Vec<u8>, Vec<u8>, Vec<u8>), u32), DispatchError>{
+
+		let window = block_num.checked_div(&<WashTradeWindow<T>>::get()).ok_or(Error::<T>::DivZero)?;
+
+		// 地址排序, A转B与B转A算同一个地址对
+		let (a, b) = if mine_parm.address <= mine_parm.to_address {
+			(mine_parm.address.clone(), mine_parm.to_address.clone())
+		} else {
+			(mine_parm.to_address.clone(), mine_parm.address.clone())
+		};
+		let key = (mine_parm.symbol.clone(), a, b);
+
+		let mut count = <PairMineCount<T>>::get(&window, &key);
+		if !window.is_zero() {
+			count = count.saturating_add(<PairMineCount<T>>::get(&(window - One::one()), &key));
+		}
+		// 同一笔tx的第二次挖矿 与第一次使用相同的倍数
+		if mine_parm.mine_count > 1 {
+			count = count.saturating_sub(1);
+		}
+
+		Ok((window, key, count))
+	}
+
+
+	/// 地址对已经挖矿count次时 本次挖矿的算力倍数
+	fn wash_trade_multiplier(count: u32) -> Permill {
+		let multipliers = <WashTradeMultipliers>::get();
+		match multipliers.get(count as usize) {
+			Some(m) => *m,
+			None => multipliers.last().cloned().unwrap_or(Permill::from_percent(100)),
+		}
+	}
+
+
+	/// 地址对挖矿次数加1, 达到阈值时发出可疑事件并通知举报模块
+	/// count是pair_mine_info返回的最近的挖矿次数
+	fn add_pair_mine_count(who: T::AccountId, mine_parm: &MineParm, window: T::BlockNumber,
+		key: (Vec<u8>, Vec<u8>, Vec<u8>), count: u32) {
+
+		// 同一笔tx的第二次挖矿不重复计数, 也不重复通知
+		if mine_parm.mine_count > 1 {
+			return;
+		}
+		<PairMineCount<T>>::mutate(&window, &key, |n| *n = n.saturating_add(1));
+		<PairWindows<T>>::mutate(|h| if !h.contains(&window) { h.push(window) });
+
+		// 只在刚好达到阈值时处理一次 防止重复举报
+		let new_count = count.saturating_add(1);
+		if new_count == <WashTradeReportThreshold>::get() {
+			Self::deposit_event(RawEvent::SuspiciousPattern(who.clone(), mine_parm.tx.clone(), new_count));
+			T::SuspiciousTxs::on_suspicious(who, mine_parm.tx.clone(), mine_parm.mine_tag.clone(), b"wash trade".to_vec());
+		}
+	}
+
+
+	/// 删除已经过期的对敲检测窗口(上一个窗口还要用来计算滑动窗口内的次数)
+	fn remove_expire_pair_windows(block_number: T::BlockNumber) {
+		let window_len = <WashTradeWindow<T>>::get();
+		if window_len.is_zero() {
+			return;
+		}
+		let now_window = block_number / window_len;
+		<PairWindows<T>>::mutate(|h| h.retain(|w| {
+			if *w + One::one() < now_window {
+				<PairMineCount<T>>::remove_prefix(w);
+				false
+			} else {
+				true
+			}
+		}));
+	}
+
+
 	/// 初始化
 	fn initialize_founders(members: &[T::AccountId]){
 
@@ -1693,3 +2300,506 @@ impl<T: Trait> Module<T> {
 
 
 
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_support::{assert_ok, assert_noop, traits::OnInitialize};
+	use sp_runtime::DispatchError;
+	use crate::{Runtime, System, Mine, Balances, Origin, Event, AccountId};
+
+	const TREASURY_BALANCE: Balance = 100_0000 * DOLLARS;
+
+	fn treasury() -> AccountId {
+		MODULE_ID.into_account()
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+		pallet_balances::GenesisConfig::<Runtime> {
+			balances: vec![(treasury(), TREASURY_BALANCE)],
+		}.assimilate_storage(&mut t).unwrap();
+		let mut t: sp_io::TestExternalities = t.into();
+		t.execute_with(|| System::set_block_number(1));
+		t
+	}
+
+	fn create_mine(who: AccountId, mine_tag: MineTag, symbol: &str) -> DispatchResult {
+		Mine::create_mine(
+			Origin::signed(who), mine_tag, b"tx".to_vec(), b"from".to_vec(), b"to".to_vec(),
+			symbol.as_bytes().to_vec(), b"1".to_vec(), b"erc20".to_vec(), 0, 10 * USDT_DECIMALS,
+			b"chain".to_vec(), vec![])
+	}
+
+	fn mine_events() -> Vec<RawEvent<AccountId, <Runtime as system::Trait>::Hash, BlockNumberOf<Runtime>, BalanceOf<Runtime>>> {
+		System::events().into_iter().filter_map(|r| match r.event {
+			Event::mine(e) => Some(e),
+			_ => None,
+		}).collect()
+	}
+
+	#[test]
+	fn pause_needs_governance() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(
+				Mine::pause_mine(Origin::signed(AccountId::from([1u8; 32])), MinePauseTarget::Global),
+				DispatchError::BadOrigin
+			);
+			assert_noop!(
+				Mine::resume_mine(Origin::signed(AccountId::from([1u8; 32])), MinePauseTarget::Global),
+				DispatchError::BadOrigin
+			);
+		});
+	}
+
+	#[test]
+	fn global_pause_stops_mining() {
+		new_test_ext().execute_with(|| {
+			let who = AccountId::from([1u8; 32]);
+			assert_ok!(Mine::pause_mine(Origin::root(), MinePauseTarget::Global));
+			assert!(mine_events().contains(&RawEvent::MinePaused(MinePauseTarget::Global)));
+			assert_noop!(create_mine(who.clone(), MineTag::WALLET, BTC), Error::<Runtime>::MinePaused);
+			assert_noop!(create_mine(who.clone(), MineTag::CLIENT, ETH), Error::<Runtime>::MinePaused);
+
+			assert_ok!(Mine::resume_mine(Origin::root(), MinePauseTarget::Global));
+			assert!(mine_events().contains(&RawEvent::MineResumed(MinePauseTarget::Global)));
+			assert!(!Mine::is_mine_paused(&BTC.as_bytes().to_vec(), &MineTag::WALLET));
+		});
+	}
+
+	#[test]
+	fn symbol_and_tag_pause_only_hit_their_scope() {
+		new_test_ext().execute_with(|| {
+			let who = AccountId::from([1u8; 32]);
+			assert_ok!(Mine::pause_mine(Origin::root(), MinePauseTarget::Symbol(BTC.as_bytes().to_vec())));
+			assert_ok!(Mine::pause_mine(Origin::root(), MinePauseTarget::Tag(MineTag::CLIENT)));
+
+			assert_noop!(create_mine(who.clone(), MineTag::WALLET, BTC), Error::<Runtime>::MinePaused);
+			assert_noop!(create_mine(who.clone(), MineTag::CLIENT, ETH), Error::<Runtime>::MinePaused);
+			assert!(!Mine::is_mine_paused(&ETH.as_bytes().to_vec(), &MineTag::WALLET));
+			// 没有被暂停的组合会走到后面的检查(没有注册)
+			assert_noop!(create_mine(who, MineTag::WALLET, ETH), Error::<Runtime>::NotRegister);
+		});
+	}
+
+	#[test]
+	fn circuit_breaker_trips_on_count_jump() {
+		new_test_ext().execute_with(|| {
+			// 第3个归档周期, 前一天是第2个
+			let block_number: BlockNumberOf<Runtime> = ArchiveDurationTime * 2 + 5;
+			let mut prev = TokenPowerInfoItem::<Runtime>::default();
+			prev.btc_total_count = 100;
+			prev.btc_total_power = 1000;
+			<TokenPowerInfoList<Runtime>>::insert(2, prev);
+
+			let mut today = TokenPowerInfoItem::<Runtime>::default();
+			today.btc_total_count = 400;
+			today.btc_total_power = 1000;
+			<TokenPowerInfoList<Runtime>>::insert(3, today.clone());
+
+			// 没有超过倍数
+			Mine::check_circuit_breaker(BTC, block_number);
+			assert!(!Mine::mine_paused(MinePauseTarget::Symbol(BTC.as_bytes().to_vec())));
+
+			today.btc_total_count = 501;
+			<TokenPowerInfoList<Runtime>>::insert(3, today);
+			Mine::check_circuit_breaker(BTC, block_number);
+			assert!(Mine::mine_paused(MinePauseTarget::Symbol(BTC.as_bytes().to_vec())));
+			assert!(!Mine::mine_paused(MinePauseTarget::Symbol(ETH.as_bytes().to_vec())));
+			assert!(mine_events().contains(
+				&RawEvent::CircuitBreakerTripped(BTC.as_bytes().to_vec(), 501, 100, 1000, 1000)));
+		});
+	}
+
+	#[test]
+	fn circuit_breaker_trips_on_power_jump_and_can_be_disabled() {
+		new_test_ext().execute_with(|| {
+			let block_number: BlockNumberOf<Runtime> = ArchiveDurationTime * 2 + 5;
+			let mut prev = TokenPowerInfoItem::<Runtime>::default();
+			prev.eth_total_count = 100;
+			prev.eth_total_power = 1000;
+			<TokenPowerInfoList<Runtime>>::insert(2, prev);
+
+			let mut today = TokenPowerInfoItem::<Runtime>::default();
+			today.eth_total_count = 100;
+			today.eth_total_power = 5001;
+			<TokenPowerInfoList<Runtime>>::insert(3, today);
+
+			assert_ok!(Mine::set_circuit_breaker_param(Origin::root(), CircuitBreakerParam::Multiple(0)));
+			Mine::check_circuit_breaker(ETH, block_number);
+			assert!(!Mine::mine_paused(MinePauseTarget::Symbol(ETH.as_bytes().to_vec())));
+
+			assert_ok!(Mine::set_circuit_breaker_param(Origin::root(), CircuitBreakerParam::Multiple(5)));
+			Mine::check_circuit_breaker(ETH, block_number);
+			assert!(Mine::mine_paused(MinePauseTarget::Symbol(ETH.as_bytes().to_vec())));
+
+			// 治理恢复
+			assert_ok!(Mine::resume_mine(Origin::root(), MinePauseTarget::Symbol(ETH.as_bytes().to_vec())));
+			assert!(!Mine::mine_paused(MinePauseTarget::Symbol(ETH.as_bytes().to_vec())));
+		});
+	}
+
+	#[test]
+	fn circuit_breaker_skips_small_samples() {
+		new_test_ext().execute_with(|| {
+			let block_number: BlockNumberOf<Runtime> = ArchiveDurationTime * 2 + 5;
+			let mut prev = TokenPowerInfoItem::<Runtime>::default();
+			prev.eos_total_count = 1;
+			<TokenPowerInfoList<Runtime>>::insert(2, prev);
+
+			let mut today = TokenPowerInfoItem::<Runtime>::default();
+			today.eos_total_count = 99;
+			<TokenPowerInfoList<Runtime>>::insert(3, today);
+
+			Mine::check_circuit_breaker(EOS, block_number);
+			assert!(!Mine::mine_paused(MinePauseTarget::Symbol(EOS.as_bytes().to_vec())));
+		});
+	}
+
+	#[test]
+	fn funding_tops_up_the_pot_to_the_daily_emission() {
+		new_test_ext().execute_with(|| {
+			let issuance = Balances::total_issuance();
+			let (emission, from_treasury) = Mine::per_day_mine_reward_token().unwrap();
+			assert!(!from_treasury);
+
+			assert_ok!(Mine::fund_mine_pot(0));
+			assert_eq!(Mine::mine_budget(), emission);
+			assert_eq!(Balances::free_balance(&Mine::mine_pot_account()), emission);
+			assert_eq!(Balances::total_issuance(), issuance + emission);
+			assert_eq!(Balances::free_balance(&treasury()), TREASURY_BALANCE);
+
+			// 同一个周期不会重复注入
+			assert_ok!(Mine::fund_mine_pot(0));
+			assert_eq!(Balances::total_issuance(), issuance + emission);
+			assert!(mine_events().contains(&RawEvent::MinePotFunded(0, emission)));
+
+			// 第二天只增发前一天发出去的部分(矿池账号要留最小存活金额) 没发完的留着用
+			let existential_deposit = <Runtime as pallet_balances::Trait>::ExistentialDeposit::get();
+			Mine::pay_from_pot(&AccountId::from([1u8; 32]), 10 * DOLLARS);
+			assert_ok!(Mine::fund_mine_pot(1));
+			assert_eq!(Mine::mine_budget(), emission);
+			assert_eq!(Balances::free_balance(&Mine::mine_pot_account()), emission + existential_deposit);
+			assert_eq!(Balances::total_issuance(), issuance + emission + 10 * DOLLARS + existential_deposit);
+		});
+	}
+
+	#[test]
+	fn rewards_are_transfers_from_the_pot() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(Mine::fund_mine_pot(0));
+			let issuance = Balances::total_issuance();
+			let pot = Balances::free_balance(&Mine::mine_pot_account());
+
+			let miner = AccountId::from([1u8; 32]);
+			let father = AccountId::from([2u8; 32]);
+			Mine::pay_from_pot(&miner, 10 * DOLLARS);
+			Mine::pay_from_pot(&father, 5 * DOLLARS);
+
+			assert_eq!(Balances::free_balance(&miner), 10 * DOLLARS);
+			assert_eq!(Balances::free_balance(&father), 5 * DOLLARS);
+			assert_eq!(Balances::free_balance(&Mine::mine_pot_account()), pot - 15 * DOLLARS);
+			assert_eq!(Balances::total_issuance(), issuance);
+			assert_eq!(Mine::reward_shortfall_total(), 0);
+		});
+	}
+
+	#[test]
+	fn shortfall_is_covered_by_treasury() {
+		new_test_ext().execute_with(|| {
+			// 矿池是空的
+			let issuance = Balances::total_issuance();
+			let miner = AccountId::from([1u8; 32]);
+
+			Mine::pay_from_pot(&miner, 100 * DOLLARS);
+
+			assert_eq!(Balances::free_balance(&miner), 100 * DOLLARS);
+			assert_eq!(Balances::free_balance(&treasury()), TREASURY_BALANCE - 100 * DOLLARS);
+			assert_eq!(Balances::total_issuance(), issuance);
+			assert_eq!(Mine::reward_shortfall_total(), 100 * DOLLARS);
+			assert!(mine_events().contains(&RawEvent::RewardShortfall(miner, 100 * DOLLARS)));
+		});
+	}
+
+	#[test]
+	fn shortfall_beyond_the_treasury_is_not_minted() {
+		new_test_ext().execute_with(|| {
+			// 矿池是空的 国库只剩30
+			let existential_deposit = <Runtime as pallet_balances::Trait>::ExistentialDeposit::get();
+			Balances::make_free_balance_be(&treasury(), 30 * DOLLARS + existential_deposit);
+			let issuance = Balances::total_issuance();
+			let miner = AccountId::from([1u8; 32]);
+
+			Mine::pay_from_pot(&miner, 100 * DOLLARS);
+
+			assert_eq!(Balances::free_balance(&miner), 30 * DOLLARS);
+			assert_eq!(Balances::free_balance(&treasury()), existential_deposit);
+			assert_eq!(Balances::total_issuance(), issuance);
+			assert_eq!(Mine::reward_shortfall_total(), 30 * DOLLARS);
+			assert_eq!(Mine::reward_unpaid_total(), 70 * DOLLARS);
+			assert!(mine_events().contains(&RawEvent::RewardUnpaid(miner, 70 * DOLLARS)));
+		});
+	}
+
+	#[test]
+	fn floor_reward_comes_from_treasury_without_minting() {
+		new_test_ext().execute_with(|| {
+			// 128年之后只有最低奖励
+			let block_number: BlockNumberOf<Runtime> = ArchiveDurationTime * 46753;
+			System::set_block_number(block_number);
+			let issuance = Balances::total_issuance();
+			let (floor, from_treasury) = Mine::per_day_mine_reward_token().unwrap();
+			assert!(from_treasury);
+
+			let day = block_number / ArchiveDurationTime;
+			assert_ok!(Mine::fund_mine_pot(day));
+			assert_eq!(Mine::mine_budget(), floor);
+			assert_eq!(Balances::free_balance(&Mine::mine_pot_account()), floor);
+			assert_eq!(Balances::free_balance(&treasury()), TREASURY_BALANCE - floor);
+			assert_eq!(Balances::total_issuance(), issuance);
+		});
+	}
+
+	fn mine_record(who: &AccountId, tx: &[u8], timestamp: u64) -> OwnerMineRecordItem<Runtime> {
+		PersonMineRecord {
+			mine_tag: MineTag::WALLET,
+			mine_count: 1,
+			timestamp,
+			blocknum: 1,
+			miner_address: who.clone(),
+			from_address: b"from".to_vec(),
+			to_address: b"to".to_vec(),
+			symbol: ETH.as_bytes().to_vec(),
+			blockchain: b"chain".to_vec(),
+			tx: tx.to_vec(),
+			usdt_amount: 10,
+			sym_amount: b"1".to_vec(),
+			decimal: 0,
+			pcount_workforce: 0,
+			pamount_workforce: 0,
+			reward: 0,
+			grandpa_reward: 0,
+			father_reward: 0,
+		}
+	}
+
+	#[test]
+	fn records_before_statistics_are_listed() {
+		new_test_ext().execute_with(|| {
+			let who = AccountId::from([1u8; 32]);
+			// 挖矿统计上线以前的记录: 只有按天保存的tx, 没有统计
+			for (tx, timestamp) in vec![(b"tx2".to_vec(), 20u64), (b"tx1".to_vec(), 10)] {
+				<MinerAllDaysTx<Runtime>>::mutate(&who, 0, |txs| txs.push(tx.clone()));
+				<OwnerMineRecord<Runtime>>::insert(&tx, MineTag::WALLET, mine_record(&who, &tx, timestamp));
+			}
+			assert_eq!(Mine::miner_statistics(&who).days_active, 0);
+
+			let records = Mine::miner_day_records(who.clone(), 0);
+			assert_eq!(records.iter().map(|r| r.0).collect::<Vec<_>>(), vec![10, 20]);
+			// 同一笔tx别人挖的不算
+			<OwnerMineRecord<Runtime>>::insert(b"tx1".to_vec(), MineTag::WALLET, mine_record(&AccountId::from([2u8; 32]), b"tx1", 10));
+			assert_eq!(Mine::miner_day_records(who, 0).len(), 1);
+		});
+	}
+
+	fn pair_parm(tx: &[u8], from: &[u8], to: &[u8], mine_count: u16) -> MineParm {
+		MineParm {
+			mine_tag: MineTag::WALLET,
+			mine_count,
+			action: b"transfer".to_vec(),
+			tx: tx.to_vec(),
+			address: from.to_vec(),
+			to_address: to.to_vec(),
+			symbol: BTC.as_bytes().to_vec(),
+			amount: b"1".to_vec(),
+			protocol: b"btc".to_vec(),
+			decimal: 0,
+			usdt_nums: 10 * USDT_DECIMALS,
+			blockchain: b"btc".to_vec(),
+			memo: vec![],
+		}
+	}
+
+	// 模拟一次挖矿里的对敲处理, 返回本次挖矿前的次数
+	fn pair_mine(who: &AccountId, parm: &MineParm, block_num: BlockNumberOf<Runtime>) -> u32 {
+		let (window, key, count) = Mine::pair_mine_info(parm, block_num).unwrap();
+		Mine::add_pair_mine_count(who.clone(), parm, window, key, count);
+		count
+	}
+
+	fn suspicious_count() -> usize {
+		mine_events().into_iter().filter(|e| match e {
+			RawEvent::SuspiciousPattern(..) => true,
+			_ => false,
+		}).count()
+	}
+
+	#[test]
+	fn wash_trade_multiplier_decreases_with_count() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(Mine::wash_trade_multiplier(0), Permill::from_percent(100));
+			assert_eq!(Mine::wash_trade_multiplier(1), Permill::from_percent(50));
+			assert_eq!(Mine::wash_trade_multiplier(2), Permill::from_percent(25));
+			assert_eq!(Mine::wash_trade_multiplier(3), Permill::from_percent(10));
+			// 超过配置长度时使用最后一个
+			assert_eq!(Mine::wash_trade_multiplier(100), Permill::from_percent(10));
+		});
+	}
+
+	#[test]
+	fn suspicious_pattern_is_emitted_once_at_threshold() {
+		new_test_ext().execute_with(|| {
+			let who = AccountId::from([1u8; 32]);
+			let threshold = Mine::wash_trade_report_threshold();
+			for i in 0..threshold {
+				// A转B与B转A算同一个地址对
+				let parm = if i % 2 == 0 {
+					pair_parm(&[i as u8], b"a", b"b", 1)
+				} else {
+					pair_parm(&[i as u8], b"b", b"a", 1)
+				};
+				assert_eq!(pair_mine(&who, &parm, 1), i);
+			}
+			assert_eq!(suspicious_count(), 1);
+			assert!(mine_events().contains(
+				&RawEvent::SuspiciousPattern(who.clone(), vec![(threshold - 1) as u8], threshold)));
+
+			// 同一笔tx的第二次挖矿 倍数与第一次相同, 不计数也不再通知
+			let second = pair_parm(&[(threshold - 1) as u8], b"a", b"b", 2);
+			assert_eq!(pair_mine(&who, &second, 1), threshold - 1);
+			assert_eq!(suspicious_count(), 1);
+
+			// 超过阈值后不再重复通知
+			assert_eq!(pair_mine(&who, &pair_parm(b"next", b"a", b"b", 1), 1), threshold);
+			assert_eq!(suspicious_count(), 1);
+		});
+	}
+
+	#[test]
+	fn previous_window_counts_until_expired() {
+		new_test_ext().execute_with(|| {
+			let who = AccountId::from([1u8; 32]);
+			let window_len = Mine::wash_trade_window();
+			let first_window: BlockNumberOf<Runtime> = 0;
+			for i in 0..3u8 {
+				pair_mine(&who, &pair_parm(&[i], b"a", b"b", 1), window_len - 1);
+			}
+
+			// 刚过窗口边界 上一个窗口的次数仍然有效
+			let parm = pair_parm(b"next", b"a", b"b", 1);
+			let (_, key, count) = Mine::pair_mine_info(&parm, window_len).unwrap();
+			assert_eq!(count, 3);
+			assert_eq!(Mine::wash_trade_multiplier(count), Permill::from_percent(10));
+
+			// 上一个窗口还在用, 不能删除
+			Mine::remove_expire_pair_windows(window_len);
+			assert_eq!(Mine::pair_mine_count(first_window, &key), 3);
+			assert_eq!(Mine::pair_windows(), vec![0]);
+
+			// 两个窗口之后 次数清零并且被删除
+			let (_, _, count) = Mine::pair_mine_info(&parm, window_len * 2).unwrap();
+			assert_eq!(count, 0);
+			Mine::remove_expire_pair_windows(window_len * 2);
+			assert_eq!(Mine::pair_mine_count(first_window, &key), 0);
+			assert!(Mine::pair_windows().is_empty());
+		});
+	}
+
+	#[test]
+	fn board_keeps_only_top_entries() {
+		new_test_ext().execute_with(|| {
+			let (a, b, c, d) = (AccountId::from([1u8; 32]), AccountId::from([2u8; 32]),
+				AccountId::from([3u8; 32]), AccountId::from([4u8; 32]));
+			let mut board: Vec<(AccountId, u64)> = vec![];
+			Mine::insert_into_board(&mut board, &a, 5, 3);
+			Mine::insert_into_board(&mut board, &b, 7, 3);
+			Mine::insert_into_board(&mut board, &c, 3, 3);
+			// 比榜上最后一名小 上不了榜
+			Mine::insert_into_board(&mut board, &d, 1, 3);
+			assert_eq!(board, vec![(b.clone(), 7), (a.clone(), 5), (c.clone(), 3)]);
+
+			// 已经在榜上的矿工更新数值, 不会重复上榜
+			Mine::insert_into_board(&mut board, &a, 8, 3);
+			assert_eq!(board, vec![(a.clone(), 8), (b.clone(), 7), (c.clone(), 3)]);
+
+			// 被挤出榜的矿工 数值变大后可以再上榜
+			Mine::insert_into_board(&mut board, &d, 4, 3);
+			assert_eq!(board, vec![(a.clone(), 8), (b.clone(), 7), (d.clone(), 4)]);
+
+			// 数值相同时 先上榜的排在前面
+			Mine::insert_into_board(&mut board, &c, 7, 3);
+			assert_eq!(board, vec![(a, 8), (b, 7), (c, 7)]);
+		});
+	}
+
+	#[test]
+	fn mining_updates_current_leaderboard() {
+		new_test_ext().execute_with(|| {
+			let who = AccountId::from([1u8; 32]);
+			<ThisArchiveDurationMinerReward<Runtime>>::insert(&who, 3 * DOLLARS);
+			Mine::update_leaderboard(&who, 1);
+			assert_eq!(Mine::current_reward_leaderboard(), vec![(who.clone(), 3 * DOLLARS)]);
+			assert_eq!(Mine::current_power_leaderboard(), vec![(who, 0)]);
+		});
+	}
+
+	#[test]
+	fn leaderboard_archive_is_weighed_by_miner_count() {
+		new_test_ext().execute_with(|| {
+			let (a, b) = (AccountId::from([1u8; 32]), AccountId::from([2u8; 32]));
+			<CurrentPowerLeaderboard<Runtime>>::put(vec![(a.clone(), 20), (b.clone(), 10)]);
+			<CurrentRewardLeaderboard<Runtime>>::put(vec![(b.clone(), 5 * DOLLARS), (a.clone(), 2 * DOLLARS)]);
+			<ThisArchiveDurationMinerReward<Runtime>>::insert(&a, 2 * DOLLARS);
+			<ThisArchiveDurationMinerReward<Runtime>>::insert(&b, 5 * DOLLARS);
+			ThisArchiveDurationMinerCount::put(2);
+			// 周期内调小了上榜人数
+			assert_ok!(Mine::set_leaderboard_size(Origin::root(), 1));
+
+			let block_number: BlockNumberOf<Runtime> = ArchiveDurationTime * 2;
+			assert_eq!(Mine::on_initialize(block_number - 1), 0);
+			// 要删除的奖励统计按条数算weight
+			let weight = Mine::on_initialize(block_number);
+			assert_eq!(weight, <Runtime as system::Trait>::DbWeight::get().reads_writes(4, 9));
+
+			Mine::archive_leaderboard(block_number);
+			assert_eq!(Mine::day_power_leaderboard(2), vec![(a.clone(), 20)]);
+			assert_eq!(Mine::day_reward_leaderboard(2), vec![(b.clone(), 5 * DOLLARS)]);
+			assert!(mine_events().contains(&RawEvent::LeaderboardArchived(2, 1)));
+
+			// 本周期的数据已经清掉
+			assert!(Mine::current_power_leaderboard().is_empty());
+			assert!(Mine::current_reward_leaderboard().is_empty());
+			assert_eq!(Mine::this_duration_miner_reward(&a), 0);
+			assert_eq!(Mine::this_duration_miner_reward(&b), 0);
+			assert_eq!(Mine::this_duration_miner_count(), 0);
+			assert!(Mine::archive_leaderboard_weight() < weight);
+		});
+	}
+
+
+	#[test]
+	fn threshold_is_hit_exactly_once_across_a_window_rollover() {
+		new_test_ext().execute_with(|| {
+			let who = AccountId::from([1u8; 32]);
+			let window_len = Mine::wash_trade_window();
+			let threshold = Mine::wash_trade_report_threshold();
+			// 上一个窗口的最后一个区块 差两次到阈值
+			for i in 0..threshold - 2 {
+				assert_eq!(pair_mine(&who, &pair_parm(&[i as u8], b"a", b"b", 1), window_len - 1), i);
+			}
+
+			// 进入新窗口后接着上一个窗口的次数累计
+			assert_eq!(pair_mine(&who, &pair_parm(b"x", b"b", b"a", 1), window_len), threshold - 2);
+			assert_eq!(suspicious_count(), 0);
+			// 刚好达到阈值 通知一次
+			assert_eq!(pair_mine(&who, &pair_parm(b"y", b"a", b"b", 1), window_len), threshold - 1);
+			assert_eq!(suspicious_count(), 1);
+			assert!(mine_events().contains(&RawEvent::SuspiciousPattern(who.clone(), b"y".to_vec(), threshold)));
+
+			// 同一个滑动窗口里超过阈值不再通知
+			assert_eq!(pair_mine(&who, &pair_parm(b"z", b"a", b"b", 1), window_len + 1), threshold);
+			assert_eq!(suspicious_count(), 1);
+		});
+	}
+}
@@ -6,6 +6,8 @@ use codec::{Encode, Decode};
 use sp_std::{self, result};
 
 use crate::constants::time::ArchiveDurationTime;
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize, Serializer};
 
 #[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
 #[derive(Encode, Decode, Clone)]
@@ -95,7 +97,7 @@ impl<Storage, Key,BlockNumber> PersonMine<Storage, Key,BlockNumber> where
         Ok(())
     }
 }
-#[cfg_attr(feature = "std", derive())]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
 pub enum MineTag {  // TODO HAHAHA
 	CLIENT,  // 收款客户端
@@ -105,7 +107,9 @@ pub enum MineTag {  // TODO HAHAHA
 
 
 // 个人算力 单次挖矿表, 不做存储
-#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq, Serialize))]
+#[cfg_attr(feature = "std", serde(bound(
+	serialize = "Moment: Serialize, BlockNumber: Serialize, Balance: sp_std::fmt::Display, AccountId: Serialize")))]
 #[derive(Encode, Decode,Clone)]
 pub struct PersonMineRecord<Moment,BlockNumber,Balance,AccountId>{
 	pub mine_tag: MineTag, // 本次交易的挖矿标记
@@ -124,11 +128,20 @@ pub struct PersonMineRecord<Moment,BlockNumber,Balance,AccountId>{
     pub decimal:u32,           // 币种精度
     pub pcount_workforce:u64,     // 这次交易频次算力
     pub pamount_workforce:u64,     //这次交易金额算力
+    #[cfg_attr(feature = "std", serde(serialize_with = "serialize_as_string"))]
     pub reward:Balance,                 // 奖励的token
+    #[cfg_attr(feature = "std", serde(serialize_with = "serialize_as_string"))]
     pub grandpa_reward:Balance,        // 上级奖励的token
+    #[cfg_attr(feature = "std", serde(serialize_with = "serialize_as_string"))]
     pub father_reward:Balance           // 上上级奖励的token
 }
 
+// 金额超过了u64 rpc返回的json里用字符串表示
+#[cfg(feature = "std")]
+fn serialize_as_string<S: Serializer, T: sp_std::fmt::Display>(t: &T, serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.serialize_str(&t.to_string())
+}
+
 pub fn bytes_into_int(bytes: Vec<u8>) -> u128{
 	let mut len = bytes.len() as u32;
 	let mut num = 0u128;
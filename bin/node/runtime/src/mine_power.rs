@@ -300,7 +300,7 @@ impl<Storage, BlockNumber>TokenPowerInfoStore<Storage, BlockNumber> where
 #[cfg_attr(feature = "std", derive(Debug))]
 pub struct MinerPowerInfo<AccountId, BlockNumber> {
     miner_id: AccountId,                        // 矿工ID
-    total_power: u64,                           // 24小时累计算力
+    pub(crate) total_power: u64,                // 24小时累计算力
     total_count: u64,                           // 24小时累计交易次数
 	pub(crate) count_power: u64,
     pub(crate) total_amount: u64,                          // 24小时累计交易金额，以USDT计算
@@ -4,17 +4,17 @@ use hex;
 use pallet_timestamp as timestamp;
 use sp_runtime::RuntimeAppPublic;
 use frame_support::{Parameter,debug};
-use app_crypto::{sr25519};
+use sp_core::sr25519;
 use frame_support::traits::{FindAllAuthor};
 use frame_system::{self as system};
 use sp_core::{crypto::KeyTypeId,offchain::Timestamp};
 use pallet_authority_discovery as authority_discovery;
-use sp_runtime::{offchain::http};
-use alt_serde::{Deserialize, Deserializer};
-use frame_support::{StorageMap,StorageValue,traits::{LockableCurrency,Currency}}; // 含有get
+use sp_runtime::{offchain::{http, storage::StorageValueRef}, traits::SaturatedConversion};
+use alt_serde::{Deserialize, Deserializer, Serialize, Serializer};
+use frame_support::{StorageMap,StorageValue}; // 含有get
 
-use crate::register::{self,IsValidtorOcw, ValidatorLocalSerErrCnt, SlashValidator, Trait as RegisterTrait};
-use crate::report::{self,IllegalPunishment, Trait as ReportTrait};
+use crate::register::{self,IsValidtorOcw, VerifierKey, DowntimeStage, VerifyTally, VerifyAssignment, Trait as RegisterTrait};
+use crate::report::{self, Trait as ReportTrait};
 
 
 pub const TX_KEY_TYPE: KeyTypeId = KeyTypeId(*b"ofty");
@@ -22,10 +22,61 @@ pub const VERIFY_STATUS: &[u8] = b"verify_status";  // 验证的返回状态
 pub const PENDING_TIME_OUT: &'static str = "Error in waiting http response back";
 pub const WAIT_HTTP_CONVER_REPONSE: &'static str ="Error in waiting http_result convert response";
 
+// 链下存储(PERSISTENT)里验证服务地址的key, 值是SCALE编码的Vec<Vec<u8>>
+// 节点用 --tx-verifier-url/--address-verifier-url 启动参数或是 offchain_localStorageSet 写入, 不需要升级runtime
+pub const TX_VERIFIER_URLS_KEY: &[u8] = b"transx::tx-verifier-urls";
+pub const ADDRESS_VERIFIER_URLS_KEY: &[u8] = b"transx::address-verifier-urls";
+
+pub const INVALID_RESPONSE_SIGNATURE: &'static str = "Error in verifying response signature";
+// 签名的验证结果最多早于(或晚于)本地时间多少秒
+pub const RESPONSE_MAX_AGE_SECS: u64 = 600;
+
+/// 验证服务返回的结果
+/// 登记了公钥的验证人, 验证服务还要返回签名的时间(秒)与签名(0x开头的16进制)
 #[serde(crate = "alt_serde")]
 #[derive(Deserialize, Encode, Decode, Default)]
-struct ResponseStatus {
-    verify_status: u64,
+pub struct ResponseStatus {
+    pub verify_status: u64,
+    #[serde(default)]
+    pub timestamp: u64,
+    #[serde(default, deserialize_with = "de_hex_to_bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// 检查验证结果的签名 签名的内容是SCALE编码的 (tx, symbol, verify_status, timestamp)
+pub struct ResponseAuth<'a> {
+    pub key: sr25519::Public,  // 验证人登记的公钥
+    pub tx: &'a [u8],
+    pub symbol: &'a [u8],
+}
+
+impl<'a> ResponseAuth<'a> {
+    pub fn message(&self, status: u64, timestamp: u64) -> Vec<u8> {
+        (self.tx, self.symbol, status, timestamp).encode()
+    }
+
+    /// now 是本地时间(秒)
+    pub fn check(&self, resp: &ResponseStatus, now: u64) -> StdResult<()> {
+        if resp.signature.len() != 64 {
+            return Err(INVALID_RESPONSE_SIGNATURE);
+        }
+        if resp.timestamp.max(now) - resp.timestamp.min(now) > RESPONSE_MAX_AGE_SECS {
+            return Err(INVALID_RESPONSE_SIGNATURE);
+        }
+        let signature = sr25519::Signature::from_slice(&resp.signature);
+        if !sp_io::crypto::sr25519_verify(&signature, &self.message(resp.verify_status, resp.timestamp), &self.key) {
+            return Err(INVALID_RESPONSE_SIGNATURE);
+        }
+        Ok(())
+    }
+}
+
+pub fn de_hex_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+{
+    let s: &str = Deserialize::deserialize(de)?;
+    hex::decode(s.trim_start_matches("0x")).map_err(alt_serde::de::Error::custom)
 }
 
 pub fn de_string_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
@@ -36,14 +87,53 @@ pub fn de_string_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
     Ok(s.as_bytes().to_vec())
 }
 
-// post json中常用的关键字符
-pub(crate) const POST_KEYWORD:[&[u8]; 5] = [
-    b"{",     // {
-    b"\"",   // "
-    b"\":",  // ":
-    b",",    // ,
-    b"}"      // }
-];
+/// 请求验证服务的json格式版本, 字段有变化时加1(格式见 文档/tx验验证文档.md)
+pub const VERIFY_SCHEMA_VERSION: u32 = 1;
+
+/// 挖矿tx的验证请求 POST /v1/tx/verify
+#[serde(crate = "alt_serde")]
+#[derive(Serialize)]
+pub struct TxVerifyRequest<'a> {
+    pub version: u32,
+    #[serde(serialize_with = "ser_hex")]
+    pub tx: &'a [u8],
+    pub symbol: &'a str,
+    #[serde(serialize_with = "ser_hex")]
+    pub from: &'a [u8],
+    #[serde(serialize_with = "ser_hex")]
+    pub to: &'a [u8],
+    pub quantity: &'a str,  // 整数字符串 币的个数是 quantity/10^decimal
+    #[serde(serialize_with = "ser_u64_str")]
+    pub amount_usd: u64,  // 兼容以前的格式 用字符串
+    pub timestamp: u64,
+    pub decimal: u32,
+}
+
+/// 地址绑定的验证请求 POST /v1/account/verify
+#[serde(crate = "alt_serde")]
+#[derive(Serialize)]
+pub struct AddressVerifyRequest<'a> {
+    pub version: u32,
+    #[serde(serialize_with = "ser_hex")]
+    pub tx: &'a [u8],
+    pub symbol: &'a str,
+    #[serde(serialize_with = "ser_hex")]
+    pub account: &'a [u8],  // 绑定的地址
+}
+
+/// 字节按 0x 开头的16进制字符串发送
+fn ser_hex<S: Serializer>(bytes: &&[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&["0x", &hex::encode(bytes)].concat())
+}
+
+fn ser_u64_str<S: Serializer>(val: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(val)
+}
+
+/// 请求体序列化为json
+pub fn to_json_body<B: Serialize>(body: &B) -> StdResult<Vec<u8>> {
+    serde_json::to_vec(body).map_err(|_| "serialize request body failed")
+}
 
 
 #[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
@@ -68,49 +158,6 @@ pub fn vecchars_to_vecbytes <I: IntoIterator<Item = char> + Clone>(it: &I) -> Ve
     it.clone().into_iter().map(|c| c as u8).collect::<_>()
 }
 
-pub fn int_covert_str(inner: u64) ->Vec<u8>{
-    let mut x:u32 = 0;    //位数
-    let mut s :Vec<&str> = vec![]; //保存字符串
-    loop {
-        let r = inner / ((10 as u64).pow(x));
-        if r == 0 {
-            s.reverse();
-            return  s.join("").as_bytes().to_vec();
-        }
-        let r = r % 10;
-        s.push(num_to_char(r));
-        x += 1;
-    }
-}
-
-pub fn num_to_char<'a>(n:u64)->&'a str{
-    if n > 10{return ""}
-    match n{
-        0=>"0",
-        1=>"1",
-        2=>"2",
-        3=>"3",
-        4=>"4",
-        5=>"5",
-        6=>"6",
-        7=>"7",
-        8=>"8",
-        9=>"9",
-        _ => {""},
-    }
-}
-
-pub fn hex_to_u8<'a>(param: &'a [u8]) -> Vec<u8>{
-    // 将 param  首先转化为 16进制字符串,然后加上0x  . 将tx等16进制保持字符串传递
-    // 例如: param的十六进制形式为0x1122,变为"0x"+"1122"的字符串,然后编码为&[u8]
-    let hex_0x = "0x".as_bytes();
-    let tx_hex =  hex::encode(param);   // tx_hex 是 16进制的字符串
-    let tx_vec = &[hex_0x,tx_hex.as_bytes()].concat();
-
-    return tx_vec.to_vec();;
-}
-
-
 pub trait AccountIdPublicConver{
     type AccountId;
     fn into_account32(self)->Self::AccountId; // 转化为accountId
@@ -146,6 +193,57 @@ pub trait BaseLocalAuthorityTrait: timestamp::Trait + system::Trait + ReportTrai
         return (None,None);
     }
 
+    /// 开启了ocw验证的验证人数(验证结束需要的票数按这个算)
+    fn ocw_validator_count() -> u32 {
+        Self::FindAllAuthor::find_all_author()
+            .iter()
+            .filter(|v| <IsValidtorOcw<Self>>::get(v))
+            .count() as u32
+    }
+
+    /// 是否是开启了ocw验证的验证人
+    fn is_ocw_validator(account: &Self::AccountId) -> bool {
+        <IsValidtorOcw<Self>>::get(account) && Self::FindAllAuthor::find_all_author().contains(account)
+    }
+
+    /// 这个区块分给自己验证的项(最多 per_block 个)
+    /// items 是待验证的项和投票情况, 每一项在还没有投通过或是不通过的验证人里按 assignment 分配(没有结果的之后还可以投)
+    fn assigned_items<K>(
+        block: Self::BlockNumber,
+        account: &Self::AccountId,
+        assignment: VerifyAssignment,
+        items: impl Iterator<Item=(K, VerifyTally<Self::AccountId>)>,
+    ) -> Vec<K> {
+        let validators = Self::FindAllAuthor::find_all_author()
+            .into_iter()
+            .filter(|v| <IsValidtorOcw<Self>>::get(v))
+            .collect::<Vec<Self::AccountId>>();
+        if !validators.contains(account) {
+            return vec![];
+        }
+        // 用上一轮最后一个区块的hash错开起点, 一轮里分工不变
+        let round_start = assignment.round_start(block.saturated_into::<u32>());
+        let seed_block: Self::BlockNumber = round_start.saturating_sub(1).saturated_into();
+        let hash = <system::Module<Self>>::block_hash(seed_block).encode();
+        let seed = u32::decode(&mut &hash[..]).unwrap_or_default();
+
+        let mut assigned = vec![];
+        for (index, (item, tally)) in items.enumerate() {
+            if assigned.len() as u32 >= assignment.per_block {
+                break;
+            }
+            let candidates = validators.iter().filter(|v| !tally.has_decided(v)).collect::<Vec<_>>();
+            let mine = assignment.assignees(seed, index as u32, candidates.len() as u32)
+                .into_iter()
+                .any(|i| candidates[i as usize] == account);
+            if mine {
+                assigned.push(item);
+            }
+        }
+        debug::info!("第{:?}轮分到了{:?}项", round_start, assigned.len());
+        assigned
+    }
+
     fn fetch_json<'a>(remote_url: &'a [u8], body:Vec<u8>) -> StdResult<Vec<u8>>{  // http post
         let remote_url_str = core::str::from_utf8(remote_url)
             .map_err(|_| "Error in converting remote_url to string")?;
@@ -185,7 +283,7 @@ pub trait BaseLocalAuthorityTrait: timestamp::Trait + system::Trait + ReportTrai
         Ok(json_result)
     }
 
-    fn fetch_parse(resp_bytes: Vec<u8>) -> StdResult<u64> {
+    fn fetch_parse(resp_bytes: Vec<u8>) -> StdResult<ResponseStatus> {
         let resp_str = core::str::from_utf8(&resp_bytes).map_err(|_| "Error in fetch_parse")?;
         // Print out our fetched JSON string
         debug::info!("{}", resp_str);
@@ -195,53 +293,181 @@ pub trait BaseLocalAuthorityTrait: timestamp::Trait + system::Trait + ReportTrai
             serde_json::from_str(&resp_str).map_err(|_| "convert to ResponseStatus failed")?;
 
         debug::info!("获取到的状态是:{:?}", status.verify_status);
-        Ok(status.verify_status)
+        Ok(status)
+    }
+
+    /// 本地设置的验证服务地址(没有设置的用默认地址)
+    fn verifier_urls(key: &[u8], default: &[u8]) -> Vec<Vec<u8>> {
+        let urls = StorageValueRef::persistent(key)
+            .get::<Vec<Vec<u8>>>()
+            .unwrap_or(None)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|url| !url.is_empty())
+            .collect::<Vec<Vec<u8>>>();
+        if urls.is_empty() {
+            return vec![default.to_vec()];
+        }
+        urls
+    }
+
+    /// 验证人登记了验证服务的公钥时 需要检查返回结果的签名
+    fn response_auth<'a>(account: &Self::AccountId, tx: &'a [u8], symbol: &'a [u8]) -> Option<ResponseAuth<'a>> {
+        <VerifierKey<Self>>::get(account).map(|key| ResponseAuth { key, tx, symbol })
+    }
+
+    /// 按顺序请求验证服务, 失败了切换到下一个(都失败时返回最后一个错误)
+    /// 签名不对的结果当作验证服务作弊, 不再请求下一个
+    fn fetch_status_failover(remote_src: &[u8], urls: &[Vec<u8>], body: Vec<u8>, auth: Option<&ResponseAuth>) -> StdResult<u64> {
+        let mut result = Err("no verifier url");
+        for url in urls.iter() {
+            result = Self::fetch_status(remote_src, url, body.clone(), auth);
+            match result {
+                Ok(_) => break,
+                Err(e) if e == INVALID_RESPONSE_SIGNATURE => {
+                    debug::error!("验证服务 {:?} 返回的签名不对", core::str::from_utf8(url).unwrap_or(""));
+                    break;
+                },
+                Err(e) => debug::warn!("验证服务 {:?} 请求失败: {:?}", core::str::from_utf8(url).unwrap_or(""), e),
+            }
+        }
+        result
     }
 
     fn fetch_status<'a>(
         remote_src: &'a [u8],
         remote_url: &'a [u8],
-        body:Vec<u8>
+        body:Vec<u8>,
+        auth: Option<&ResponseAuth>,
     ) -> StdResult<u64> {
         let json = Self::fetch_json(remote_url, body)?; // http请求
-        let status = match remote_src {
+        let resp = match remote_src {
             src if src == b"localhost" => Self::fetch_parse(json)  // 解析
                 .map_err(|_| "fetch_price_from_localhost error"),
             _ => Err("Unknown remote source"),
         }?;
-        Ok(status)
+        if let Some(auth) = auth {
+            // 用节点的本地时间(毫秒), 链上的时间戳只是最新区块的时间, 同步落后时会把新的签名当成过期的
+            let now = sp_io::offchain::timestamp().unix_millis();
+            auth.check(&resp, now / 1000)?;
+        }
+        Ok(resp.verify_status)
+    }
+
+    /// 验证服务返回的结果签名不对, 以系统的名义提交一条 VerifierFault 的证据(同一个tx只算一次)
+    fn report_verifier_fault(account: Self::AccountId, tx: Vec<u8>) {
+        let reporter = <report::Module<Self>>::get_treasury_id();
+        if let Err(e) = <report::Module<Self>>::report_misbehaviour(report::OffenceKind::VerifierFault, reporter, account, tx) {
+            debug::error!("------verifier fault report failed: {:?}------", e);
+        }
     }
 
-    fn slash_validtor(account: Self::AccountId) {
-        // 首先对 ValidatorLocalSerErrCnt+ 1
-        // 如果超过了 20次才处罚
-        // 处罚了之后就恢复为0,并且设置 IsValidtorOcw 为false,防止多次处罚.下次需要前段手动重置为true
-        if <ValidatorLocalSerErrCnt<Self>>::get(&account) < 20 {
-            <ValidatorLocalSerErrCnt<Self>>::mutate(&account, |cnt| *cnt += 1);
-        } else {
-            Self::Currency1::remove_lock(register::REGISTER_ID, &account);
-            Self::Currency1::slash(&account, <IllegalPunishment<Self>>::get());
-            <ValidatorLocalSerErrCnt<Self>>::mutate(&account, |cnt| *cnt = 0);
-            <IsValidtorOcw<Self>>::insert(&account, false);
-            let now = <system::Module<Self>>::block_number();
-            <SlashValidator<Self>>::mutate(&account, |blocks| {
-                if blocks.len() > 20 {
-                    blocks.pop();
-                }
-                blocks.push(now)
-                }
-            );
+    /// 本地服务没开 按register的DowntimePenalty分阶段处理(警告/关闭ocw验证/惩罚)
+    /// 关闭了之后需要验证人重新开启 IsValidtorOcw
+    fn note_verifier_downtime(account: Self::AccountId) {
+        if <register::Module<Self>>::note_verifier_downtime(&account) == DowntimeStage::Slash {
+            <report::Module<Self>>::punish_verifier_downtime(&account);
         }
     }
 }
 
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core::offchain::{OffchainExt, testing::{TestOffchainExt, PendingRequest}};
+    use crate::Runtime;
 
+    const BODY: &[u8] = br#"{"tx":"0x01"}"#;
 
+    fn new_test_ext(requests: Vec<PendingRequest>) -> sp_io::TestExternalities {
+        let (offchain, state) = TestOffchainExt::new();
+        for request in requests {
+            state.write().expect_request(request);
+        }
+        let mut t = sp_io::TestExternalities::default();
+        t.register_extension(OffchainExt::new(offchain));
+        t
+    }
 
+    /// 验证服务收到的请求与返回的结果
+    fn post(url: &str, response: &[u8]) -> PendingRequest {
+        PendingRequest {
+            method: "POST".into(),
+            uri: url.into(),
+            body: BODY.to_vec(),
+            response: Some(response.to_vec()),
+            sent: true,
+            ..Default::default()
+        }
+    }
 
+    const SIGNED_AT: u64 = 1_600_000_000;
 
+    /// 验证服务用key签名的结果
+    fn signed_status(key: &sr25519::Pair, auth: &ResponseAuth) -> Vec<u8> {
+        let signature = key.sign(&auth.message(1, SIGNED_AT));
+        format!(r#"{{"verify_status":1,"timestamp":{},"signature":"0x{}"}}"#, SIGNED_AT, hex::encode(signature)).into_bytes()
+    }
 
+    fn failover(urls: &[&[u8]]) -> StdResult<u64> {
+        let urls = urls.iter().map(|url| url.to_vec()).collect::<Vec<_>>();
+        <Runtime as BaseLocalAuthorityTrait>::fetch_status_failover(b"localhost", &urls, BODY.to_vec(), None)
+    }
 
+    #[test]
+    fn verifier_urls_fall_back_to_default() {
+        new_test_ext(vec![]).execute_with(|| {
+            let urls = || <Runtime as BaseLocalAuthorityTrait>::verifier_urls(TX_VERIFIER_URLS_KEY, b"http://default");
+            assert_eq!(urls(), vec![b"http://default".to_vec()]);
+
+            // 空的地址会被忽略
+            StorageValueRef::persistent(TX_VERIFIER_URLS_KEY).set(&vec![b"http://a".to_vec(), vec![], b"http://b".to_vec()]);
+            assert_eq!(urls(), vec![b"http://a".to_vec(), b"http://b".to_vec()]);
+            StorageValueRef::persistent(TX_VERIFIER_URLS_KEY).set(&vec![Vec::<u8>::new()]);
+            assert_eq!(urls(), vec![b"http://default".to_vec()]);
+        });
+    }
 
+    #[test]
+    fn failover_moves_to_the_next_endpoint() {
+        new_test_ext(vec![
+            post("http://a", b"bad gateway"),
+            post("http://b", br#"{"verify_status":1}"#),
+        ]).execute_with(|| {
+            assert_eq!(failover(&[b"http://a", b"http://b", b"http://c"]), Ok(1));
+        });
+    }
 
+    #[test]
+    fn failover_returns_the_last_error() {
+        new_test_ext(vec![
+            post("http://a", b"bad gateway"),
+            post("http://b", b"{}"),
+        ]).execute_with(|| {
+            assert_eq!(failover(&[b"http://a", b"http://b"]), Err("fetch_price_from_localhost error"));
+            assert_eq!(failover(&[]), Err("no verifier url"));
+        });
+    }
+
+    #[test]
+    fn signed_status_age_is_checked_against_the_local_clock() {
+        use sp_core::Pair;
+        let key = sr25519::Pair::from_seed(&[1; 32]);
+        let auth = ResponseAuth { key: key.public(), tx: b"0x01", symbol: b"btc" };
+        let response = signed_status(&key, &auth);
+        let (offchain, state) = TestOffchainExt::new();
+        state.write().expect_request(post("http://a", &response));
+        state.write().expect_request(post("http://a", &response));
+        let mut t = sp_io::TestExternalities::default();
+        t.register_extension(OffchainExt::new(offchain));
+        t.execute_with(|| {
+            let fetch = || <Runtime as BaseLocalAuthorityTrait>::fetch_status(b"localhost", b"http://a", BODY.to_vec(), Some(&auth));
+            // 链上的时间戳还是0 只看本地时间
+            state.write().timestamp = Timestamp::from_unix_millis((SIGNED_AT + RESPONSE_MAX_AGE_SECS) * 1000);
+            assert_eq!(fetch(), Ok(1));
+            state.write().timestamp = Timestamp::from_unix_millis((SIGNED_AT + RESPONSE_MAX_AGE_SECS + 1) * 1000);
+            assert_eq!(fetch(), Err(INVALID_RESPONSE_SIGNATURE));
+        });
+    }
+}
@@ -2,22 +2,31 @@
 type BalanceOf<T> = <<T as Trait>::Currency1 as Currency<<T as system::Trait>::AccountId>>::Balance;
 use frame_support::traits::{Get,
 	Currency, ReservableCurrency, LockIdentifier,
-	WithdrawReasons, LockableCurrency, EnsureOrigin
+	WithdrawReasons, LockableCurrency, EnsureOrigin, ExistenceRequirement
 };
 use sp_std::{prelude::*, result::Result};
 use frame_support::{debug, ensure, decl_module, decl_storage, decl_error, decl_event, weights::{Weight},
-					StorageValue, StorageMap, StorageDoubleMap, Blake2_256};
+					StorageValue, StorageMap, StorageDoubleMap, IterableStorageMap, IterableStorageDoubleMap, Blake2_256, storage::migration};
 use frame_system as system;
+use sp_core::sr25519;
 use system::{ensure_signed, ensure_root};
-use sp_runtime::{DispatchResult, Perbill, Permill, Percent};
-use pallet_staking::{self,Trait as StakingTrait};
+use sp_runtime::{DispatchResult, Perbill, Permill, Percent, ModuleId, traits::{Saturating, Zero, AccountIdConversion}};
+use pallet_staking::{self,EraIndex,Trait as StakingTrait};
 use pallet_timestamp;
 use codec::{Encode, Decode};
-use crate::constants::{symbol::*, currency::DOLLARS};
+use crate::constants::{symbol::*, currency::DOLLARS, time::HOURS};
 use sp_std::convert::{TryInto,TryFrom, Into};
 
 pub const REGISTER_ID: LockIdentifier = *b"register";
 
+// 验证奖励池
+const VERIFY_POT_ID: ModuleId = ModuleId(*b"tx/verif");
+
+// 每个区块最多移出多少个到期的黑名单
+pub const MAX_BLACKLIST_EXPIRE_PER_BLOCK: usize = 50;
+// 每个区块最多处理多少个验证超时的项
+pub const MAX_VERIFY_TIMEOUT_PER_BLOCK: usize = 50;
+
 
 /// 机器状态
 #[cfg_attr(feature = "std", derive())]
@@ -65,6 +74,272 @@ pub struct MinerInfo<A, M, S> {
 
 
 
+/// 黑名单记录
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode, Clone, Default)]
+pub struct BlacklistInfo<BlockNumber> {
+	pub tx: Vec<u8>,  // 作弊的交易
+	pub reason: Vec<u8>,  // 进入黑名单的原因
+	pub report_id: Option<u32>,  // 由哪个举报引起(旧的记录没有)
+	pub expire_at: Option<BlockNumber>,  // 到哪个区块移出黑名单(None是永久)
+}
+
+
+
+/// 验证人的一票
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyVote {
+	Pass,  // 验证通过
+	Fail,  // 验证不通过
+	Error,  // 没有结果(本地服务没有返回或是请求失败)
+}
+
+impl VerifyVote {
+	/// 验证服务返回的状态码 0: 通过, 255: 没有收到返回的消息, 其他(200x 400x): 不通过
+	pub fn from_status(status: u64) -> Self {
+		match status {
+			0 => VerifyVote::Pass,
+			255 => VerifyVote::Error,
+			_ => VerifyVote::Fail,
+		}
+	}
+}
+
+
+/// 待验证tx的投票情况 每个验证人的通过/不通过只算一次
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode, Clone, Default)]
+pub struct VerifyTally<AccountId> {
+	pub passes: Vec<AccountId>,  // 投通过的验证人
+	pub fails: Vec<AccountId>,  // 投不通过的验证人
+	pub errors: Vec<AccountId>,  // 没有结果的验证人(不算票 之后还可以投通过或是不通过)
+}
+
+impl<AccountId: PartialEq + Clone> VerifyTally<AccountId> {
+	/// 已经投了通过或是不通过
+	pub fn has_decided(&self, who: &AccountId) -> bool {
+		self.passes.contains(who) || self.fails.contains(who)
+	}
+
+	/// 这一票是否有效 投过通过或是不通过的不能再投, 没有结果的只记一次
+	pub fn can_vote(&self, who: &AccountId, vote: VerifyVote) -> bool {
+		!self.has_decided(who) && (vote != VerifyVote::Error || !self.errors.contains(who))
+	}
+
+	/// 记一票 返回这一票是否有效(重复的不算)
+	pub fn vote(&mut self, who: &AccountId, vote: VerifyVote) -> bool {
+		if !self.can_vote(who, vote) {
+			return false;
+		}
+		match vote {
+			VerifyVote::Error => self.errors.push(who.clone()),
+			VerifyVote::Pass | VerifyVote::Fail => {
+				self.errors.retain(|a| a != who);
+				if vote == VerifyVote::Pass {
+					self.passes.push(who.clone());
+				} else {
+					self.fails.push(who.clone());
+				}
+			},
+		}
+		true
+	}
+}
+
+
+/// 结束验证需要的票数 按开启了ocw验证的验证人数的比例算(至少1票)
+/// 没有结果的不算票, 一直没有结论的tx按超时处理
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyQuorum {
+	pub pass: Perbill,  // 通过
+	pub fail: Perbill,  // 不通过
+}
+
+impl VerifyQuorum {
+	/// 挖矿tx的默认比例
+	pub fn tx_default() -> Self {
+		VerifyQuorum {
+			pass: Perbill::from_percent(34),
+			fail: Perbill::from_percent(34),
+		}
+	}
+
+	/// 地址绑定的默认比例
+	pub fn address_default() -> Self {
+		VerifyQuorum {
+			pass: Perbill::from_percent(50),
+			fail: Perbill::from_percent(60),
+		}
+	}
+
+	/// validators 个验证人时需要多少票
+	pub fn required(ratio: Perbill, validators: u32) -> u32 {
+		ratio.mul_ceil(validators).max(1)
+	}
+
+	/// 通过的票数是否够了
+	pub fn is_passed<AccountId>(&self, tally: &VerifyTally<AccountId>, validators: u32) -> bool {
+		tally.passes.len() as u32 >= Self::required(self.pass, validators)
+	}
+
+	/// 不通过的票数是否够了
+	pub fn is_failed<AccountId>(&self, tally: &VerifyTally<AccountId>, validators: u32) -> bool {
+		tally.fails.len() as u32 >= Self::required(self.fail, validators)
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.pass != Perbill::zero() && self.fail != Perbill::zero()
+	}
+}
+
+
+/// 验证超时后怎么处理
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPolicy {
+	Fail,  // 按不通过处理(不举报)
+	Pass,  // 按通过处理
+	Report,  // 按不通过处理, 并且以系统的名义举报
+}
+
+
+/// 待验证的项加入队列后 blocks 个区块还没有结论算超时, 超时后按 policy 处理
+/// 加入队列时按当时的设置算出超时的区块, 之后修改只对新加入的项生效
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyTimeout {
+	pub blocks: u32,
+	pub policy: TimeoutPolicy,
+}
+
+impl VerifyTimeout {
+	/// 挖矿tx默认1小时, 超时了交给举报投票
+	pub fn tx_default() -> Self {
+		VerifyTimeout {
+			blocks: HOURS,
+			policy: TimeoutPolicy::Report,
+		}
+	}
+
+	/// 地址绑定默认1小时, 超时了不激活(可以重新提交)
+	pub fn address_default() -> Self {
+		VerifyTimeout {
+			blocks: HOURS,
+			policy: TimeoutPolicy::Fail,
+		}
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.blocks > 0
+	}
+
+	/// 挖矿的收益在挖矿时已经发了, 挖矿tx按不通过处理和按通过处理没有区别, 所以不能设置成 Fail
+	pub fn is_valid_for_tx(&self) -> bool {
+		self.is_valid() && self.policy != TimeoutPolicy::Fail
+	}
+}
+
+
+/// 验证人的本地验证服务没有应答的分阶段处罚 按最近 window 个区块里没有应答的次数(同一个区块只算一次):
+/// 到 warn 次警告, 到 slash 次交给pallet_offences按 slash_fraction 惩罚, 到 disable 次关闭ocw验证(需要验证人重新开启)并清零
+/// 关闭ocw验证之后就不会再有记录, 所以关闭是最后一个阶段
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct DowntimePenalty {
+	pub window: u32,
+	pub warn: u32,
+	pub slash: u32,
+	pub disable: u32,
+	pub slash_fraction: Perbill,
+}
+
+impl Default for DowntimePenalty {
+	fn default() -> Self {
+		DowntimePenalty {
+			window: HOURS,
+			warn: 5,
+			slash: 10,
+			disable: 20,
+			slash_fraction: Perbill::from_percent(1),
+		}
+	}
+}
+
+impl DowntimePenalty {
+	pub fn is_valid(&self) -> bool {
+		self.window > 0 && self.warn > 0 && self.warn < self.slash && self.slash < self.disable
+	}
+
+	/// 窗口内第 count 次没有应答到了哪个阶段
+	pub fn stage(&self, count: u32) -> DowntimeStage {
+		if count >= self.disable {
+			DowntimeStage::Disable
+		} else if count == self.slash {
+			DowntimeStage::Slash
+		} else if count == self.warn {
+			DowntimeStage::Warn
+		} else {
+			DowntimeStage::None
+		}
+	}
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DowntimeStage {
+	None,
+	Warn,  // 警告
+	Slash,  // 惩罚
+	Disable,  // 关闭ocw验证
+}
+
+
+/// 链下验证的分工 每一轮(period 个区块)按上一轮最后一个区块的hash给待验证的项分配验证人
+/// 每一项分给 verifiers 个还没有投票的验证人, 每个验证人每个区块最多验证 per_block 项
+/// 一轮结束还没有结论的项下一轮换一批验证人
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyAssignment {
+	pub per_block: u32,  // 每个验证人每个区块最多验证几项
+	pub verifiers: u32,  // 每一项分给几个验证人
+	pub period: u32,  // 一轮多少个区块
+}
+
+impl Default for VerifyAssignment {
+	fn default() -> Self {
+		VerifyAssignment {
+			per_block: 5,
+			verifiers: 3,
+			period: 10,
+		}
+	}
+}
+
+impl VerifyAssignment {
+	pub fn is_valid(&self) -> bool {
+		self.per_block > 0 && self.verifiers > 0 && self.period > 0
+	}
+
+	/// block 所在的这一轮从哪个区块开始
+	pub fn round_start(&self, block: u32) -> u32 {
+		block - block % self.period
+	}
+
+	/// 第 index 项分给了哪些验证人(candidates 里的下标)
+	/// 起点按这一轮的 seed 错开, 相邻的项依次往后排, 分给 min(verifiers, candidates) 个不同的验证人
+	pub fn assignees(&self, seed: u32, index: u32, candidates: u32) -> Vec<u32> {
+		if candidates == 0 {
+			return vec![];
+		}
+		let start = (seed as u64 + index as u64 * self.verifiers as u64) % candidates as u64;
+		(0..self.verifiers.min(candidates) as u64)
+			.map(|i| ((start + i) % candidates as u64) as u32)
+			.collect()
+	}
+}
+
+
 pub trait Trait: StakingTrait + pallet_timestamp::Trait + system::Trait{
 
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
@@ -80,6 +355,9 @@ pub trait Trait: StakingTrait + pallet_timestamp::Trait + system::Trait{
 	type UnBondTime: Get<Self::BlockNumber>;
 
 	type RegisterSetOrigin: EnsureOrigin<Self::Origin>;
+
+	// 国库(验证奖励的预算从这里转入奖励池)
+	type TreasuryId: Get<ModuleId>;
 }
 
 
@@ -96,11 +374,17 @@ decl_storage! {
 		/// 临时存储个人此时的币种地址  AccountId, symbol => (token_address, AddressStatus, tx, symbol)
 		pub TokenInfo: double_map hasher(blake2_128_concat) T::AccountId,  hasher(blake2_128_concat) Vec<u8> => (Vec<u8>, AddressStatus, Vec<u8>, Vec<u8>);
 
-        // 设置状态位  u32表现形式1xxx.初始值为 1000,低3位分别表示 验证通过次数,验证失败次数,非正常情况返回次数, tx => 1xxx,AccountId, symbol
-		pub TokenStatus get(fn tx_status): map hasher(blake2_128_concat) Vec<u8> => (u64,T::AccountId,Vec<u8>);
+		/// 待验证的地址绑定 tx => (投票情况, AccountId, symbol)
+		pub TokenStatus get(fn tx_status): map hasher(blake2_128_concat) Vec<u8> => (VerifyTally<T::AccountId>,T::AccountId,Vec<u8>);
 		// 记录 TokenStatus 的长度,防止队列过大
 		pub TokenStatusLen: u32;
 
+		/// 待验证的地址绑定tx什么时候加入队列, 什么时候超时 tx => (加入队列的区块, 超时的区块)
+		pub TokenStatusPending get(fn token_status_pending): map hasher(blake2_128_concat) Vec<u8> => Option<(T::BlockNumber, T::BlockNumber)>;
+
+		/// 地址绑定tx在哪个区块超时 (区块高度 => tx)
+		pub TokenStatusExpiry get(fn token_status_expiry): map hasher(twox_64_concat) T::BlockNumber => Vec<Vec<u8>>;
+
 		/// 硬件id对应的账户（保证一个硬件id只能注册一次)
 		pub AllRegisters get(fn allregisters):  map hasher(blake2_128_concat)  Vec<u8> => T::AccountId;
 
@@ -114,7 +398,34 @@ decl_storage! {
 		pub PerMinerUsingAddress get(fn per_miner_using_address): map hasher(blake2_128_concat)  T::AccountId => Vec<(Vec<u8>, Vec<u8>)>;
 
 		/// 某个账户是否进入黑名单
-		pub BlackList get(fn blacklist): map hasher(blake2_128_concat)  T::AccountId => Option<Vec<u8>>;
+		pub BlackList get(fn blacklist): map hasher(blake2_128_concat)  T::AccountId => Option<BlacklistInfo<T::BlockNumber>>;
+
+		/// 黑名单在哪个区块到期 (区块高度 => 账户)
+		pub BlacklistExpiry get(fn blacklist_expiry): map hasher(twox_64_concat) T::BlockNumber => Vec<T::AccountId>;
+
+		/// 进入黑名单多久(None是永久)
+		pub BlacklistDuration get(fn blacklist_duration): Option<T::BlockNumber>;
+
+		/// 挖矿tx验证结束需要的票数比例
+		pub TxVerifyQuorum get(fn tx_verify_quorum): VerifyQuorum = VerifyQuorum::tx_default();
+
+		/// 地址绑定验证结束需要的票数比例
+		pub AddressVerifyQuorum get(fn address_verify_quorum): VerifyQuorum = VerifyQuorum::address_default();
+
+		/// 挖矿tx的链下验证分工
+		pub TxVerifyAssignment get(fn tx_verify_assignment): VerifyAssignment;
+
+		/// 地址绑定的链下验证分工
+		pub AddressVerifyAssignment get(fn address_verify_assignment): VerifyAssignment;
+
+		/// 挖矿tx的验证超时
+		pub TxVerifyTimeout get(fn tx_verify_timeout): VerifyTimeout = VerifyTimeout::tx_default();
+
+		/// 地址绑定的验证超时
+		pub AddressVerifyTimeout get(fn address_verify_timeout): VerifyTimeout = VerifyTimeout::address_default();
+
+		/// 存储的版本(用于升级时迁移数据)
+		pub StorageVersion get(fn storage_version): u32;
 
 		/// 全网矿工数
 		pub MinersCount: u64;
@@ -131,11 +442,30 @@ decl_storage! {
 		/// validtors 自愿开启ocw验证功能
 		pub IsValidtorOcw get(fn is_validtor_ocw): map hasher(blake2_128_concat) T::AccountId => bool = false;
 
-		/// 记录 validtors node 没有开启本地服务导致的验证出错次数
-       pub ValidatorLocalSerErrCnt get(fn validator_local_ser_err_cnt): map hasher(blake2_128_concat) T::AccountId => u32=0;
+		/// ERC20币种的合约(小写的币种 => (20字节的地址, 合约的精度)), 链下直接请求ETH节点验证时只认这个合约的Transfer
+		/// Transfer的金额是合约精度的原始数量, 要按合约的精度换算后再与挖矿记录的金额比较
+		pub TokenContract get(fn token_contract): map hasher(blake2_128_concat) Vec<u8> => Option<(Vec<u8>, u32)>;
+
+		/// 验证人的本地验证服务没有应答的区块(只保留窗口内的, 最多 disable 个)
+		pub VerifierDowntime get(fn verifier_downtime): map hasher(blake2_128_concat) T::AccountId => Vec<T::BlockNumber>;
+
+		/// 本地验证服务没有应答的分阶段处罚
+		pub VerifierDowntimePenalty get(fn verifier_downtime_penalty): DowntimePenalty = DowntimePenalty::default();
+
+		/// 每个era从国库转入验证奖励池的金额(0是不转入, 别人转入奖励池的照样发放)
+		pub VerifyRewardPerEra get(fn verify_reward_per_era): BalanceOf<T>;
 
-      /// 记录惩罚的时间, 数量最大 20次
-      pub SlashValidator get(fn slash_validator): map hasher(blake2_128_concat) T::AccountId => Vec<T::BlockNumber>;
+		/// 验证人在某个era里与最终结论一致的票数 (era, stash) => 票数
+		pub VerifyPoints get(fn verify_points): double_map hasher(twox_64_concat) EraIndex, hasher(blake2_128_concat) T::AccountId => u32;
+
+		/// 某个era里与最终结论一致的总票数
+		pub EraVerifyPoints get(fn era_verify_points): map hasher(twox_64_concat) EraIndex => u32;
+
+		/// 还没有发放验证奖励的era
+		pub VerifyRewardEra get(fn verify_reward_era): Option<EraIndex>;
+
+		/// 验证人登记的验证服务公钥(登记了的验证人, 链下工作机只接受签名正确的验证结果)
+		pub VerifierKey get(fn verifier_key): map hasher(blake2_128_concat) T::AccountId => Option<sr25519::Public>;
 	}
 }
 
@@ -150,9 +480,15 @@ decl_error! {
 		/// 已经注册（不能再注册）
 		AlreadyRegisted,
 
-		/// 被举报进入黑名单的成员（不能注册)
+		/// 被举报进入黑名单的成员（永久不能注册)
 		InBlackList,
 
+		/// 在黑名单里 还没有到期(到期区块见BlackList的expire_at)
+		BannedTemporarily,
+
+		/// 不在黑名单里
+		NotInBlackList,
+
 		/// 硬件id已经被使用
 		HardIdBeenUsed,
 
@@ -205,6 +541,21 @@ decl_error! {
 		CallNotAllowed,
 
 		NotController,
+
+		/// 比例不能为0
+		InvalidQuorum,
+
+		/// 分工的参数不能为0
+		InvalidAssignment,
+
+		/// 超时的区块数不能为0
+		InvalidTimeout,
+
+		/// 合约地址要是20字节
+		InvalidTokenContract,
+
+		/// 阶段的次数要满足 0 < warn < slash < disable, 窗口不能为0
+		InvalidDowntimePenalty,
 	}
 }
 
@@ -239,8 +590,8 @@ decl_module! {
 			ensure!(!<AllMiners<T>>::contains_key(who.clone()), Error::<T>::AlreadyRegisted);
 			// 账户已经存在不需要注册！
 
-			ensure!(!<BlackList<T>>::contains_key(who.clone()),  Error::<T>::InBlackList);
-			// 如果账户已经进入黑名单， 则不能再注册
+			Self::ensure_not_blacklisted(&who)?;
+			// 如果账户已经进入黑名单， 则不能再注册(临时黑名单到期后可以注册)
 
 			ensure!(!<AllRegisters<T>>::contains_key(hardware_id.clone()), Error::<T>::HardIdBeenUsed);
 			// 硬件已经被注册则不能再次注册。
@@ -389,16 +740,14 @@ decl_module! {
 
 			<TokenInfo<T>>::insert(who.clone(), symbol.clone(), (tokenaddress.clone(), AddressStatus::default(), tx.clone(), symbol.clone()));
 
-			// 初始化状态码
-			let curent_status = <TokenStatus<T>>::get(tx.clone()).0;
-			// 等于0表示没被占用,继续向下执行
-			ensure!(<TokenStatus<T>>::get(tx.clone()).0 == 0, Error::<T>::TxInUsing);
+			// 不在验证队列里才能使用
+			ensure!(!<TokenStatus<T>>::contains_key(tx.clone()), Error::<T>::TxInUsing);
 			debug::RuntimeLogger::init();
 			debug::info!("当前长度: {:?}",TokenStatusLen::get());
 			ensure!(TokenStatusLen::get() <= T::TxsMaxCount::get(), Error::<T>::OverMaximum);
-			<TokenStatus<T>>::insert(tx.clone(),(1000,who.clone(), symbol.clone()));
-			debug::info!("TokenStatus 初始化状态为:{:?}",<TokenStatus<T>>::get(tx.clone()).0);
+			<TokenStatus<T>>::insert(tx.clone(),(VerifyTally::default(),who.clone(), symbol.clone()));
 			TokenStatusLen::mutate(|n|*n += 1);
+			Self::schedule_address_timeout(tx.clone(), <system::Module<T>>::block_number());
 			Self::deposit_event(RawEvent::AddTokenInfoEvent(who, symbol));
 			Ok(())
 
@@ -470,6 +819,141 @@ decl_module! {
 		}
 
 
+		/// 设置进入黑名单多久(None是永久 只影响以后进入黑名单的账户)
+		#[weight = 500_000]
+		fn set_blacklist_duration(origin, duration: Option<T::BlockNumber>){
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			<BlacklistDuration<T>>::set(duration);
+			Self::deposit_event(RawEvent::SetBlacklistDuration);
+
+		}
+
+
+		/// 设置挖矿tx验证结束需要的票数比例
+		#[weight = 500_000]
+		fn set_tx_verify_quorum(origin, quorum: VerifyQuorum) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(quorum.is_valid(), Error::<T>::InvalidQuorum);
+			<TxVerifyQuorum>::put(quorum);
+			Self::deposit_event(RawEvent::SetTxVerifyQuorum);
+			Ok(())
+		}
+
+
+		/// 设置地址绑定验证结束需要的票数比例
+		#[weight = 500_000]
+		fn set_address_verify_quorum(origin, quorum: VerifyQuorum) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(quorum.is_valid(), Error::<T>::InvalidQuorum);
+			<AddressVerifyQuorum>::put(quorum);
+			Self::deposit_event(RawEvent::SetAddressVerifyQuorum);
+			Ok(())
+		}
+
+
+		/// 设置挖矿tx的链下验证分工
+		#[weight = 500_000]
+		fn set_tx_verify_assignment(origin, assignment: VerifyAssignment) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(assignment.is_valid(), Error::<T>::InvalidAssignment);
+			<TxVerifyAssignment>::put(assignment);
+			Self::deposit_event(RawEvent::SetTxVerifyAssignment);
+			Ok(())
+		}
+
+
+		/// 设置地址绑定的链下验证分工
+		#[weight = 500_000]
+		fn set_address_verify_assignment(origin, assignment: VerifyAssignment) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(assignment.is_valid(), Error::<T>::InvalidAssignment);
+			<AddressVerifyAssignment>::put(assignment);
+			Self::deposit_event(RawEvent::SetAddressVerifyAssignment);
+			Ok(())
+		}
+
+
+		/// 设置挖矿tx的验证超时
+		#[weight = 500_000]
+		fn set_tx_verify_timeout(origin, timeout: VerifyTimeout) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(timeout.is_valid_for_tx(), Error::<T>::InvalidTimeout);
+			<TxVerifyTimeout>::put(timeout);
+			Self::deposit_event(RawEvent::SetTxVerifyTimeout);
+			Ok(())
+		}
+
+
+		/// 设置地址绑定的验证超时
+		#[weight = 500_000]
+		fn set_address_verify_timeout(origin, timeout: VerifyTimeout) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(timeout.is_valid(), Error::<T>::InvalidTimeout);
+			<AddressVerifyTimeout>::put(timeout);
+			Self::deposit_event(RawEvent::SetAddressVerifyTimeout);
+			Ok(())
+		}
+
+
+		/// 把账户移出黑名单
+		#[weight = 500_000]
+		pub fn unblacklist(origin, who: T::AccountId) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(<BlackList<T>>::contains_key(&who), Error::<T>::NotInBlackList);
+			Self::remove_from_blacklist(&who);
+			Ok(())
+		}
+
+
+		fn on_runtime_upgrade() -> Weight {
+			Self::migrate()
+		}
+
+
+		// 新的era开始后发放上一个era的验证奖励
+		fn on_initialize(_n: T::BlockNumber) -> Weight {
+			Self::settle_verify_rewards()
+		}
+
+
+		// 移出到期的黑名单
+		fn on_finalize(n: T::BlockNumber) {
+			Self::remove_expired_blacklist(n);
+		}
+
+
 		#[weight = 500_000]
 		fn set_validator_ocw(origin,is_ocw: bool) -> DispatchResult {
 			// 通过 controller 找到对应的stash账号
@@ -493,13 +977,83 @@ decl_module! {
 			<IsValidtorOcw<T>>::insert(stash,is_ocw);
 			Ok(())
 		}
+
+
+		/// 设置本地验证服务没有应答的分阶段处罚
+		#[weight = 500_000]
+		fn set_verifier_downtime_penalty(origin, penalty: DowntimePenalty) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			ensure!(penalty.is_valid(), Error::<T>::InvalidDowntimePenalty);
+			<VerifierDowntimePenalty>::put(penalty);
+			Self::deposit_event(RawEvent::SetVerifierDowntimePenalty);
+			Ok(())
+		}
+
+
+		/// 设置每个era从国库转入验证奖励池的金额
+		#[weight = 500_000]
+		fn set_verify_reward_per_era(origin, amount: BalanceOf<T>) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			<VerifyRewardPerEra<T>>::put(amount);
+			Self::deposit_event(RawEvent::SetVerifyRewardPerEra);
+			Ok(())
+		}
+
+
+		/// 登记(或是用None取消)验证服务对结果签名用的公钥
+		#[weight = 500_000]
+		fn set_verifier_key(origin, key: Option<sr25519::Public>) -> DispatchResult {
+			// 与 set_validator_ocw 一样用 controller 签名, 记在stash账号下
+			let controller = ensure_signed(origin)?;
+			let ledger = <pallet_staking::Module<T>>::ledger(&controller).ok_or(Error::<T>::NotController)?;
+			let stash = ledger.stash;
+
+			match key {
+				Some(key) => <VerifierKey<T>>::insert(&stash, key),
+				None => <VerifierKey<T>>::remove(&stash),
+			}
+			Self::deposit_event(RawEvent::SetVerifierKey(stash));
+			Ok(())
+		}
+
+
+		/// 登记(或是用None取消)ERC20币种的合约地址与合约的精度(decimals)
+		#[weight = 500_000]
+		fn set_token_contract(origin, symbol: Vec<u8>, contract: Option<(Vec<u8>, u32)>) -> DispatchResult {
+
+			T::RegisterSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			let symbol = symbol.to_ascii_lowercase();
+			match contract {
+				Some((contract, decimals)) => {
+					// u128最多能表示10^38
+					ensure!(contract.len() == 20 && decimals <= 38, Error::<T>::InvalidTokenContract);
+					<TokenContract>::insert(&symbol, (contract, decimals));
+				},
+				None => <TokenContract>::remove(&symbol),
+			}
+			Self::deposit_event(RawEvent::SetTokenContract(symbol));
+			Ok(())
+		}
 	}
 }
 
 decl_event!(
 	pub enum Event<T> where
 	 <T as system::Trait>::AccountId,
-	 <T as pallet_timestamp::Trait>::Moment {
+	 <T as system::Trait>::BlockNumber,
+	 <T as pallet_timestamp::Trait>::Moment,
+	 Balance = BalanceOf<T> {
 		// Just a dummy event.
 
 		RegisterEvent(u64, AccountId, Moment),
@@ -509,12 +1063,307 @@ decl_event!(
 		WithdrawUnbond(AccountId),
 		Withdraw(AccountId),
 		SetPledgeAmount,
+		SetBlacklistDuration,
+		SetTxVerifyQuorum,
+		SetAddressVerifyQuorum,
+		SetTxVerifyAssignment,
+		SetAddressVerifyAssignment,
+		SetTxVerifyTimeout,
+		SetAddressVerifyTimeout,
+		// 登记了验证服务的公钥(stash账号)
+		SetVerifierKey(AccountId),
+		SetTokenContract(Vec<u8>),
+		SetVerifyRewardPerEra,
+		SetVerifierDowntimePenalty,
+		// 本地验证服务没有应答(验证人, 窗口内的次数)
+		VerifierWarned(AccountId, u32),
+		VerifierSlashed(AccountId, u32),
+		VerifierDisabled(AccountId, u32),
+		// 发放验证奖励(era, 验证人, 金额)
+		VerifyReward(EraIndex, AccountId, Balance),
+
+		// 进入黑名单(账户, 到期区块)
+		Blacklisted(AccountId, Option<BlockNumber>),
+		// 被治理移出黑名单
+		Unblacklisted(AccountId),
+		// 黑名单到期
+		BlacklistExpired(AccountId),
 	}
 );
 
 impl <T: Trait> Module <T> {
 
 
+	/// 是否在黑名单里(已经到期 但是还没有清理的不算)
+	pub fn is_blacklisted(who: &T::AccountId) -> bool {
+		match <BlackList<T>>::get(who) {
+			Some(info) => match info.expire_at {
+				Some(expire_at) => expire_at > <system::Module<T>>::block_number(),
+				None => true,
+			},
+			None => false,
+		}
+	}
+
+
+	/// 不在黑名单里 否则说明是永久的还是要等到什么时候
+	fn ensure_not_blacklisted(who: &T::AccountId) -> DispatchResult {
+		let info = match <BlackList<T>>::get(who) {
+			Some(info) => info,
+			None => return Ok(()),
+		};
+		let now = <system::Module<T>>::block_number();
+		match info.expire_at {
+			None => Err(Error::<T>::InBlackList.into()),
+			Some(expire_at) if expire_at > now => {
+				debug::warn!("-----{:?} is blacklisted until block {:?}, {:?} blocks left-----", who, expire_at, expire_at - now);
+				Err(Error::<T>::BannedTemporarily.into())
+			},
+			_ => Ok(()),
+		}
+	}
+
+
+	/// 加入黑名单(到期时间按BlacklistDuration算)
+	pub fn add_to_blacklist(who: T::AccountId, tx: Vec<u8>, reason: Vec<u8>, report_id: Option<u32>) {
+		let expire_at = <BlacklistDuration<T>>::get()
+			.map(|d| <system::Module<T>>::block_number() + d);
+		if let Some(at) = expire_at {
+			<BlacklistExpiry<T>>::mutate(at, |q| q.push(who.clone()));
+		}
+		<BlackList<T>>::insert(&who, BlacklistInfo{ tx, reason, report_id, expire_at });
+		Self::deposit_event(RawEvent::Blacklisted(who, expire_at));
+	}
+
+
+	/// 移出黑名单(到期队列里的账户到期时会被跳过)
+	pub fn remove_from_blacklist(who: &T::AccountId) {
+		if <BlackList<T>>::take(who).is_some() {
+			Self::deposit_event(RawEvent::Unblacklisted(who.clone()));
+		}
+	}
+
+
+	/// 移出这个区块到期的黑名单 每个区块最多处理MAX_BLACKLIST_EXPIRE_PER_BLOCK个 剩下的留到下一个区块
+	fn remove_expired_blacklist(n: T::BlockNumber) {
+		let mut expiring = <BlacklistExpiry<T>>::take(n);
+		if expiring.is_empty() {
+			return;
+		}
+		if expiring.len() > MAX_BLACKLIST_EXPIRE_PER_BLOCK {
+			let rest = expiring.split_off(MAX_BLACKLIST_EXPIRE_PER_BLOCK);
+			<BlacklistExpiry<T>>::mutate(n + T::BlockNumber::from(1u32), |q| q.extend(rest));
+		}
+
+		for who in expiring {
+			// 已经被移出或是重新进入黑名单的跳过
+			match <BlackList<T>>::get(&who).and_then(|info| info.expire_at) {
+				Some(expire_at) if expire_at <= n => {
+					<BlackList<T>>::remove(&who);
+					Self::deposit_event(RawEvent::BlacklistExpired(who));
+				},
+				_ => continue,
+			}
+		}
+	}
+
+
+	/// 返回迁移用掉的weight(按读写的次数算)
+	fn migrate() -> Weight {
+		let version = <StorageVersion>::get();
+		let mut weight = T::DbWeight::get().reads(1);
+		if version < 1 {
+			weight = weight.saturating_add(Self::migrate_v1());
+		}
+		if version < 2 {
+			weight = weight.saturating_add(Self::migrate_v2());
+		}
+		if version < 3 {
+			weight = weight.saturating_add(Self::migrate_v3());
+		}
+		if version < 4 {
+			weight = weight.saturating_add(Self::migrate_v4());
+		}
+		weight
+	}
+
+
+	/// 旧的黑名单只保存了tx 改成BlacklistInfo(永久)
+	fn migrate_v1() -> Weight {
+		let translated = sp_std::cell::Cell::new(0u64);
+		<BlackList<T>>::translate::<Vec<u8>, _>(|_who, tx| {
+			translated.set(translated.get() + 1);
+			Some(BlacklistInfo{
+				tx,
+				reason: vec![],
+				report_id: None,
+				expire_at: None,
+			})
+		});
+		<StorageVersion>::put(1);
+		T::DbWeight::get().reads_writes(translated.get(), translated.get() + 1)
+	}
+
+
+	/// 旧的TokenStatus是1000开始的计数 改成VerifyTally
+	/// 旧的计数不知道是哪个验证人投的 迁移后重新投票
+	fn migrate_v2() -> Weight {
+		let translated = sp_std::cell::Cell::new(0u64);
+		<TokenStatus<T>>::translate::<(u64, T::AccountId, Vec<u8>), _>(|_tx, (status, who, symbol)| {
+			translated.set(translated.get() + 1);
+			if status < 1000 {
+				return None;
+			}
+			Some((VerifyTally::default(), who, symbol))
+		});
+		TokenStatusLen::put(<TokenStatus<T>>::iter().count() as u32);
+		<StorageVersion>::put(2);
+		T::DbWeight::get().reads_writes(2 * translated.get(), translated.get() + 2)
+	}
+
+
+	/// 记一次本地验证服务没有应答 返回到了哪个阶段(惩罚由调用的模块执行)
+	pub fn note_verifier_downtime(who: &T::AccountId) -> DowntimeStage {
+		let now = <system::Module<T>>::block_number();
+		let penalty = <VerifierDowntimePenalty>::get();
+		let window_start = now.saturating_sub(penalty.window.into());
+		let mut blocks = <VerifierDowntime<T>>::get(who);
+		if blocks.last() == Some(&now) {
+			return DowntimeStage::None;
+		}
+		blocks.retain(|b| *b > window_start);
+		blocks.push(now);
+
+		let count = blocks.len() as u32;
+		let stage = penalty.stage(count);
+		match stage {
+			DowntimeStage::Disable => {
+				<VerifierDowntime<T>>::remove(who);
+				<IsValidtorOcw<T>>::insert(who, false);
+				Self::deposit_event(RawEvent::VerifierDisabled(who.clone(), count));
+			},
+			DowntimeStage::Slash => {
+				<VerifierDowntime<T>>::insert(who, blocks);
+				Self::deposit_event(RawEvent::VerifierSlashed(who.clone(), count));
+			},
+			DowntimeStage::Warn => {
+				<VerifierDowntime<T>>::insert(who, blocks);
+				Self::deposit_event(RawEvent::VerifierWarned(who.clone(), count));
+			},
+			DowntimeStage::None => <VerifierDowntime<T>>::insert(who, blocks),
+		}
+		stage
+	}
+
+
+	/// 验证奖励池账号
+	pub fn verify_pot_account() -> T::AccountId {
+		VERIFY_POT_ID.into_account()
+	}
+
+
+	/// 验证结束时 投票与结论一致的验证人各记一票
+	pub fn note_correct_votes(voters: &[T::AccountId]) {
+		let era = match <pallet_staking::Module<T>>::active_era() {
+			Some(active) => active.index,
+			None => return,
+		};
+		for who in voters {
+			<VerifyPoints<T>>::mutate(era, who, |points| *points = points.saturating_add(1));
+		}
+		EraVerifyPoints::mutate(era, |points| *points = points.saturating_add(voters.len() as u32));
+	}
+
+
+	/// 新的era开始后 发放上一个era的验证奖励(返回用掉的weight)
+	fn settle_verify_rewards() -> Weight {
+		let db = T::DbWeight::get();
+		let active = match <pallet_staking::Module<T>>::active_era() {
+			Some(active) => active.index,
+			None => return db.reads(1),
+		};
+		let weight = match <VerifyRewardEra>::get() {
+			Some(era) if era == active => return db.reads(2),
+			Some(era) => Self::pay_verify_rewards(era),
+			None => 0,
+		};
+		<VerifyRewardEra>::put(active);
+		weight.saturating_add(db.reads_writes(2, 1))
+	}
+
+
+	/// 国库先转入这个era的预算, 然后把奖励池按票数的比例发给验证人(没有票的era留到下一个era)
+	/// 投过票的只有验证人 weight按这个era里有票的验证人个数算
+	fn pay_verify_rewards(era: EraIndex) -> Weight {
+		let db = T::DbWeight::get();
+		let pot = Self::verify_pot_account();
+		let budget = <VerifyRewardPerEra<T>>::get();
+		let mut weight = db.reads(1);
+		if !budget.is_zero() {
+			let treasury = T::TreasuryId::get().into_account();
+			let available = T::Currency1::free_balance(&treasury).saturating_sub(T::Currency1::minimum_balance());
+			let amount = budget.min(available);
+			if !amount.is_zero() {
+				let _ = T::Currency1::transfer(&treasury, &pot, amount, ExistenceRequirement::KeepAlive);
+			}
+			weight = weight.saturating_add(db.reads_writes(2, 2));
+		}
+
+		let total = EraVerifyPoints::take(era);
+		let points = <VerifyPoints<T>>::drain_prefix(era).collect::<Vec<_>>();
+		let voters = points.len() as u64;
+		weight = weight.saturating_add(db.reads_writes(voters + 1, voters + 1));
+		if total == 0 {
+			return weight;
+		}
+		let pool = T::Currency1::free_balance(&pot).saturating_sub(T::Currency1::minimum_balance());
+		// 每个验证人一次转账(读写奖励池与验证人的账户)和一个事件
+		weight = weight.saturating_add(db.reads_writes(2 * voters + 1, 3 * voters));
+		for (who, point) in points {
+			let reward = Perbill::from_rational_approximation(point, total) * pool;
+			if reward.is_zero() {
+				continue;
+			}
+			// 对方账号不存在并且金额小于最小存活金额的不发
+			if T::Currency1::transfer(&pot, &who, reward, ExistenceRequirement::KeepAlive).is_ok() {
+				Self::deposit_event(RawEvent::VerifyReward(era, who, reward));
+			}
+		}
+		weight
+	}
+
+
+	/// 以前的地址绑定tx没有超时 从升级的区块开始算
+	fn migrate_v3() -> Weight {
+		let now = <system::Module<T>>::block_number();
+		let mut count = 0u64;
+		for (tx, _) in <TokenStatus<T>>::iter() {
+			count += 1;
+			Self::schedule_address_timeout(tx, now);
+		}
+		<StorageVersion>::put(3);
+		T::DbWeight::get().reads_writes(3 * count + 2, 2 * count + 1)
+	}
+
+
+	/// 本地验证服务没有应答的次数改成窗口内的记录 删除以前的计数与惩罚记录
+	fn migrate_v4() -> Weight {
+		// 每个验证人最多各有一条
+		let validators = <pallet_staking::Module<T>>::validator_count() as u64;
+		migration::remove_storage_prefix(b"TemplateModule", b"ValidatorLocalSerErrCnt", &[]);
+		migration::remove_storage_prefix(b"TemplateModule", b"SlashValidator", &[]);
+		<StorageVersion>::put(4);
+		T::DbWeight::get().reads_writes(1, 2 * validators + 1)
+	}
+
+	/// 记下地址绑定tx加入队列的区块和超时的区块
+	fn schedule_address_timeout(tx: Vec<u8>, now: T::BlockNumber) {
+		let deadline = now.saturating_add(<AddressVerifyTimeout>::get().blocks.into());
+		<TokenStatusPending<T>>::insert(&tx, (now, deadline));
+		<TokenStatusExpiry<T>>::mutate(deadline, |txs| txs.push(tx));
+	}
+
+
 	pub fn kill_man(who: T::AccountId) {
 
 			let hardware_id = <AllMiners<T>>::get(who.clone()).hardware_id;
@@ -1,8 +1,9 @@
 
-use frame_support::{decl_module, decl_storage, decl_event, decl_error, weights::{Weight}, ensure, debug, StorageMap, StorageValue};
+use frame_support::{decl_module, decl_storage, decl_event, decl_error, weights::{Weight}, ensure, debug, StorageMap, StorageValue, StorageDoubleMap, IterableStorageMap, IterableStorageDoubleMap};
+use frame_support::storage::migration::{StorageIterator, take_storage_value};
 use frame_system as system;
 use system::{ensure_signed, ensure_root};
-use sp_std::{prelude::*, result, cmp, collections::btree_set::BTreeSet};
+use sp_std::{prelude::*, result, cmp, collections::btree_set::BTreeSet, marker::PhantomData};
 use sp_std::{result::Result};
 use pallet_balances as balances;
 use sp_std::convert::{TryInto,TryFrom, Into};
@@ -10,26 +11,108 @@ use codec::{Encode, Decode};
 use sp_runtime::{DispatchResult, DispatchError, traits::{Hash}};
 use frame_support::traits::{Get,
 	Currency, ReservableCurrency, OnUnbalanced, Contains, EnsureOrigin, IsDeadAccount,
-	GetMembers, ReportedTxs, LockableCurrency,
+	GetMembers, ReportedTxs, SuspiciousTxs, LockableCurrency, ExistenceRequirement,
+	IsStaker, LockIdentifier, WithdrawReasons,
 };
-use sp_runtime::{Permill, ModuleId};
+use sp_runtime::{Permill, Perbill, ModuleId};
+use sp_core::H256;
 use sp_runtime::traits::{
-	Zero, StaticLookup, AccountIdConversion, Saturating,
+	Zero, StaticLookup, AccountIdConversion, Saturating, SaturatedConversion, Convert,
 };
-use crate::register::{AllMiners, BlackList, Trait as RegisterTrait};
+use sp_staking::{SessionIndex, offence::{Offence, ReportOffence, Kind}};
+use pallet_session::historical::IdentificationTuple;
+use crate::register::{AllMiners, Trait as RegisterTrait};
 use crate::register::{self, PledgeAmount, REGISTER_ID};
 use crate::constants::{time::*, currency::*};
 use crate::mine_linked::{MineTag};
 use crate::mine::{self, OwnerMineRecord};
 use pallet_elections_phragmen as elections_phragmen;
+pub use pallet_democracy::Conviction;
+pub use node_report_rpc_runtime_api::{ReporterReputation, AppealStatus, ReportSummary, ReportTally, PunishmentRecord};
 
 const MODULE_ID: ModuleId = ModuleId(*b"py/trsry");
 
+/// 抵押投票的锁仓
+pub const REPORT_VOTE_ID: LockIdentifier = *b"reportvt";
+
 type BalanceOf<T> = <<T as register::Trait>::Currency1 as Currency<<T as system::Trait>::AccountId>>::Balance;
-type PositiveImbalanceOf<T> = <<T as register::Trait>::Currency1 as Currency<<T as frame_system::Trait>::AccountId>>::PositiveImbalance;
 type NegativeImbalanceOf<T> = <<T as register::Trait>::Currency1 as Currency<<T as frame_system::Trait>::AccountId>>::NegativeImbalance;
 type Balance = u128;
 
+/// 举报的编号
+pub type ReportIndex = u32;
+type VoteInfoOf<T> = VoteInfo<<T as system::Trait>::BlockNumber, <T as system::Trait>::AccountId, <T as balances::Trait>::Balance>;
+type PunishmentInfoOf<T> = PunishmentInfo<<T as system::Trait>::BlockNumber, <T as system::Trait>::AccountId,
+	<T as balances::Trait>::Balance, BalanceOf<T>>;
+
+// 申诉理由的最大长度
+pub const MAX_APPEAL_EVIDENCE_LEN: usize = 1024;
+
+// 举报(或反驳)证据说明的最大长度
+pub const MAX_EVIDENCE_DESCRIPTION_LEN: usize = 256;
+
+// 信誉分: 没有举报过是50分, 作弊成立一次加5分, 不成立一次减10分, 取消一次减3分, 最高100分
+pub const REPUTATION_BASE: u32 = 50;
+pub const REPUTATION_MAX: u32 = 100;
+const REPUTATION_PASSED: u32 = 5;
+const REPUTATION_REJECTED: u32 = 10;
+const REPUTATION_CANCELLED: u32 = 3;
+
+
+// 举报者的历史举报结果
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ReporterRecord {
+	pub passed: u32,  // 作弊成立
+	pub rejected: u32,  // 作弊不成立(包括申诉成功的)
+	pub cancelled: u32,  // 取消举报
+}
+
+
+impl ReporterRecord {
+	/// 信誉分(0~100)
+	pub fn score(&self) -> u32 {
+		let gained = REPUTATION_BASE.saturating_add(self.passed.saturating_mul(REPUTATION_PASSED));
+		let lost = self.rejected.saturating_mul(REPUTATION_REJECTED)
+			.saturating_add(self.cancelled.saturating_mul(REPUTATION_CANCELLED));
+		cmp::min(gained.saturating_sub(lost), REPUTATION_MAX)
+	}
+}
+
+
+// 举报的类别
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum ReportCategory{
+	FakeTx,  // 交易不存在或是失败的交易
+	AmountMismatch,  // 链上记录的金额与真实交易不符
+	AddressNotOwned,  // 挖矿地址不属于该矿工
+	WashTrade,  // 刷单(对敲)
+	Other,  // 其他
+}
+
+impl Default for ReportCategory {
+	fn default() -> Self {
+		ReportCategory::Other
+	}
+}
+
+
+// 举报或是反驳的证据
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Evidence {
+	category: ReportCategory,  // 类别
+	description: Vec<u8>,  // 简短说明(不超过MAX_EVIDENCE_DESCRIPTION_LEN)
+	content_hash: Option<H256>,  // 链下证据(截图、文件等)的哈希
+}
+
+impl Evidence {
+	pub fn new(category: ReportCategory, description: Vec<u8>, content_hash: Option<H256>) -> Self {
+		Evidence { category, description, content_hash }
+	}
+}
+
 
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -38,13 +121,76 @@ pub struct VoteInfo<Bo, A, Ba> {
 	symbol: Vec<u8>,   // 币种
 	tx: Vec<u8>,   // 交易tx
 	reporter: A,  // 举报人
-	report_reason: Vec<u8>,  // 举报理由
+	evidence: Evidence,  // 举报的证据
 	illegal_man: A,  // 作弊者
 	transaction_amount: Vec<u8>,  // 交易币额
 	usdt_amount: Ba,  // usdt数额
 	decimals: u32,  // 精度
 	approve_mans: Vec<A>,  // 投赞成票的人
 	reject_mans: Vec<A>,  // 投反对票的人
+	counter_evidences: Vec<(A, Evidence)>,  // 议员提交的反驳证据(每人最多一份)
+}
+
+
+// 版本0的投票信息(举报理由是任意长度的字符串 只用于数据迁移)
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+struct VoteInfoV0<Bo, A, Ba> {
+	start_vote_block: Bo,
+	symbol: Vec<u8>,
+	tx: Vec<u8>,
+	reporter: A,
+	report_reason: Vec<u8>,
+	illegal_man: A,
+	transaction_amount: Vec<u8>,
+	usdt_amount: Ba,
+	decimals: u32,
+	approve_mans: Vec<A>,
+	reject_mans: Vec<A>,
+}
+
+impl<Bo, A, Ba> From<VoteInfoV0<Bo, A, Ba>> for VoteInfo<Bo, A, Ba> {
+	fn from(old: VoteInfoV0<Bo, A, Ba>) -> Self {
+		let mut description = old.report_reason;
+		description.truncate(MAX_EVIDENCE_DESCRIPTION_LEN);
+		VoteInfo {
+			start_vote_block: old.start_vote_block,
+			symbol: old.symbol,
+			tx: old.tx,
+			reporter: old.reporter,
+			evidence: Evidence::new(ReportCategory::Other, description, None),
+			illegal_man: old.illegal_man,
+			transaction_amount: old.transaction_amount,
+			usdt_amount: old.usdt_amount,
+			decimals: old.decimals,
+			approve_mans: old.approve_mans,
+			reject_mans: old.reject_mans,
+			counter_evidences: vec![],
+		}
+	}
+}
+
+
+// 申诉信息
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct AppealInfo<Bo, A, B> {
+	appellant: A,  // 申诉人(被惩罚的矿工)
+	evidence: Vec<u8>,  // 申诉理由或证据
+	start_block: Bo,  // 发起申诉的区块高度
+	status: AppealStatus,  // 申诉状态
+	bond: B,  // 申诉时实际抵押的金额
+}
+
+
+// 惩罚记录(永久保存, 包括扣款与申诉结果)
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct PunishmentInfo<Bo, A, Ba, B> {
+	vote_info: VoteInfo<Bo, A, Ba>,  // 举报的投票信息
+	punish_block: Option<Bo>,  // 执行扣款的区块高度(还没有执行是None)
+	slashed: B,  // 实际扣除的金额
+	rewarded: B,  // 实际发给举报者的奖励(申诉成功时没收)
+	appeal: Option<AppealInfo<Bo, A, B>>,  // 申诉信息
 }
 
 
@@ -56,6 +202,8 @@ pub enum ReportModuleAmount<BalanceOf>{
 	PunishmentAmount(BalanceOf),
 	CouncilReward(BalanceOf),
 	CancelReportSlash(BalanceOf),
+	AppealBond(BalanceOf),
+	VoterSlash(BalanceOf),
 }
 
 
@@ -64,10 +212,25 @@ pub enum ReportModuleAmount<BalanceOf>{
 pub enum ReportModuleTime<BlockNumber>{
 	ProposalExpireTime(BlockNumber),
 	RewardDuration(BlockNumber),
+	AppealWindow(BlockNumber),
+	ConvictionLockPeriod(BlockNumber),
+	AppealJudgePeriod(BlockNumber),
 
 }
 
 
+#[derive(Encode, Decode, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive())]
+pub enum ReportModuleLimit{
+	MaxExpirePerBlock(u32),
+	MaxSettlePerBlock(u32),
+	MaxStakeVoters(u32),
+	VoterSlashMinVotes(u32),
+	VoterSlashThreshold(Permill),
+	MaxReportsPerDay(u32),
+}
+
+
 #[derive(Encode, Decode, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "std", derive())]
 pub enum VoteRewardPeriodEnum{
@@ -104,7 +267,222 @@ pub enum VoteResult{
 
 
 
-pub trait Trait: balances::Trait + RegisterTrait + mine::Trait{
+/// 判断举报结果时用到的票数
+#[derive(Clone, PartialEq, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct JudgementTally<Balance> {
+	pub council_ayes: u32,  // 议员的赞成票
+	pub council_nays: u32,  // 议员的反对票
+	pub council_size: u32,  // 议员人数
+	pub stake_ayes: Balance,  // 抵押投票的赞成票(已经按conviction加权)
+	pub stake_nays: Balance,  // 抵押投票的反对票(已经按conviction加权)
+}
+
+
+/// 举报的裁决规则
+pub trait JudgementPolicy<Balance> {
+	/// 是否接受抵押投票
+	fn accepts_stake_votes() -> bool;
+
+	/// None表示还没有结果, Some(true)表示作弊成立, Some(false)表示作弊不成立
+	fn judge(tally: &JudgementTally<Balance>) -> Option<bool>;
+
+	/// 一方的议员票数达到多少就结束投票(给runtime api显示用)
+	fn council_threshold(council_size: u32) -> u32;
+
+	/// 抵押投票至少需要多少票(给runtime api显示用)
+	fn min_turnout() -> Balance;
+}
+
+
+/// 简单多数: 议员一方胜出两票或是一方先过半就结束(相等时赞成优先)
+pub struct SimpleMajority;
+
+impl<Balance: Zero> JudgementPolicy<Balance> for SimpleMajority {
+	fn accepts_stake_votes() -> bool {
+		false
+	}
+
+	fn judge(tally: &JudgementTally<Balance>) -> Option<bool> {
+		let ayes = tally.council_ayes;
+		let nays = tally.council_nays;
+		let n = cmp::max(ayes, nays) - cmp::min(ayes, nays);
+		let threshold = Self::council_threshold(tally.council_size);
+		if threshold > 0u32 && (n >= 2 || ayes >= threshold || nays >= threshold) {
+			Some(ayes >= nays)
+		} else {
+			None
+		}
+	}
+
+	fn council_threshold(council_size: u32) -> u32 {
+		(council_size + 1u32) / 2u32
+	}
+
+	fn min_turnout() -> Balance {
+		Zero::zero()
+	}
+}
+
+
+/// 绝对多数: 赞成票达到议员人数的P才成立, 反对票多到赞成票不可能达到P时不成立
+pub struct SuperMajority<P>(PhantomData<P>);
+
+impl<Balance: Zero, P: Get<Permill>> JudgementPolicy<Balance> for SuperMajority<P> {
+	fn accepts_stake_votes() -> bool {
+		false
+	}
+
+	fn judge(tally: &JudgementTally<Balance>) -> Option<bool> {
+		if tally.council_size == 0 {
+			return None;
+		}
+		let needed = Self::council_threshold(tally.council_size);
+		if tally.council_ayes >= needed {
+			Some(true)
+		} else if tally.council_size.saturating_sub(tally.council_nays) < needed {
+			Some(false)
+		} else {
+			None
+		}
+	}
+
+	fn council_threshold(council_size: u32) -> u32 {
+		cmp::max(P::get().mul_ceil(council_size), 1)
+	}
+
+	fn min_turnout() -> Balance {
+		Zero::zero()
+	}
+}
+
+
+/// 抵押投票: 注册过的矿工或是staking抵押者锁仓投票, 票数按conviction加权
+/// 总票数达到MinTurnout后票数多的一方胜出(相等时赞成优先)
+pub struct StakeWeighted<MinTurnout>(PhantomData<MinTurnout>);
+
+impl<Balance, M> JudgementPolicy<Balance> for StakeWeighted<M> where
+	Balance: Zero + Saturating + PartialOrd + Copy,
+	M: Get<Balance>,
+{
+	fn accepts_stake_votes() -> bool {
+		true
+	}
+
+	fn judge(tally: &JudgementTally<Balance>) -> Option<bool> {
+		let turnout = tally.stake_ayes.saturating_add(tally.stake_nays);
+		if turnout.is_zero() || turnout < M::get() {
+			None
+		} else {
+			Some(tally.stake_ayes >= tally.stake_nays)
+		}
+	}
+
+	fn council_threshold(_council_size: u32) -> u32 {
+		0
+	}
+
+	fn min_turnout() -> Balance {
+		M::get()
+	}
+}
+
+
+/// 可以举报的作弊类型(挖矿交易作弊走议会投票的`report`, 不在这里)
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum OffenceKind {
+	VerifierOffline,  // 验证人的本地验证服务没有开起来(已不再提交, 改由register按DowntimePenalty分阶段处理, 保留是为了不改变编码)
+	FakeAddress,  // 绑定的地址被验证人判定不通过
+	AssetMintAbuse,  // 滥用generic-asset的铸币
+	VerifierFault,  // 验证人的验证服务返回的结果签名不对(被篡改或是伪造)
+}
+
+impl OffenceKind {
+	/// 只能由系统(链下验证的结果)提交证据
+	pub fn system_only(&self) -> bool {
+		*self == OffenceKind::VerifierOffline || *self == OffenceKind::VerifierFault
+	}
+
+	/// 拉入黑名单时记录的原因
+	pub fn reason(&self) -> &'static [u8] {
+		match self {
+			OffenceKind::VerifierOffline => b"verifier offline",
+			OffenceKind::FakeAddress => b"fake address binding",
+			OffenceKind::AssetMintAbuse => b"asset mint abuse",
+			OffenceKind::VerifierFault => b"verifier fault",
+		}
+	}
+}
+
+
+/// 作弊成立后的处罚
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum OffencePenalty<Balance> {
+	Slash(Balance),  // 扣款(进国库)
+	SlashAndBlacklist(Balance),  // 扣款并且拉入黑名单
+	ValidatorSlash(Perbill),  // 交给pallet_offences按staking的规则惩罚(只用于VerifierOffline, 不是验证人时不处罚)
+}
+
+
+/// 某种作弊的规则
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct OffenceConfig<Balance> {
+	pub threshold: u32,  // 累计多少条证据才成立
+	pub penalty: OffencePenalty<Balance>,  // 成立后的处罚
+}
+
+
+/// 一条作弊证据(同一个举报者的同一条证据只算一次)
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct OffenceEvidence<A, Bo> {
+	pub reporter: A,  // 举报者(系统提交的是国库账户)
+	pub evidence: Vec<u8>,  // tx或是简短说明(不超过MAX_EVIDENCE_DESCRIPTION_LEN)
+	pub block: Bo,  // 提交的区块高度
+}
+
+
+/// 交给pallet_offences的验证人作弊(本地验证服务在窗口内没有应答的次数到了register的DowntimePenalty::slash)
+pub struct VerifierOffence<T: Trait> {
+	/// 在哪个session里成立
+	pub session_index: SessionIndex,
+	/// 当前的验证人数量
+	pub validator_set_count: u32,
+	/// 作弊的验证人
+	pub offenders: Vec<IdentificationTuple<T>>,
+}
+
+impl<T: Trait> Offence<IdentificationTuple<T>> for VerifierOffence<T> {
+	const ID: Kind = *b"transx:verifyoff";
+	type TimeSlot = SessionIndex;
+
+	fn offenders(&self) -> Vec<IdentificationTuple<T>> {
+		self.offenders.clone()
+	}
+
+	fn session_index(&self) -> SessionIndex {
+		self.session_index
+	}
+
+	fn validator_set_count(&self) -> u32 {
+		self.validator_set_count
+	}
+
+	fn time_slot(&self) -> Self::TimeSlot {
+		self.session_index
+	}
+
+	// 惩罚比例由治理在register的DowntimePenalty里设置, 与作弊的人数无关
+	fn slash_fraction(_offenders: u32, _validator_set_count: u32) -> Perbill {
+		<register::Module<T>>::verifier_downtime_penalty().slash_fraction
+	}
+}
+
+
+pub trait Trait: balances::Trait + RegisterTrait + mine::Trait + pallet_session::historical::Trait{
 
 	// 议会成员
 	type ConcilOrigin: EnsureOrigin<Self::Origin, Success=Self::AccountId>;
@@ -113,10 +491,6 @@ pub trait Trait: balances::Trait + RegisterTrait + mine::Trait{
 
 	type ConcilMembers: Contains<Self::AccountId>;
 
-	type ShouldAddOrigin: OnUnbalanced<PositiveImbalanceOf<Self>>;
-
-	type ShouldSubOrigin: OnUnbalanced<NegativeImbalanceOf<Self>>;
-
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 
 	type DeadOrigin: IsDeadAccount<Self::AccountId>;
@@ -125,6 +499,21 @@ pub trait Trait: balances::Trait + RegisterTrait + mine::Trait{
 
 	type ReportSetOrigin: EnsureOrigin<Self::Origin>;
 
+	// 裁决申诉
+	type AppealOrigin: EnsureOrigin<Self::Origin>;
+
+	// 举报的裁决规则
+	type JudgementPolicy: JudgementPolicy<BalanceOf<Self>>;
+
+	// staking的抵押者(可以参与抵押投票)
+	type Stakers: IsStaker<Self::AccountId>;
+
+	// 直接裁决系统发起的举报
+	type FastTrackOrigin: EnsureOrigin<Self::Origin>;
+
+	// 验证人的作弊交给pallet_offences(走staking的惩罚)
+	type ReportOffence: ReportOffence<Self::AccountId, IdentificationTuple<Self>, VerifierOffence<Self>>;
+
 }
 
 
@@ -132,14 +521,38 @@ pub trait Trait: balances::Trait + RegisterTrait + mine::Trait{
 decl_storage! {
 	trait Store for Module<T: Trait> as ReportModule {
 
-		/// 所有还未奖励的投票的集合（一直到投票奖励完成才kill）
-		pub Votes get(fn votes): map hasher(blake2_128_concat) Vec<u8> => VoteInfo<T::BlockNumber, T::AccountId, T::Balance>;
+		/// 下一个举报的编号
+		pub NextReportId get(fn next_report_id): ReportIndex;
+
+		/// 所有还未奖励的举报（一直到投票结果处理完成才删除）  用举报编号做key
+		pub Reports get(fn reports): map hasher(twox_64_concat) ReportIndex => Option<VoteInfoOf<T>>;
+
+		/// tx对应的举报编号
+		pub ReportIdOf get(fn report_id_of): map hasher(blake2_128_concat) Vec<u8> => Option<ReportIndex>;
+
+		/// 在某个区块过期的举报
+		pub ExpiryQueue get(fn expiry_queue): map hasher(twox_64_concat) T::BlockNumber => Vec<ReportIndex>;
 
 		/// 与自己有关的所有tx
-		pub ManTxHashs get(fn mantxhashs): map hasher(blake2_128_concat) T::AccountId => Vec<Vec<u8>>;
+		pub ReportsOf get(fn reports_of_account): map hasher(blake2_128_concat) T::AccountId => Vec<ReportIndex>;
+
+		/// 矿工被惩罚的tx(AllPunishmentInfo的索引)
+		pub PunishedTxsOf get(fn punished_txs_of): map hasher(blake2_128_concat) T::AccountId => Vec<Vec<u8>>;
 
 		/// 已经通过但是还没有处理的投票结果
-		pub RewardList get(fn rewardlist): Vec<VoteInfo<T::BlockNumber, T::AccountId, T::Balance>>;
+		pub RewardQueue get(fn reward_queue): Vec<ReportIndex>;
+
+		/// 是否正在处理投票结果(一个区块处理不完的留到下一个区块)
+		pub Settling get(fn settling): bool;
+
+		/// 这一轮处理到了RewardQueue的哪个位置(前面的是国库不够暂时不处理的)
+		pub SettleCursor get(fn settle_cursor): u32;
+
+		/// 每个区块最多处理多少个过期的举报
+		pub MaxExpirePerBlock get(fn max_expire_per_block): u32 = 50;
+
+		/// 每个区块最多处理多少个投票结果
+		pub MaxSettlePerBlock get(fn max_settle_per_block): u32 = 50;
 
 		/// 多久统一处理投票结果
 		pub VoteRewardPeriod get(fn vote_reward_period): T::BlockNumber = T::BlockNumber::from(1 * DAYS);
@@ -156,11 +569,68 @@ decl_storage! {
 		/// 对参与投票的议会成员的奖励金额
 		pub CouncilReward get(fn council_reward): BalanceOf<T> = <BalanceOf<T> as TryFrom::<Balance>>::try_from(10 * DOLLARS).ok().unwrap();
 
-		/// 正在进行投票的举报
-		pub Voting get(fn voting): Vec<(Vec<u8>, VoteInfo<T::BlockNumber, T::AccountId, T::Balance>)>;
+		/// 进入黑名单的所有信息 被永久保存  现在用tx做key(包括扣款与申诉结果)
+		pub AllPunishmentInfo get(fn allpunishmentinfo): map hasher(blake2_128_concat) Vec<u8> => PunishmentInfoOf<T>;
+
+		/// 扣款后多久之内可以申诉
+		pub AppealWindow get(fn appeal_window): T::BlockNumber = T::BlockNumber::from(7 * DAYS);
+
+		/// 申诉需要抵押的金额(申诉失败会被没收)
+		pub AppealBond get(fn appeal_bond): BalanceOf<T> = <BalanceOf<T> as TryFrom::<Balance>>::try_from(100 * DOLLARS).ok().unwrap();
 
-		/// 进入黑名单的所有信息 被永久保存  现在用tx做key
-		pub AllPunishmentInfo get(fn allpunishmentinfo): map hasher(blake2_128_concat) Vec<u8> => VoteInfo<T::BlockNumber, T::AccountId, T::Balance>;
+		/// 发起申诉后多久之内没有裁决就过期(退还抵押)
+		pub AppealJudgePeriod get(fn appeal_judge_period): T::BlockNumber = T::BlockNumber::from(14 * DAYS);
+
+		/// 申诉在哪个区块过期 (区块高度 => tx)
+		pub AppealExpiry get(fn appeal_expiry): map hasher(twox_64_concat) T::BlockNumber => Vec<Vec<u8>>;
+
+		/// 举报时实际抵押的金额(按举报者的信誉计算)
+		pub ReportDeposit get(fn report_deposit): map hasher(twox_64_concat) ReportIndex => Option<BalanceOf<T>>;
+
+		/// 举报者的历史举报结果
+		pub ReporterRecords get(fn reporter_records): map hasher(blake2_128_concat) T::AccountId => ReporterRecord;
+
+		/// 举报者今天的举报次数 (第几天, 次数)
+		pub ReportsToday get(fn reports_today): map hasher(blake2_128_concat) T::AccountId => (u32, u32);
+
+		/// 信誉分为50时每天最多举报的次数(信誉分越高次数越多, 最少1次)
+		pub MaxReportsPerDay get(fn max_reports_per_day): u32 = 5;
+
+		/// 系统发起的举报是哪些验证人提交的验证失败结果
+		pub SystemReportValidators get(fn system_report_validators): map hasher(twox_64_concat) ReportIndex => Vec<T::AccountId>;
+
+		/// 议会直接裁决的结果(true表示作弊成立)
+		pub FastTracked get(fn fast_tracked): map hasher(twox_64_concat) ReportIndex => Option<bool>;
+
+		/// 抵押投票 (举报编号, 投票人) => (是否赞成, 加权后的票数)
+		pub StakeVotes get(fn stake_votes): double_map hasher(twox_64_concat) ReportIndex, hasher(blake2_128_concat) T::AccountId => Option<(bool, BalanceOf<T>)>;
+
+		/// 举报的抵押投票汇总 (赞成票, 反对票, 投票人数)
+		pub StakeTally get(fn stake_tally): map hasher(twox_64_concat) ReportIndex => (BalanceOf<T>, BalanceOf<T>, u32);
+
+		/// 每个举报最多多少人参与抵押投票
+		pub MaxStakeVoters get(fn max_stake_voters): u32 = 100;
+
+		/// 抵押投票的锁仓 (锁仓金额, 解锁的区块高度)
+		pub VoteLockOf get(fn vote_lock_of): map hasher(blake2_128_concat) T::AccountId => (BalanceOf<T>, T::BlockNumber);
+
+		/// conviction每一倍锁仓多久(投票期结束后开始算)
+		pub ConvictionLockPeriod get(fn conviction_lock_period): T::BlockNumber = T::BlockNumber::from(7 * DAYS);
+
+		/// 投票人的历史记录 (参与裁决的次数, 与最终结果相反的次数)
+		pub VoterRecords get(fn voter_records): map hasher(blake2_128_concat) T::AccountId => (u32, u32);
+
+		/// 至少参与多少次裁决才判断是否要惩罚
+		pub VoterSlashMinVotes get(fn voter_slash_min_votes): u32 = 10;
+
+		/// 与最终结果相反的比例达到多少就惩罚
+		pub VoterSlashThreshold get(fn voter_slash_threshold): Permill = Permill::from_percent(70);
+
+		/// 经常与最终结果相反的投票人被惩罚的金额
+		pub VoterSlash get(fn voter_slash): BalanceOf<T> = <BalanceOf<T> as TryFrom::<Balance>>::try_from(10 * DOLLARS).ok().unwrap();
+
+		/// 存储的版本(用于升级时迁移数据)
+		pub StorageVersion get(fn storage_version): u32;
 
 		/// 取消举报被惩罚的金额
 		pub CancelReportSlash get(fn cancel_report_slash): BalanceOf<T> = <BalanceOf<T> as TryFrom::<Balance>>::try_from(1 * DOLLARS).ok().unwrap();
@@ -171,6 +641,12 @@ decl_storage! {
 		/// 个人正在被举报的tx
 		pub BeingReportedTxsOf get(fn reported_txs_of): map hasher(blake2_128_concat) T::AccountId => BTreeSet<Vec<u8>>;
 
+		/// 每种作弊的规则(没有设置过的用默认规则)
+		pub OffenceConfigs get(fn offence_configs): map hasher(twox_64_concat) OffenceKind => Option<OffenceConfig<BalanceOf<T>>>;
+
+		/// 还没有达到阈值的作弊证据 (作弊类型, 作弊者) => 证据
+		pub PendingOffences get(fn pending_offences): double_map hasher(twox_64_concat) OffenceKind, hasher(blake2_128_concat) T::AccountId => Vec<OffenceEvidence<T::AccountId, T::BlockNumber>>;
+
 	}
 
 }
@@ -224,6 +700,51 @@ decl_error! {
 		/// 未定义的amount或是时间参数)
 		Undefine,
 
+		/// 没有这条惩罚记录
+		NotPunished,
+
+		/// 还没有执行扣款
+		PunishmentNotExecuted,
+
+		/// 已经过了申诉期
+		AppealExpired,
+
+		/// 已经申诉过了
+		AppealExists,
+
+		/// 没有正在进行的申诉
+		NotAppealing,
+
+		/// 申诉理由或证据说明太长
+		EvidenceTooLong,
+
+		/// 当前的裁决规则不接受抵押投票
+		StakeVoteDisabled,
+
+		/// 不是注册矿工也不是staking抵押者
+		NotStakeVoter,
+
+		/// 抵押投票的人数已满
+		TooManyStakeVoters,
+
+		/// 今天的举报次数已经用完
+		TooManyReports,
+
+		/// 还没有到解锁时间
+		VoteLocked,
+
+		/// 不是系统发起的举报
+		NotSystemReport,
+
+		/// 已经提交过这条证据
+		DuplicateEvidence,
+
+		/// 这种作弊只能由系统提交证据
+		SystemOnlyOffence,
+
+		/// 阈值为0 或是对不是验证人的作弊使用了ValidatorSlash
+		InvalidOffenceConfig,
+
 	}
 }
 
@@ -247,6 +768,8 @@ decl_module! {
 				ReportModuleAmount::PunishmentAmount(x) => <IllegalPunishment<T>>::put(x),
 				ReportModuleAmount::CouncilReward(x) => <CouncilReward<T>>::put(x),
 				ReportModuleAmount::CancelReportSlash(x) => <CancelReportSlash<T>>::put(x),
+				ReportModuleAmount::AppealBond(x) => <AppealBond<T>>::put(x),
+				ReportModuleAmount::VoterSlash(x) => <VoterSlash<T>>::put(x),
 				_ => return Err(Error::<T>::Undefine)?,
 
 			}
@@ -264,6 +787,9 @@ decl_module! {
 			match time {
 				ReportModuleTime::ProposalExpireTime(x) => <ProposalExpire<T>>::put(x),
 				ReportModuleTime::RewardDuration(x) => <VoteRewardPeriod<T>>::put(x),
+				ReportModuleTime::AppealWindow(x) => <AppealWindow<T>>::put(x),
+				ReportModuleTime::ConvictionLockPeriod(x) => <ConvictionLockPeriod<T>>::put(x),
+				ReportModuleTime::AppealJudgePeriod(x) => <AppealJudgePeriod<T>>::put(x),
 				_ => return Err(Error::<T>::Undefine)?,
 			}
 			Self::deposit_event(RawEvent::SetTime);
@@ -272,116 +798,120 @@ decl_module! {
 		}
 
 
+		/// 设置每个区块处理数量的上限
+		#[weight = 500_000]
+		fn set_limit(origin, limit: ReportModuleLimit) -> DispatchResult{
+			T::ReportSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			match limit {
+				ReportModuleLimit::MaxExpirePerBlock(x) => <MaxExpirePerBlock>::put(x),
+				ReportModuleLimit::MaxSettlePerBlock(x) => <MaxSettlePerBlock>::put(x),
+				ReportModuleLimit::MaxStakeVoters(x) => <MaxStakeVoters>::put(x),
+				ReportModuleLimit::VoterSlashMinVotes(x) => <VoterSlashMinVotes>::put(x),
+				ReportModuleLimit::VoterSlashThreshold(x) => <VoterSlashThreshold>::put(x),
+				ReportModuleLimit::MaxReportsPerDay(x) => <MaxReportsPerDay>::put(x),
+			}
+			Self::deposit_event(RawEvent::SetLimit);
+			Ok(())
+		}
+
+
+		/// 设置某种作弊的阈值与处罚
+		#[weight = 500_000]
+		fn set_offence_config(origin, kind: OffenceKind, config: OffenceConfig<BalanceOf<T>>) -> DispatchResult{
+			T::ReportSetOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			ensure!(config.threshold > 0, Error::<T>::InvalidOffenceConfig);
+			if let OffencePenalty::ValidatorSlash(_) = config.penalty {
+				ensure!(kind == OffenceKind::VerifierOffline, Error::<T>::InvalidOffenceConfig);
+			}
+			<OffenceConfigs<T>>::insert(kind, config);
+			Self::deposit_event(RawEvent::SetOffenceConfig);
+			Ok(())
+		}
+
+
+		/// 议员提交其他类型的作弊证据(挖矿交易作弊用`report`)
+		#[weight = 500_000]
+		pub fn report_offence(origin, kind: OffenceKind, offender: T::AccountId, evidence: Vec<u8>) -> DispatchResult{
+			let who = T::ConcilOrigin::ensure_origin(origin)?;
+			ensure!(!kind.system_only(), Error::<T>::SystemOnlyOffence);
+			ensure!(!<register::Module<T>>::is_blacklisted(&who), Error::<T>::InBlackList);
+			Self::report_misbehaviour(kind, who, offender, evidence).map(|_| ())
+		}
+
+
 		/// 举报不良的挖矿
 		#[weight = 500_000]
-		pub fn report(origin, tx: Vec<u8>, mine_tag: MineTag, reason: Vec<u8>) -> DispatchResult{
+		pub fn report(origin, tx: Vec<u8>, mine_tag: MineTag, evidence: Evidence) -> DispatchResult{
 
 			let who = ensure_signed(origin)?;
 			debug::warn!("-----report,account:{:?}------",who);
-			let tx_info = if let Some(info) = <OwnerMineRecord<T>>::get(tx.clone(), mine_tag){
-			info
-			}
-			else{
-				return Err(Error::<T>::NotMinerTx)?;
-			};
-			#[cfg(feature = "std")]{
-				debug::info!("tx_info:{:?}",tx_info);
-			}
-
-			let illegalman: T::AccountId = tx_info.miner_address;
-			let decimals = tx_info.decimal;
-			let symbol = tx_info.symbol;
-			let usdt_amount: T::Balance = <<T as balances::Trait>::Balance as TryFrom::<u64>>::try_from(tx_info.usdt_amount).unwrap_or(T::Balance::default());
-
-			let tx_amount = tx_info.sym_amount;
-
-			// 如果作弊者和举报人有至少有一个在黑名单里， 则不给举报。
-			ensure!(!<BlackList<T>>::contains_key(who.clone()) || !<BlackList<T>>::contains_key(illegalman.clone()), Error::<T>::RepoterOrIllegalmanInBlackList);
-
-			// 被举报人必须是注册过的。
-			ensure!(Self::is_register_member(illegalman.clone()), Error::<T>::NotRegister);
-
-			// 根据tx判断这笔交易是否已经存在  已经存在的话不再添加进来
-			ensure!(!<Votes<T>>::contains_key(&tx), Error::<T>::BeingReported);
-
-			// 被举报人不能已经在被惩罚队列中
-			ensure!(!<AllPunishmentInfo<T>>::contains_key(tx.clone()), Error::<T>::InPunishmentList);
-
-			// 没有足够抵押资金，不给举报
-			T::Currency1::reserve(&who, <ReportReserve<T>>::get()).map_err(|_| Error::<T>::BondTooLow)?;
-
-			// 获取当前区块高度
-			let start_vote_block = <system::Module<T>>::block_number();
-			let mut vote_info = VoteInfo{
-				start_vote_block: start_vote_block.clone(),
-				symbol: symbol.clone(),
-				tx: tx.clone(),
-				reporter: who.clone(),
-				report_reason: reason.clone(),
-				illegal_man: illegalman.clone(),
-				transaction_amount: tx_amount.clone(),
-				usdt_amount: usdt_amount.clone(),
-				decimals: decimals.clone(),
-				approve_mans: vec![],
-				reject_mans:vec![],
-			};
-			// 判断投票者是否是议员
-			if Self::is_concil_member(who.clone()) {
-				vote_info.approve_mans.push(who.clone());
-			}
-			// 添加该投票的信息
-			<Votes<T>>::insert(tx.clone(), vote_info.clone());
-			// 添加人与相关交易映射
-			Self::add_mantxhashs(who.clone(), tx.clone());
-			Self::add_mantxhashs(illegalman.clone(), tx.clone());
+			Self::do_report(who, tx, mine_tag, evidence, true).map(|_| ())
+		}
 
-			// 添加到正在进行投票的队列
-			<Voting<T>>::mutate(|votes| votes.push((tx.clone(), vote_info.clone())));
 
-			<BeingReportedTxsOf<T>>::mutate(illegalman.clone(), |h| h.insert(tx.clone()));
+		/// 议员在投票期间提交反驳证据(再次提交会覆盖自己之前的证据)
+		#[weight = 500_000]
+		pub fn counter_evidence(origin, report_id: ReportIndex, evidence: Evidence) -> DispatchResult{
 
-			if Self::is_concil_member(who.clone())	{
-				Self::judge(tx.clone(), vote_info.clone());
+			let who = T::ConcilOrigin::ensure_origin(origin)?;
+			ensure!(evidence.description.len() <= MAX_EVIDENCE_DESCRIPTION_LEN, Error::<T>::EvidenceTooLong);
+			let mut voting = <Reports<T>>::get(report_id).ok_or(Error::<T>::NotInVoteList)?;
+			ensure!(!(<register::Module<T>>::is_blacklisted(&who)), Error::<T>::InBlackList);
+
+			// 投票已经结束的不能再提交
+			ensure!(Self::vote_result(voting.clone()).0 == VoteResult::NoPASS, Error::<T>::PassedProposal);
+			// 举报者与被举报者不能提交反驳证据
+			ensure!(who != voting.reporter, Error::<T>::Reporter);
+			ensure!(who != voting.illegal_man, Error::<T>::IllegalMan);
+
+			if let Some(pos) = voting.counter_evidences.iter().position(|h| h.0 == who) {
+				voting.counter_evidences[pos].1 = evidence;
+			}
+			else {
+				voting.counter_evidences.push((who.clone(), evidence));
 			}
+			let tx = voting.tx.clone();
+			<Reports<T>>::insert(report_id, voting);
 
-			Self::deposit_event(RawEvent::ReportEvent(start_vote_block, illegalman));
+			Self::deposit_event(RawEvent::CounterEvidenceEvent(who, tx));
 			Ok(())
 		}
 
 
 		/// 取消举报
 		#[weight = 500_000]
-		pub fn cancel_report(origin, tx: Vec<u8>) -> DispatchResult{
+		pub fn cancel_report(origin, report_id: ReportIndex) -> DispatchResult{
 
 			let who = ensure_signed(origin)?;
-			// 交易不存在不能操作
-			ensure!(<Votes<T>>::contains_key(tx.clone()), Error::<T>::ReportNotExists);
-			let reporter = <Votes<T>>::get(tx.clone()).reporter;
-			let illegalman = <Votes<T>>::get(tx.clone()).illegal_man;
+			// 举报不存在不能操作
+			let vote_info = <Reports<T>>::get(report_id).ok_or(Error::<T>::ReportNotExists)?;
+			let tx = vote_info.tx.clone();
+			let reporter = vote_info.reporter.clone();
+			let illegalman = vote_info.illegal_man.clone();
 
-			ensure!(!<BlackList<T>>::contains_key(who.clone()), Error::<T>::InBlackList);
+			ensure!(!<register::Module<T>>::is_blacklisted(&who), Error::<T>::InBlackList);
 
 			// 不是举报者本人则不能取消该举报提案
 			ensure!((who.clone() == reporter.clone()), Error::<T>::NotSelf);
 
 			// 如果提案已经结束 则不能再取消
-			ensure!(Self::vote_result(<Votes<T>>::get(tx.clone())).0 == VoteResult::NoPASS, Error::<T>::PassedProposal);
+			ensure!(Self::vote_result(vote_info).0 == VoteResult::NoPASS, Error::<T>::PassedProposal);
 
-			// 从Votes中删除该提案
-			<Votes<T>>::remove(tx.clone());
+			let deposit = Self::deposit_of(&tx);
 
-			// 删除个人相关的tx
-			Self::remove_mantxhashs(reporter.clone(),tx.clone());
-			Self::remove_mantxhashs(illegalman.clone(),tx.clone());
-			Self::deposit_event(RawEvent::RemoveManTxhashs(reporter.clone(), illegalman.clone()));
+			// 删除该提案(过期队列里的编号过期时会被跳过)
+			Self::remove_report(report_id, &tx);
 
 			// 归还举报者个人抵押
-			T::Currency1::unreserve(&reporter, <ReportReserve<T>>::get());
+			T::Currency1::unreserve(&reporter, deposit);
+			<ReporterRecords<T>>::mutate(&reporter, |r| r.cancelled = r.cancelled.saturating_add(1));
 
 			// 惩罚举报者1个token(这个token放到国库)
 			T::ReportSlash::on_unbalanced(T::Currency1::slash(&reporter, <CancelReportSlash<T>>::get() ).0);
-            // 从正在投票的队列中删除
-			<Voting<T>>::mutate(|votes| votes.retain(|h| h.0 != tx.clone()));
 
 			<BeingReportedTxsOf<T>>::mutate(illegalman.clone(), |h| h.remove(&tx));
 
@@ -393,40 +923,40 @@ decl_module! {
 
 		/// 对举报提案进行投票
 		#[weight = 500_000]
-		pub fn vote(origin, tx: Vec<u8>, yes_no: bool) -> DispatchResult{
+		pub fn vote(origin, report_id: ReportIndex, yes_no: bool) -> DispatchResult{
 
 			// 如果自己不是议会成员则不给操作
 			let who = T::ConcilOrigin::ensure_origin(origin)?;
-			// 判断这个tx是否存在于投票队列中，不存在则退出
-			ensure!(<Votes<T>>::contains_key(&tx), Error::<T>::NotInVoteList);
-			let illegalman = <Votes<T>>::get(&tx).illegal_man;
-			let reporter = <Votes<T>>::get(&tx).reporter;
+			// 判断这个举报是否存在，不存在则退出
+			let vote_info = <Reports<T>>::get(report_id).ok_or(Error::<T>::NotInVoteList)?;
+			let illegalman = vote_info.illegal_man.clone();
+			let reporter = vote_info.reporter.clone();
+
+			// 投票已经结束(等待处理结果)的不能再投
+			ensure!(Self::vote_result(vote_info.clone()).0 == VoteResult::NoPASS, Error::<T>::PassedProposal);
 
 			// 自己是举报者不能参与投票
 			ensure!(who.clone() != reporter, Error::<T>::Reporter);
 
 			// 如果该投票的议员进入黑名单 则不能参与投票
-			ensure!(!(<BlackList<T>>::contains_key(who.clone())), Error::<T>::InBlackList);
+			ensure!(!(<register::Module<T>>::is_blacklisted(&who)), Error::<T>::InBlackList);
 
 			// 如果这个议会成员是作弊者（被举报方），则禁止其投票。
 			ensure!(!(illegalman.clone() == who.clone()), Error::<T>::IllegalMan);
 
-			let vote_info = <Votes<T>>::get(&tx);
-
 			// 如果举报者和作弊者有至少有一个在黑名单列表中， 则退出。
-			if <BlackList<T>>::contains_key(reporter.clone()) || <BlackList<T>>::contains_key(illegalman.clone()){
+			if <register::Module<T>>::is_blacklisted(&reporter) || <register::Module<T>>::is_blacklisted(&illegalman){
 				// 删除相关信息  并且退出
-				Self::vote_expire_do(tx.clone(), vote_info);
+				Self::vote_expire_do(report_id, vote_info);
 				return Err(Error::<T>::InBlackList)?;
 			}
 
-			let now = <system::Module<T>>::block_number();
 			// 过期删除相关信息  并且退出
-			if now - vote_info.start_vote_block > <ProposalExpire<T>>::get(){
-				Self::vote_expire_do(tx.clone(), vote_info);
-				Self::deposit_event(RawEvent::RemoveManTxhashs(who.clone(), illegalman.clone()));
+			if Self::is_vote_expired(&vote_info){
+				Self::vote_expire_do(report_id, vote_info);
+				return Ok(());
 			}
-			let mut voting = <Votes<T>>::get(&tx);
+			let mut voting = vote_info;
 			let position_yes = voting.approve_mans.iter().position(|a| a == &who);
 			let position_no = voting.reject_mans.iter().position(|a| a == &who);
 			// 如果投赞成票
@@ -454,52 +984,223 @@ decl_module! {
 				}
 			}
 
-			<Votes<T>>::insert(tx.clone(), voting.clone());
-
-			Self::judge(tx.clone(), voting.clone());
+			Self::judge(report_id, voting);
 
 			Self::deposit_event(RawEvent::VoteEvent(illegalman.clone()));
 			Ok(())
 		}
 
 
-		// 每次出块结束都要去计算一下是否是奖励时间 如果是则奖励
-		fn on_finalize(n: T::BlockNumber){
+		/// 注册矿工或是staking抵押者锁仓投票(裁决规则接受抵押投票时才可以)
+		/// 投票期结束后还要锁仓 conviction.lock_periods() * ConvictionLockPeriod
+		#[weight = 500_000]
+		pub fn stake_vote(origin, report_id: ReportIndex, aye: bool, conviction: Conviction, #[compact] balance: BalanceOf<T>) -> DispatchResult{
 
-			if !<VoteRewardPeriod<T>>::get().is_zero(){
-				if (n % <VoteRewardPeriod<T>>::get()).is_zero() {  // 默认一天奖励一次
-					Self::reward();  // 奖励的方法
+			let who = ensure_signed(origin)?;
+			ensure!(T::JudgementPolicy::accepts_stake_votes(), Error::<T>::StakeVoteDisabled);
+			ensure!(Self::is_register_member(who.clone()) || T::Stakers::is_staker(&who), Error::<T>::NotStakeVoter);
+			ensure!(!(<register::Module<T>>::is_blacklisted(&who)), Error::<T>::InBlackList);
+
+			let vote_info = <Reports<T>>::get(report_id).ok_or(Error::<T>::NotInVoteList)?;
+			ensure!(Self::vote_result(vote_info.clone()).0 == VoteResult::NoPASS, Error::<T>::PassedProposal);
+			ensure!(who != vote_info.reporter, Error::<T>::Reporter);
+			ensure!(who != vote_info.illegal_man, Error::<T>::IllegalMan);
+			ensure!(!<StakeVotes<T>>::contains_key(report_id, &who), Error::<T>::RepeatVoteError);
+			ensure!(!balance.is_zero() && balance <= T::Currency1::free_balance(&who), Error::<T>::BondTooLow);
+
+			// 与议员投票一样 过期删除相关信息  并且退出(不锁仓)
+			if Self::is_vote_expired(&vote_info){
+				Self::vote_expire_do(report_id, vote_info);
+				return Ok(());
 			}
 
-			// 奖励时间到优先奖励
-			else{
-				Self::remove_expire_voting(n);
-			}
+			let mut tally = <StakeTally<T>>::get(report_id);
+			ensure!(tally.2 < <MaxStakeVoters>::get(), Error::<T>::TooManyStakeVoters);
 
-			}
-			else{
-				   assert!(1==2, "period is zero");
+			// 锁仓
+			let unlock_at = vote_info.start_vote_block + <ProposalExpire<T>>::get()
+				+ <ConvictionLockPeriod<T>>::get() * T::BlockNumber::from(conviction.lock_periods());
+			Self::extend_vote_lock(&who, balance, unlock_at);
 
-				}
+			let votes = conviction.votes(balance).votes;
+			if aye {
+				tally.0 = tally.0.saturating_add(votes);
 			}
-	}
-}
+			else {
+				tally.1 = tally.1.saturating_add(votes);
+			}
+			tally.2 += 1;
+			<StakeTally<T>>::insert(report_id, tally);
+			<StakeVotes<T>>::insert(report_id, &who, (aye, votes));
 
+			Self::judge(report_id, vote_info);
 
-decl_event!(
-	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId,
-		<T as system::Trait>::BlockNumber,
-		<T as balances::Trait>::Balance,
-	 {
+			Self::deposit_event(RawEvent::StakeVoteEvent(who, report_id));
+			Ok(())
+		}
+
+
+		/// 议会直接裁决系统发起的举报(验证人链下验证失败的证据)
+		#[weight = 500_000]
+		pub fn fast_track(origin, report_id: ReportIndex, punish: bool) -> DispatchResult{
+			T::FastTrackOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+
+			let vote_info = <Reports<T>>::get(report_id).ok_or(Error::<T>::NotInVoteList)?;
+			ensure!(Self::is_system_reporter(&vote_info.reporter), Error::<T>::NotSystemReport);
+			ensure!(Self::vote_result(vote_info.clone()).0 == VoteResult::NoPASS, Error::<T>::PassedProposal);
+
+			<FastTracked>::insert(report_id, punish);
+			Self::judge(report_id, vote_info);
+
+			Self::deposit_event(RawEvent::FastTrackEvent(report_id, punish));
+			Ok(())
+		}
+
+
+		/// 解除抵押投票的锁仓
+		#[weight = 500_000]
+		pub fn unlock_vote(origin) -> DispatchResult{
+			let who = ensure_signed(origin)?;
+			let (_, unlock_at) = <VoteLockOf<T>>::get(&who);
+			ensure!(<system::Module<T>>::block_number() >= unlock_at, Error::<T>::VoteLocked);
+
+			T::Currency1::remove_lock(REPORT_VOTE_ID, &who);
+			<VoteLockOf<T>>::remove(&who);
+			Self::deposit_event(RawEvent::VoteUnlocked(who));
+			Ok(())
+		}
+
+
+		/// 被惩罚的矿工在申诉期内发起申诉(需要抵押)
+		#[weight = 500_000]
+		pub fn appeal(origin, tx: Vec<u8>, evidence: Vec<u8>) -> DispatchResult{
+			let who = ensure_signed(origin)?;
+			ensure!(evidence.len() <= MAX_APPEAL_EVIDENCE_LEN, Error::<T>::EvidenceTooLong);
+			ensure!(<AllPunishmentInfo<T>>::contains_key(&tx), Error::<T>::NotPunished);
+
+			let mut info = <AllPunishmentInfo<T>>::get(&tx);
+			// 只有被惩罚的本人可以申诉
+			ensure!(info.vote_info.illegal_man == who, Error::<T>::NotSelf);
+			ensure!(info.appeal.is_none(), Error::<T>::AppealExists);
+
+			// 扣款后的申诉期内才可以申诉
+			let punish_block = info.punish_block.ok_or(Error::<T>::PunishmentNotExecuted)?;
+			let now = <system::Module<T>>::block_number();
+			ensure!(now.saturating_sub(punish_block) <= <AppealWindow<T>>::get(), Error::<T>::AppealExpired);
+
+			let bond = <AppealBond<T>>::get();
+			T::Currency1::reserve(&who, bond).map_err(|_| Error::<T>::BondTooLow)?;
+
+			info.appeal = Some(AppealInfo{
+				appellant: who.clone(),
+				evidence: evidence,
+				start_block: now,
+				status: AppealStatus::Voting,
+				bond,
+			});
+			<AllPunishmentInfo<T>>::insert(&tx, info);
+			<AppealExpiry<T>>::mutate(now.saturating_add(<AppealJudgePeriod<T>>::get()), |txs| txs.push(tx.clone()));
+
+			Self::deposit_event(RawEvent::AppealEvent(who, tx));
+			Ok(())
+		}
+
+
+		/// 裁决申诉
+		/// 申诉成功: 移出黑名单, 国库退还扣款, 没收举报人实际拿到的举报奖励, 退还申诉抵押
+		/// 申诉失败: 没收申诉抵押
+		/// 抵押都按申诉时实际抵押的金额
+		#[weight = 500_000]
+		pub fn judge_appeal(origin, tx: Vec<u8>, accept: bool) -> DispatchResult{
+			T::AppealOrigin::try_origin(origin)
+				.map(|_| ())
+				.or_else(ensure_root)?;
+			ensure!(<AllPunishmentInfo<T>>::contains_key(&tx), Error::<T>::NotPunished);
+
+			let mut info = <AllPunishmentInfo<T>>::get(&tx);
+			let mut appeal = match info.appeal.clone() {
+				Some(a) if a.status == AppealStatus::Voting => a,
+				_ => return Err(Error::<T>::NotAppealing)?,
+			};
+			let appellant = appeal.appellant.clone();
+
+			if accept {
+				// 国库退还扣掉的金额(国库不够就退还国库剩下的) 会失败的转账放在修改数据之前
+				let refund = cmp::min(info.slashed, Self::treasury_useable_balance());
+				if !refund.is_zero() {
+					T::Currency1::transfer(&Self::get_treasury_id(), &appellant, refund, ExistenceRequirement::KeepAlive)?;
+				}
+
+				<register::Module<T>>::remove_from_blacklist(&appellant);
+
+				// 没收举报人实际拿到的举报奖励 这次举报改记为不成立
+				let reporter = info.vote_info.reporter.clone();
+				if !Self::is_system_reporter(&reporter) {
+					T::ReportSlash::on_unbalanced(T::Currency1::slash(&reporter, info.rewarded).0);
+					<ReporterRecords<T>>::mutate(&reporter, |r| {
+						r.passed = r.passed.saturating_sub(1);
+						r.rejected = r.rejected.saturating_add(1);
+					});
+				}
+
+				T::Currency1::unreserve(&appellant, appeal.bond);
+				appeal.status = AppealStatus::Accepted;
+				Self::deposit_event(RawEvent::AppealAccepted(appellant, tx.clone()));
+			}
+			else {
+				T::ReportSlash::on_unbalanced(T::Currency1::slash_reserved(&appellant, appeal.bond).0);
+				appeal.status = AppealStatus::Rejected;
+				Self::deposit_event(RawEvent::AppealRejected(appellant, tx.clone()));
+			}
+
+			info.appeal = Some(appeal);
+			<AllPunishmentInfo<T>>::insert(&tx, info);
+			Ok(())
+		}
+
+
+		fn on_runtime_upgrade() -> Weight {
+			Self::migrate()
+		}
+
+
+		// 每次出块结束都要去计算一下是否是奖励时间 如果是则奖励
+		fn on_finalize(n: T::BlockNumber){
+
+			if !<VoteRewardPeriod<T>>::get().is_zero(){
+				if (n % <VoteRewardPeriod<T>>::get()).is_zero() {  // 默认一天奖励一次
+					<Settling>::put(true);
+					<SettleCursor>::kill();
+				}
+				if <Settling>::get() {
+					Self::reward();  // 奖励的方法
+				}
+				Self::remove_expire_voting(n);
+				Self::expire_appeals(n);
+			}
+			else{
+				   assert!(1==2, "period is zero");
+
+				}
+			}
+	}
+}
+
+
+decl_event!(
+	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId,
+		<T as system::Trait>::BlockNumber,
+		<T as balances::Trait>::Balance,
+	 {
 
-		// 开始的区块 被举报者姓名
-		ReportEvent(BlockNumber, AccountId),
+		// 举报编号 开始的区块 被举报者姓名
+		ReportEvent(ReportIndex, BlockNumber, AccountId),
 
 		// 取消提案
 		CancelReportEvent(AccountId, Vec<u8>),
 
-		//删除mantxhashs
-		RemoveManTxhashs(AccountId, AccountId),
 
 		// 正在投谁的票
 		VoteEvent(AccountId),
@@ -520,70 +1221,587 @@ decl_event!(
 		SetAmount,
 
 		SetTime,
+
+		SetLimit,
+
+		// 申诉(申诉人, tx)
+		AppealEvent(AccountId, Vec<u8>),
+
+		// 申诉过期没有裁决(申诉人, tx)
+		AppealExpired(AccountId, Vec<u8>),
+
+		// 申诉成功(申诉人, tx)
+		AppealAccepted(AccountId, Vec<u8>),
+
+		// 申诉失败(申诉人, tx)
+		AppealRejected(AccountId, Vec<u8>),
+
+		// 议员提交了反驳证据(议员, tx)
+		CounterEvidenceEvent(AccountId, Vec<u8>),
+
+		// 系统发起的举报(举报编号, 提交验证失败结果的验证人数)
+		SystemReportEvent(ReportIndex, u32),
+
+		// 议会直接裁决(举报编号, 作弊是否成立)
+		FastTrackEvent(ReportIndex, bool),
+
+		// 抵押投票(投票人, 举报编号)
+		StakeVoteEvent(AccountId, ReportIndex),
+
+		// 解除抵押投票的锁仓
+		VoteUnlocked(AccountId),
+
+		// 经常与最终结果相反的投票人被惩罚(投票人, 相反的次数, 参与的次数)
+		VoterSlashed(AccountId, u32, u32),
+
+		SetOffenceConfig,
+
+		// 提交了作弊证据(作弊类型, 作弊者, 累计的证据条数)
+		OffenceReported(OffenceKind, AccountId, u32),
+
+		// 作弊成立并且已经处罚(作弊类型, 作弊者)
+		OffencePunished(OffenceKind, AccountId),
 	}
 );
 
 impl<T: Trait> Module <T> {
 
 
-	pub fn remove_voting(tx: Vec<u8>){
-		<Voting<T>>::mutate(|votes| votes.retain(|h| h.0 != tx.clone()));
+	/// 发起举报  need_reserve为false时不抵押(系统发起的举报)
+	fn do_report(who: T::AccountId, tx: Vec<u8>, mine_tag: MineTag, evidence: Evidence, need_reserve: bool) -> Result<ReportIndex, DispatchError>{
+
+		ensure!(evidence.description.len() <= MAX_EVIDENCE_DESCRIPTION_LEN, Error::<T>::EvidenceTooLong);
+
+		let tx_info = if let Some(info) = <OwnerMineRecord<T>>::get(tx.clone(), mine_tag){
+		info
+		}
+		else{
+			return Err(Error::<T>::NotMinerTx)?;
+		};
+		#[cfg(feature = "std")]{
+			debug::info!("tx_info:{:?}",tx_info);
+		}
+
+		let illegalman: T::AccountId = tx_info.miner_address;
+		let decimals = tx_info.decimal;
+		let symbol = tx_info.symbol;
+		let usdt_amount: T::Balance = <<T as balances::Trait>::Balance as TryFrom::<u64>>::try_from(tx_info.usdt_amount).unwrap_or(T::Balance::default());
+
+		let tx_amount = tx_info.sym_amount;
+
+		// 如果作弊者和举报人有至少有一个在黑名单里， 则不给举报。
+		ensure!(!<register::Module<T>>::is_blacklisted(&who) || !<register::Module<T>>::is_blacklisted(&illegalman), Error::<T>::RepoterOrIllegalmanInBlackList);
+
+		// 被举报人必须是注册过的。
+		ensure!(Self::is_register_member(illegalman.clone()), Error::<T>::NotRegister);
+
+		// 根据tx判断这笔交易是否已经存在  已经存在的话不再添加进来
+		ensure!(!<ReportIdOf>::contains_key(&tx), Error::<T>::BeingReported);
+
+		// 被举报人不能已经在被惩罚队列中
+		ensure!(!<AllPunishmentInfo<T>>::contains_key(tx.clone()), Error::<T>::InPunishmentList);
+
+		// 每天的举报次数有限制 没有足够抵押资金，不给举报(抵押金额按信誉计算)
+		let deposit = Self::reputation_reserve(&who);
+		if need_reserve {
+			let today = Self::today();
+			let (day, count) = <ReportsToday<T>>::get(&who);
+			let count = if day == today { count } else { 0 };
+			ensure!(count < Self::daily_report_limit(&who), Error::<T>::TooManyReports);
+
+			T::Currency1::reserve(&who, deposit).map_err(|_| Error::<T>::BondTooLow)?;
+			<ReportsToday<T>>::insert(&who, (today, count + 1));
+		}
+
+		// 获取当前区块高度
+		let start_vote_block = <system::Module<T>>::block_number();
+		let mut vote_info = VoteInfo{
+			start_vote_block: start_vote_block.clone(),
+			symbol: symbol.clone(),
+			tx: tx.clone(),
+			reporter: who.clone(),
+			evidence: evidence,
+			illegal_man: illegalman.clone(),
+			transaction_amount: tx_amount.clone(),
+			usdt_amount: usdt_amount.clone(),
+			decimals: decimals.clone(),
+			approve_mans: vec![],
+			reject_mans:vec![],
+			counter_evidences: vec![],
+		};
+		// 判断投票者是否是议员
+		if Self::is_concil_member(who.clone()) {
+			vote_info.approve_mans.push(who.clone());
+		}
+		// 添加该投票的信息
+		let report_id = Self::insert_report(vote_info.clone());
+		if need_reserve {
+			<ReportDeposit<T>>::insert(report_id, deposit);
+		}
+
+		// 到期时间加入过期队列
+		Self::schedule_expiry(report_id, start_vote_block + <ProposalExpire<T>>::get() + T::BlockNumber::from(1u32));
+
+		<BeingReportedTxsOf<T>>::mutate(illegalman.clone(), |h| h.insert(tx.clone()));
+
+		if Self::is_concil_member(who.clone())	{
+			Self::judge(report_id, vote_info);
+		}
+
+		Self::deposit_event(RawEvent::ReportEvent(report_id, start_vote_block, illegalman));
+		Ok(report_id)
+	}
+
+
+	/// 验证人链下验证交易失败后 以系统的名义发起举报(不需要抵押)
+	/// validators是提交了验证失败结果的验证人 作弊成立时由他们平分举报奖励
+	pub fn system_report(tx: Vec<u8>, mine_tag: MineTag, evidence: Evidence, validators: Vec<T::AccountId>) -> DispatchResult{
+		let report_id = Self::do_report(Self::get_treasury_id(), tx, mine_tag, evidence, false)?;
+		let count = validators.len() as u32;
+		if !validators.is_empty() && <Reports<T>>::contains_key(report_id) {
+			<SystemReportValidators<T>>::insert(report_id, validators);
+		}
+		Self::deposit_event(RawEvent::SystemReportEvent(report_id, count));
+		Ok(())
+	}
+
+
+	/// 系统举报的weight(按do_report最多的读写次数算, 再加上保存验证人与事件)
+	pub fn system_report_weight() -> Weight {
+		T::DbWeight::get().reads_writes(14, 10)
+	}
+
+
+	/// 提交一条作弊证据的weight(按达到阈值执行处罚的分支算)
+	pub fn report_misbehaviour_weight() -> Weight {
+		T::DbWeight::get().reads_writes(10, 8)
+	}
+
+
+	/// 某种作弊的规则(没有设置过的用默认规则)
+	pub fn offence_config(kind: OffenceKind) -> OffenceConfig<BalanceOf<T>> {
+		if let Some(config) = <OffenceConfigs<T>>::get(kind) {
+			return config;
+		}
+		let dollars = |n: Balance| <BalanceOf<T> as TryFrom::<Balance>>::try_from(n * DOLLARS).ok().unwrap_or_else(Zero::zero);
+		match kind {
+			// 与原来一样 本地服务连续20次没有应答才处罚
+			OffenceKind::VerifierOffline => OffenceConfig {
+				threshold: 20,
+				penalty: OffencePenalty::ValidatorSlash(Perbill::from_percent(1)),
+			},
+			OffenceKind::FakeAddress => OffenceConfig {
+				threshold: 3,
+				penalty: OffencePenalty::Slash(dollars(100)),
+			},
+			OffenceKind::AssetMintAbuse => OffenceConfig {
+				threshold: 2,
+				penalty: OffencePenalty::SlashAndBlacklist(<IllegalPunishment<T>>::get()),
+			},
+			OffenceKind::VerifierFault => OffenceConfig {
+				threshold: 3,
+				penalty: OffencePenalty::Slash(dollars(100)),
+			},
+		}
+	}
+
+
+	/// 提交一条作弊证据, 累计到阈值后按规则处罚(返回是否已经处罚)
+	/// 系统提交的证据用国库账户做举报者
+	pub fn report_misbehaviour(
+		kind: OffenceKind,
+		reporter: T::AccountId,
+		offender: T::AccountId,
+		evidence: Vec<u8>,
+	) -> Result<bool, DispatchError>{
+		ensure!(evidence.len() <= MAX_EVIDENCE_DESCRIPTION_LEN, Error::<T>::EvidenceTooLong);
+		ensure!(reporter != offender, Error::<T>::IllegalMan);
+
+		let mut pending = <PendingOffences<T>>::get(kind, &offender);
+		ensure!(!pending.iter().any(|e| e.reporter == reporter && e.evidence == evidence), Error::<T>::DuplicateEvidence);
+		pending.push(OffenceEvidence {
+			reporter,
+			evidence,
+			block: <system::Module<T>>::block_number(),
+		});
+
+		let count = pending.len() as u32;
+		let config = Self::offence_config(kind);
+		Self::deposit_event(RawEvent::OffenceReported(kind, offender.clone(), count));
+		if count < config.threshold {
+			<PendingOffences<T>>::insert(kind, &offender, pending);
+			return Ok(false);
+		}
+
+		<PendingOffences<T>>::remove(kind, &offender);
+		Self::punish_offence(kind, &offender, pending, config.penalty);
+		Ok(true)
+	}
+
+
+	/// 执行作弊的处罚
+	fn punish_offence(
+		kind: OffenceKind,
+		offender: &T::AccountId,
+		evidences: Vec<OffenceEvidence<T::AccountId, T::BlockNumber>>,
+		penalty: OffencePenalty<BalanceOf<T>>,
+	){
+		match penalty {
+			OffencePenalty::Slash(amount) => {
+				T::ReportSlash::on_unbalanced(T::Currency1::slash(offender, amount).0);
+			},
+			OffencePenalty::SlashAndBlacklist(amount) => {
+				let tx = evidences.last().map(|e| e.evidence.clone()).unwrap_or_default();
+				<register::Module<T>>::add_to_blacklist(offender.clone(), tx, kind.reason().to_vec(), None);
+				T::ReportSlash::on_unbalanced(T::Currency1::slash(offender, amount).0);
+			},
+			OffencePenalty::ValidatorSlash(_) => {
+				// 议员提交的证据 举报者可以拿到staking的举报奖励
+				let reporters = evidences.into_iter()
+					.map(|e| e.reporter)
+					.filter(|r| !Self::is_system_reporter(r))
+					.collect::<BTreeSet<_>>()
+					.into_iter()
+					.collect::<Vec<_>>();
+				Self::report_verifier_offence(offender, reporters);
+			},
+		}
+		Self::deposit_event(RawEvent::OffencePunished(kind, offender.clone()));
+	}
+
+
+	/// 本地验证服务没有应答的次数到了惩罚的阶段 交给pallet_offences处罚(不是当前的验证人时不处罚)
+	pub fn punish_verifier_downtime(offender: &T::AccountId) {
+		Self::report_verifier_offence(offender, vec![]);
+	}
+
+
+	fn report_verifier_offence(offender: &T::AccountId, reporters: Vec<T::AccountId>) {
+		let id = match Self::identify_validator(offender) {
+			Some(id) => id,
+			None => {
+				debug::info!("-----{:?} is not a validator, verifier offence ignored-----", offender);
+				return;
+			},
+		};
+		let offence = VerifierOffence::<T> {
+			session_index: <pallet_session::Module<T>>::current_index(),
+			validator_set_count: <pallet_session::Module<T>>::validators().len() as u32,
+			offenders: vec![id],
+		};
+		// 同一个session里重复的作弊会被pallet_offences忽略
+		if let Err(e) = T::ReportOffence::report_offence(reporters, offence) {
+			debug::error!("-----report verifier offence failed: {:?}-----", e);
+		}
+	}
+
+
+	/// 当前验证人在pallet_offences里的身份(不是当前的验证人返回None)
+	fn identify_validator(who: &T::AccountId) -> Option<IdentificationTuple<T>> {
+		let validator_id = T::ValidatorIdOf::convert(who.clone())?;
+		if !<pallet_session::Module<T>>::validators().contains(&validator_id) {
+			return None;
+		}
+		let full_id = T::FullIdentificationOf::convert(validator_id.clone())?;
+		Some((validator_id, full_id))
+	}
+
+
+	/// 升级时迁移旧版本的数据
+	/// 版本0 -> 1: Votes/Voting/RewardList换成用举报编号索引的Reports、ExpiryQueue、RewardQueue
+	/// 			ManTxHashs换成ReportsOf 惩罚记录从VoteInfo变成PunishmentInfo并建立索引PunishedTxsOf
+	/// 返回迁移用掉的weight(按读写的次数算)
+	fn migrate() -> Weight {
+		let version = <StorageVersion>::get();
+		let mut weight = T::DbWeight::get().reads(1);
+		if version < 1 {
+			weight = weight.saturating_add(Self::migrate_v1());
+		}
+		weight
+	}
+
+
+	/// 版本1: 举报按编号保存 过期与奖励改成队列 惩罚记录增加扣款、奖励与申诉信息
+	fn migrate_v1() -> Weight {
+		let slashed = <IllegalPunishment<T>>::get();
+		<AllPunishmentInfo<T>>::translate::<VoteInfoV0<T::BlockNumber, T::AccountId, T::Balance>, _>(|_tx, old| {
+			let start_vote_block = old.start_vote_block.clone();
+			Some(PunishmentInfo{
+				vote_info: old.into(),
+				// 旧记录不知道扣款时间 当作投票开始时已经扣款
+				punish_block: Some(start_vote_block),
+				slashed,
+				// 旧记录不知道实际发了多少奖励 申诉成功时不没收
+				rewarded: Zero::zero(),
+				appeal: None,
+			})
+		});
+
+		let mut punishments = 0u64;
+		for (tx, info) in <AllPunishmentInfo<T>>::iter() {
+			punishments += 1;
+			<PunishedTxsOf<T>>::mutate(&info.vote_info.illegal_man, |txs| txs.push(tx));
+		}
+
+		// 取出旧的Votes、Voting、RewardList 举报者与被举报者的索引由insert_report重建
+		let module = b"ReportModule";
+		let removed = StorageIterator::<Vec<Vec<u8>>>::new(module, b"ManTxHashs").drain().count() as u64;
+		let votes: Vec<VoteInfoOf<T>> = StorageIterator::<VoteInfoV0<T::BlockNumber, T::AccountId, T::Balance>>::new(module, b"Votes")
+			.drain().map(|(_, v)| v.into()).collect();
+		let voting: Vec<Vec<u8>> = take_storage_value::<Vec<(Vec<u8>, VoteInfoV0<T::BlockNumber, T::AccountId, T::Balance>)>>(module, b"Voting", &[])
+			.unwrap_or_default().into_iter().map(|(tx, _)| tx).collect();
+		let reward_list: Vec<VoteInfoOf<T>> = take_storage_value::<Vec<VoteInfoV0<T::BlockNumber, T::AccountId, T::Balance>>>(module, b"RewardList", &[])
+			.unwrap_or_default().into_iter().map(|v| v.into()).collect();
+
+		let (n_votes, n_voting, n_rewards) = (votes.len() as u64, voting.len() as u64, reward_list.len() as u64);
+		for vote_info in votes {
+			Self::insert_report(vote_info);
+		}
+
+		// 正在投票的举报重新加入过期队列(已经过期的下一个区块处理)
+		let next_block = <system::Module<T>>::block_number() + T::BlockNumber::from(1u32);
+		for tx in voting {
+			if let Some(report_id) = <ReportIdOf>::get(&tx) {
+				if let Some(vote_info) = <Reports<T>>::get(report_id) {
+					let expire_at = vote_info.start_vote_block + <ProposalExpire<T>>::get() + T::BlockNumber::from(1u32);
+					Self::schedule_expiry(report_id, cmp::max(expire_at, next_block));
+				}
+			}
+		}
+
+		// 已经有结果的举报以RewardList里的为准
+		for vote_info in reward_list {
+			let report_id = match <ReportIdOf>::get(&vote_info.tx) {
+				Some(report_id) => {
+					<Reports<T>>::insert(report_id, vote_info);
+					report_id
+				},
+				None => Self::insert_report(vote_info),
+			};
+			<RewardQueue>::mutate(|q| q.push(report_id));
+		}
+
+		<StorageVersion>::put(1);
+		// 每个惩罚记录转换时读写1次 建索引时读2次写1次
+		// 保存一个举报要读1次写5次(编号、索引、举报者与被举报者的列表、举报)
+		T::DbWeight::get().reads_writes(
+			3 * punishments + removed + n_votes + 3 * n_voting + 2 * n_rewards + 7,
+			2 * punishments + removed + 6 * n_votes + 2 * n_voting + 6 * n_rewards + 4,
+		)
+	}
+
+
+	/// 处理这个区块过期的申诉 还没有裁决的退还抵押(每个区块最多处理MaxExpirePerBlock个 剩下的留到下一个区块)
+	fn expire_appeals(n: T::BlockNumber) {
+		let mut expiring = <AppealExpiry<T>>::take(n);
+		if expiring.is_empty() {
+			return;
+		}
+		let max = <MaxExpirePerBlock>::get() as usize;
+		if expiring.len() > max {
+			let rest = expiring.split_off(max);
+			<AppealExpiry<T>>::mutate(n + T::BlockNumber::from(1u32), |q| q.extend(rest));
+		}
+
+		for tx in expiring {
+			if !<AllPunishmentInfo<T>>::contains_key(&tx) {
+				continue;
+			}
+			<AllPunishmentInfo<T>>::mutate(&tx, |info| {
+				if let Some(appeal) = info.appeal.as_mut() {
+					if appeal.status == AppealStatus::Voting {
+						T::Currency1::unreserve(&appeal.appellant, appeal.bond);
+						appeal.status = AppealStatus::Expired;
+						Self::deposit_event(RawEvent::AppealExpired(appeal.appellant.clone(), tx.clone()));
+					}
+				}
+			});
+		}
+	}
+
+
+	/// 保存一个新的举报 返回举报编号
+	fn insert_report(vote_info: VoteInfoOf<T>) -> ReportIndex {
+		let report_id = <NextReportId>::get();
+		<NextReportId>::put(report_id.wrapping_add(1));
+		<ReportIdOf>::insert(&vote_info.tx, report_id);
+		<ReportsOf<T>>::mutate(&vote_info.reporter, |ids| ids.push(report_id));
+		if vote_info.illegal_man != vote_info.reporter {
+			<ReportsOf<T>>::mutate(&vote_info.illegal_man, |ids| ids.push(report_id));
+		}
+		<Reports<T>>::insert(report_id, vote_info);
+		report_id
+	}
+
+
+	/// 删除举报(过期队列与奖励队列里的编号处理时会被跳过)
+	fn remove_report(report_id: ReportIndex, tx: &Vec<u8>) {
+		if let Some(vote_info) = <Reports<T>>::take(report_id) {
+			for who in [vote_info.reporter, vote_info.illegal_man].iter() {
+				<ReportsOf<T>>::mutate_exists(who, |ids| {
+					let empty = match ids {
+						Some(list) => {
+							list.retain(|id| *id != report_id);
+							list.is_empty()
+						},
+						None => false,
+					};
+					if empty {
+						*ids = None;
+					}
+				});
+			}
+		}
+		<ReportIdOf>::remove(tx);
+		<StakeVotes<T>>::remove_prefix(report_id);
+		<StakeTally<T>>::remove(report_id);
+		<SystemReportValidators<T>>::remove(report_id);
+		<FastTracked>::remove(report_id);
+		<ReportDeposit<T>>::remove(report_id);
+	}
+
+
+	/// 增加抵押投票的锁仓(金额和解锁时间都取较大的)
+	fn extend_vote_lock(who: &T::AccountId, balance: BalanceOf<T>, unlock_at: T::BlockNumber) {
+		let (amount, until) = <VoteLockOf<T>>::get(who);
+		let amount = cmp::max(amount, balance);
+		let until = cmp::max(until, unlock_at);
+		T::Currency1::set_lock(REPORT_VOTE_ID, who, amount, WithdrawReasons::all());
+		<VoteLockOf<T>>::insert(who, (amount, until));
+	}
+
+
+	/// 裁决结束后记录每个投票人是否与最终结果一致
+	fn settle_voters(report_id: ReportIndex, voting: &VoteInfoOf<T>, is_punished: bool) {
+		for who in voting.approve_mans.iter() {
+			Self::note_voter(who, is_punished);
+		}
+		for who in voting.reject_mans.iter() {
+			Self::note_voter(who, !is_punished);
+		}
+		for (who, (aye, _)) in <StakeVotes<T>>::drain_prefix(report_id) {
+			Self::note_voter(&who, aye == is_punished);
+		}
+	}
+
+
+	/// 记录投票人的一次裁决 与最终结果相反的比例太高就惩罚
+	fn note_voter(who: &T::AccountId, with_result: bool) {
+		let (mut total, mut against) = <VoterRecords<T>>::get(who);
+		total = total.saturating_add(1);
+		if !with_result {
+			against = against.saturating_add(1);
+		}
+
+		if total >= <VoterSlashMinVotes>::get()
+			&& Permill::from_rational_approximation(against, total) >= <VoterSlashThreshold>::get() {
+			T::ReportSlash::on_unbalanced(T::Currency1::slash(who, <VoterSlash<T>>::get()).0);
+			Self::deposit_event(RawEvent::VoterSlashed(who.clone(), against, total));
+			// 惩罚后重新计算
+			<VoterRecords<T>>::remove(who);
+		}
+		else {
+			<VoterRecords<T>>::insert(who, (total, against));
+		}
+	}
+
+
+	/// 投票期是否已经结束
+	fn is_vote_expired(vote_info: &VoteInfoOf<T>) -> bool {
+		<system::Module<T>>::block_number() - vote_info.start_vote_block > <ProposalExpire<T>>::get()
+	}
+
+
+	/// 举报在某个区块过期
+	fn schedule_expiry(report_id: ReportIndex, at: T::BlockNumber) {
+		<ExpiryQueue<T>>::mutate(at, |q| q.push(report_id));
+	}
+
+
+	/// 是否是系统发起的举报(举报人是国库账号, 没有抵押也没有举报奖励)
+	pub fn is_system_reporter(who: &T::AccountId) -> bool {
+		who == &Self::get_treasury_id()
 	}
 
 
 	/// 判断投票是否结束 并执行相应操作
-	pub fn judge(tx: Vec<u8>, voting: VoteInfo<T::BlockNumber, T::AccountId, T::Balance>){
+	pub fn judge(report_id: ReportIndex, voting: VoteInfoOf<T>){
 
 		let illegalman = voting.illegal_man.clone();
 		let reporter = voting.reporter.clone();
+		let tx = voting.tx.clone();
 
 		// 判断议案是否结束
 		let vote_result = Self::vote_result(voting.clone());
 		// 如果议案投票已经结束
 		if vote_result.0 == VoteResult::PASS{
 
+			// 记录投票人与最终结果是否一致
+			Self::settle_voters(report_id, &voting, vote_result.1 == IsPunished::YES);
+
+			// 记录举报者的举报结果
+			if !Self::is_system_reporter(&reporter) {
+				<ReporterRecords<T>>::mutate(&reporter, |r| if vote_result.1 == IsPunished::YES {
+					r.passed = r.passed.saturating_add(1);
+				} else {
+					r.rejected = r.rejected.saturating_add(1);
+				});
+			}
+
 			// 把该投票结果存储到奖励名单
-			<RewardList<T>>::mutate(|a| a.push(voting.clone()));
-			Self::remove_mantxhashs(reporter.clone(),tx.clone());
-			Self::remove_mantxhashs(illegalman.clone(),tx.clone());
-			Self::deposit_event(RawEvent::RemoveManTxhashs(reporter.clone(), illegalman.clone()));
+			<RewardQueue>::mutate(|a| a.push(report_id));
 			// 如果作弊是真  把名字加入黑名单  并且从注册列表中删除  把该投票信息保存
 			if vote_result.1 == IsPunished::YES{
 
 				// 先于奖励加入黑名单
-				<BlackList<T>>::insert(illegalman.clone(), tx.clone());
+				<register::Module<T>>::add_to_blacklist(illegalman.clone(), tx.clone(), voting.evidence.description.clone(), Some(report_id));
 				Self::kill_register(illegalman.clone());
 				Self::deposit_event(RawEvent::KillRegisterEvent(illegalman.clone()));
 
-				// 永久保存该投票信息
-				<AllPunishmentInfo<T>>::insert(tx.clone(), voting.clone());
+				// 永久保存该投票信息(扣款时再记录扣款金额)
+				<PunishedTxsOf<T>>::mutate(&illegalman, |txs| txs.push(tx.clone()));
+				<AllPunishmentInfo<T>>::insert(tx.clone(), PunishmentInfo{
+					vote_info: voting.clone(),
+					punish_block: None,
+					slashed: <BalanceOf<T>>::from(0),
+					rewarded: <BalanceOf<T>>::from(0),
+					appeal: None,
+				});
 			}
-
-			// 从正在投票队列中移除
-			Self::remove_voting(tx.clone())
 		}
 
-		else{
-			// 修改正在投票的队列
-			<Voting<T>>::mutate(|votes| votes.retain(|h| h.0 != tx));
-			<Voting<T>>::mutate(|votes| votes.push((tx.clone(), voting.clone())));
-		}
+		// 保存最新的投票信息
+		<Reports<T>>::insert(report_id, voting);
 	}
 
 
+	/// 处理这个区块过期的举报 每个区块最多处理MaxExpirePerBlock个 剩下的留到下一个区块
 	pub fn remove_expire_voting(n: T::BlockNumber){
 
-		<Voting<T>>::mutate(|votes| votes.retain(|vote| if n - <Votes<T>>::get(&vote.0).start_vote_block <= <ProposalExpire<T>>::get(){
-			true
+		let mut expiring = <ExpiryQueue<T>>::take(n);
+		if expiring.is_empty() {
+			return;
+		}
+		let max = <MaxExpirePerBlock>::get() as usize;
+		if expiring.len() > max {
+			let rest = expiring.split_off(max);
+			<ExpiryQueue<T>>::mutate(n + T::BlockNumber::from(1u32), |q| q.extend(rest));
 		}
-			else {
-				let vote_info = <Votes<T>>::get(&vote.0);
-				Self::vote_expire_do(vote_info.clone().tx, vote_info.clone());
-				false
-			}
-
-		));
 
+		for report_id in expiring {
+			// 已经取消、已经处理或是投票已经结束的跳过
+			let vote_info = match <Reports<T>>::get(report_id) {
+				Some(v) if Self::vote_result(v.clone()).0 == VoteResult::NoPASS => v,
+				_ => continue,
+			};
+			// 过期时间被调大了 重新排队
+			let expire_at = vote_info.start_vote_block + <ProposalExpire<T>>::get() + T::BlockNumber::from(1u32);
+			if expire_at > n {
+				Self::schedule_expiry(report_id, expire_at);
+				continue;
+			}
+			Self::vote_expire_do(report_id, vote_info);
+		}
 	}
 
 
@@ -594,77 +1812,65 @@ impl<T: Trait> Module <T> {
 
 
 	// 判断投票过期后做的
-	pub fn vote_expire_do(tx: Vec<u8>,  vote_info: VoteInfo<T::BlockNumber, T::AccountId, T::Balance>){
+	pub fn vote_expire_do(report_id: ReportIndex, vote_info: VoteInfoOf<T>){
+		let tx = vote_info.tx;
 		let reporter = vote_info.reporter;
 		let illegalman = vote_info.illegal_man;
-		<Votes<T>>::remove(&tx);
-		// TODO 添加和删除方法均已经实现， 注意查看代码是否正确
+		let deposit = Self::deposit_of(&tx);
+		Self::remove_report(report_id, &tx);
 		// 把举报者的抵押归还
-		T::Currency1::unreserve(&reporter, <ReportReserve<T>>::get());
-		// 删除相关的man thhashs信息
-		Self::remove_mantxhashs(reporter.clone(), tx.clone());
-		Self::remove_mantxhashs(illegalman.clone(), tx.clone());
+		T::Currency1::unreserve(&reporter, deposit);
 
 		<BeingReportedTxsOf<T>>::mutate(illegalman.clone(), |h| h.remove(&tx));
-		// 从正在投票队列中移除
-		Self::remove_voting(tx.clone());
-
-
 	}
 
 
+	/// 处理投票结果 每个区块最多处理MaxSettlePerBlock个 这一轮处理不完的留到下一个区块
+	/// 国库不够支付的举报留在队列里 等国库有钱了再处理
 	pub fn reward() -> Result<(), DispatchError> {
-		// 计算国库还有多少钱
-		let mut useable_balance = Self::treasury_useable_balance();
-		// 获取国库id
-		let treasury_id = Self::get_treasury_id();
-
-		// 这一步按照两个步骤来走
-		for i in 0..2 {
-			<RewardList<T>>::mutate(|v| {
-			v.retain(|voteinfo| {
-				let is_punish = Self::vote_result(voteinfo.clone()).1;
-				let treasury_result = Self::treasury_imbalance(is_punish.clone(), voteinfo.clone(), false);
-				let sub_or_add = treasury_result.0;
-				let imbalances = treasury_result.1;
-
-				// 如果国库需要添加金额
-				if sub_or_add == TreasuryNeed::ADD{
-					// 给国库增加金额
-					useable_balance += imbalances;
-					T::Currency1::make_free_balance_be(&treasury_id, useable_balance + T::Currency1::minimum_balance());
-					Self::treasury_imbalance(is_punish.clone(), voteinfo.clone(), true);
-					let illegalman = voteinfo.illegal_man.clone();
-					<BeingReportedTxsOf<T>>::mutate(illegalman, |h|  h.remove(&voteinfo.tx));
-
-					// 彻底删掉投票信息
-					<Votes<T>>::remove(voteinfo.clone().tx);
-
-					false
-				}
-					// 如果国库需要减掉金额
-				else{
-					if useable_balance >= imbalances{
-						// 给国库减掉金额
-						useable_balance -= imbalances;
-						T::Currency1::make_free_balance_be(&treasury_id, useable_balance + T::Currency1::minimum_balance());
-
-						let illegalman = voteinfo.illegal_man.clone();
-						<BeingReportedTxsOf<T>>::mutate(illegalman, |h|  h.remove(&voteinfo.tx));
-
-						// 彻底删掉投票信息
-						<Votes<T>>::remove(voteinfo.clone().tx);
-						Self::treasury_imbalance(is_punish.clone(), voteinfo.clone(), true);
-						false
-					}
-						// 金额不够 暂时不执行
-					else{
-						true
-					}
-				}
-			});
-		});
+		let mut queue = <RewardQueue>::get();
+		let cursor = cmp::min(<SettleCursor>::get() as usize, queue.len());
+		let end = cmp::min(cursor.saturating_add(<MaxSettlePerBlock>::get() as usize), queue.len());
+		let batch: Vec<ReportIndex> = queue.drain(cursor..end).collect();
+
+		let mut waiting: Vec<ReportIndex> = vec![];
+		for report_id in batch {
+			let voteinfo = match <Reports<T>>::get(report_id) {
+				Some(v) => v,
+				None => continue,
+			};
+			let is_punish = Self::vote_result(voteinfo.clone()).1;
+			let (sub_or_add, imbalances) = Self::treasury_imbalance(is_punish.clone(), voteinfo.clone());
+
+			// 国库不够支付 暂时不执行
+			if sub_or_add == TreasuryNeed::SUB && Self::treasury_useable_balance() < imbalances {
+				waiting.push(report_id);
+				continue;
+			}
+
+			Self::settle(is_punish, voteinfo.clone());
+
+			let illegalman = voteinfo.illegal_man.clone();
+			<BeingReportedTxsOf<T>>::mutate(illegalman, |h|  h.remove(&voteinfo.tx));
+
+			// 彻底删掉投票信息
+			Self::remove_report(report_id, &voteinfo.tx);
+			Self::deposit_event(RawEvent::RewardEvent(voteinfo.reporter, voteinfo.tx));
+		}
+
+		// 没有处理的放回原来的位置 下一个区块从它们后面继续
+		let next_cursor = cursor + waiting.len();
+		for (i, report_id) in waiting.into_iter().enumerate() {
+			queue.insert(cursor + i, report_id);
 		}
+		if next_cursor >= queue.len() {
+			<Settling>::put(false);
+			<SettleCursor>::kill();
+		}
+		else {
+			<SettleCursor>::put(next_cursor as u32);
+		}
+		<RewardQueue>::put(queue);
 		Ok(())
 	}
 
@@ -711,157 +1917,320 @@ impl<T: Trait> Module <T> {
 	}
 
 
-	// 添加man hashs映射添加相关信息
-	pub fn add_mantxhashs(who: T::AccountId, tx: Vec<u8>) {
-		let mut vec_txhash = vec![];
-		if <ManTxHashs<T>>::contains_key(&who) {
-			vec_txhash = <ManTxHashs<T>>::get(&who);
-			vec_txhash.push(tx);
-		} else {
-			vec_txhash.push(tx)
-		}
-		<ManTxHashs<T>>::insert(&who, &vec_txhash);
-	}
+	// 这个方法用于验证投票是否结束（是否有一方胜出）
+	pub fn vote_result(vote_info: VoteInfo<T::BlockNumber, T::AccountId, T::Balance>)
+		-> (VoteResult, IsPunished) {
+
+		let report_id = <ReportIdOf>::get(&vote_info.tx);
 
+		// 议会直接裁决过的以裁决为准
+		match report_id.and_then(|id| <FastTracked>::get(id)) {
+			Some(true) => return (VoteResult::PASS, IsPunished::YES),
+			Some(false) => return (VoteResult::PASS, IsPunished::NO),
+			None => {},
+		}
 
-	// 删除man txhashs相关信息
-	pub fn remove_mantxhashs(who: T::AccountId, tx: Vec<u8>) {
-		let mut vec_txhash = vec![];
-		vec_txhash = <ManTxHashs<T>>::get(&who);
-		if let Some(pos) = vec_txhash.iter().position(|a| <Votes<T>>::contains_key(&tx)) {
-			vec_txhash.swap_remove(pos);
+		let stake = report_id
+			.map(|id| <StakeTally<T>>::get(id))
+			.unwrap_or_default();
+		let tally = JudgementTally{
+			council_ayes: vote_info.approve_mans.len() as u32,
+			council_nays: vote_info.reject_mans.len() as u32,
+			council_size: Self::get_members_count(),
+			stake_ayes: stake.0,
+			stake_nays: stake.1,
 		};
-		if vec_txhash.len() == 0 {
-			<ManTxHashs<T>>::remove(&who)
-		} else {
-			<ManTxHashs<T>>::insert(&who, &vec_txhash);
+
+		// 由裁决规则决定是否结束
+		match T::JudgementPolicy::judge(&tally) {
+			Some(true) => (VoteResult::PASS, IsPunished::YES),
+			Some(false) => (VoteResult::PASS, IsPunished::NO),
+			None => (VoteResult::NoPASS, IsPunished::NO),
 		}
+
 	}
 
 
-	// 这个方法用于验证投票是否结束（是否有一方胜出）
-	pub fn vote_result(vote_info: VoteInfo<T::BlockNumber, T::AccountId, T::Balance>)
-		-> (VoteResult, IsPunished) {
+	// 计算国库盈余或是亏损多少(只计算 不操作)
+	pub fn treasury_imbalance(is_punish: IsPunished, vote:
+	VoteInfo<T::BlockNumber, T::AccountId, T::Balance>) -> (TreasuryNeed, BalanceOf<T>) {
 
-		let approve_len = vote_info.approve_mans.len() as u32;
-		let reject_len = vote_info.reject_mans.len() as u32;
-		// 胜出两票或是有一方先过半 那么就结束
+		let reporter = vote.reporter.clone();
+		let illegalman = vote.illegal_man.clone();
+		let mut postive: BalanceOf<T> = 0.into();
+		let mut negative: BalanceOf<T> = 0.into();
+		// 真的作弊
+		if is_punish == IsPunished::YES {
+			// 扣除作弊者的金额进入国库
+			postive = cmp::min(T::Currency1::total_balance(&illegalman), <IllegalPunishment<T>>::get());
 
-		let n = cmp::max(approve_len, reject_len) - cmp::min(approve_len, reject_len);
-		let thredshould = (Self::get_members_count() + 1u32)/2u32;
-		// 相等情况下赞同优先
-		if (thredshould > 0u32) && (n >= 2 || approve_len >= thredshould || reject_len >= thredshould){
-			if approve_len >= reject_len {
-				(VoteResult::PASS, IsPunished::YES)
-			} else {
-				(VoteResult::PASS, IsPunished::NO)
+			// 奖励举报者的金额(如果这个人已经在黑名单或是系统发起的举报， 则不给奖励)
+			if Self::should_reward_reporter(&reporter) {
+				negative = Self::reputation_reward(&reporter);
 			}
 
-		} else {
-			(VoteResult::NoPASS, IsPunished::NO)
+			// 系统发起的举报 由提交证据的验证人平分举报奖励
+			let (validators, each) = Self::upheld_validators(&vote);
+			negative = negative.saturating_add(each.saturating_mul(<BalanceOf<T>>::from(validators.len() as u32)));
 		}
 
+		// 虚假举报 举报者的抵押进入国库(系统发起的举报没有抵押)
+		else if !Self::is_system_reporter(&reporter) {
+			postive = cmp::min(T::Currency1::reserved_balance(&reporter), Self::deposit_of(&vote.tx));
+		}
+
+		// 议员总奖励金额
+		let council_count = <BalanceOf<T>>::from(Self::rewarded_council(&vote).len() as u32);
+		negative = negative.saturating_add(<CouncilReward<T>>::get().saturating_mul(council_count));
+
+		// 惩罚金额如果还有 那就直接给国库
+		if postive > negative {
+			(TreasuryNeed::ADD, postive - negative)
+
+		// 惩罚金额没有剩余 从国库扣除
+		} else {
+			(TreasuryNeed::SUB, negative - postive)
+		}
 	}
 
 
-	// 计算国库盈余或是亏损多少  第一个参数返回true是盈余  返回false是亏损
-	pub fn treasury_imbalance(is_punish: IsPunished, vote:
-	VoteInfo<T::BlockNumber, T::AccountId, T::Balance>, is_oprate: bool) -> (TreasuryNeed, BalanceOf<T>) {
+	/// 执行投票结果的金额操作
+	/// 扣除的金额通过ReportSlash进入国库, 奖励都从国库转账, 不会凭空增加或是减少总发行量
+	fn settle(is_punish: IsPunished, vote: VoteInfo<T::BlockNumber, T::AccountId, T::Balance>) {
+
+		let reporter = vote.reporter.clone();
+		let illegalman = vote.illegal_man.clone();
 
-		let reporter = vote.clone().reporter;
-		let illegalman = vote.clone().illegal_man;
-		let mut postive: BalanceOf<T> = 0.into();
-		let mut negative: BalanceOf<T> = 0.into();
 		// 真的作弊
 		if is_punish == IsPunished::YES {
-			if T::Currency1::total_balance(&illegalman) >= <IllegalPunishment<T>>::get(){
-				postive = <IllegalPunishment<T>>::get();
-			}
-			else{
-				postive = T::Currency1::total_balance(&illegalman);
-			}
 
-			// 奖励举报者的总金额(如果这个人已经在黑名单， 则不给奖励)
-			if !<BlackList<T>>::contains_key(reporter.clone()) {
-				// 数额巨大 不需要考虑存活问题
-				negative = <ReportReward<T>>::get();
-			}
+			// 释放锁并且扣除金额
+			T::Currency1::remove_lock(REGISTER_ID, &illegalman);
+			let (imbalance, unslashed) = T::Currency1::slash(&illegalman, <IllegalPunishment<T>>::get());
+			T::ReportSlash::on_unbalanced(imbalance);
 
-			// 如果确定进行金额操作
-			if is_oprate{
+			// 释放举报者的抵押金额
+			T::Currency1::unreserve(&reporter, Self::deposit_of(&vote.tx));
 
-				// 释放锁并且扣除金额
-				T::Currency1::remove_lock(REGISTER_ID, &illegalman);
-				T::Currency1::slash(&illegalman, <IllegalPunishment<T>>::get());
+			let rewarded = if Self::should_reward_reporter(&reporter) {
+				Self::pay_from_treasury(&reporter, Self::reputation_reward(&reporter))
+			} else {
+				Zero::zero()
+			};
 
-				// 释放举报者的抵押金额
-				T::Currency1::unreserve(&reporter, <ReportReserve<T>>::get());
+			// 记录扣款与实际发放的举报奖励 申诉期从这里开始算
+			if <AllPunishmentInfo<T>>::contains_key(&vote.tx) {
+				let now = <system::Module<T>>::block_number();
+				<AllPunishmentInfo<T>>::mutate(&vote.tx, |h| {
+					h.punish_block = Some(now);
+					h.slashed = <IllegalPunishment<T>>::get().saturating_sub(unslashed);
+					h.rewarded = rewarded;
+				});
+			}
 
-				// 数额巨大 不需要考虑存活问题
-				if negative > <BalanceOf<T>>::from(0){
-					T::Currency1::deposit_creating(&reporter, <ReportReward<T>>::get());
-				}
+			let (validators, each) = Self::upheld_validators(&vote);
+			for validator in validators.iter() {
+				Self::pay_from_treasury(validator, each);
 			}
+		}
 
+		// 虚假举报(系统发起的举报没有抵押 不做惩罚)
+		else if !Self::is_system_reporter(&reporter) {
+			T::ReportSlash::on_unbalanced(T::Currency1::slash_reserved(&reporter, Self::deposit_of(&vote.tx)).0);
 		}
 
-		// 虚假举报
-		else {
-			// 账户必须要存在(不管你是否在黑名单 均会扣除费用)
-			if !(T::DeadOrigin::is_dead_account(&reporter)){
-				if T::Currency1::total_balance(&reporter) >= <ReportReserve<T>>::get(){
-					postive += <ReportReserve<T>>::get();
-					if is_oprate{
+		// 奖励参与投票的议员
+		for peaple in Self::rewarded_council(&vote) {
+			Self::pay_from_treasury(&peaple, <CouncilReward<T>>::get());
+		}
+	}
 
-					}
-				}
-				else{
-					postive += T::Currency1::total_balance(&reporter);
-				}
-				if is_oprate{
-					T::Currency1::slash_reserved(&reporter, <ReportReserve<T>>::get());
+
+	/// 举报实际抵押的金额(升级之前的举报按ReportReserve算, 系统发起的举报没有抵押)
+	fn deposit_of(tx: &[u8]) -> BalanceOf<T> {
+		match <ReportIdOf>::get(tx) {
+			Some(report_id) => <ReportDeposit<T>>::get(report_id).unwrap_or_else(|| {
+				match <Reports<T>>::get(report_id) {
+					Some(v) if Self::is_system_reporter(&v.reporter) => <BalanceOf<T>>::from(0),
+					_ => <ReportReserve<T>>::get(),
 				}
+			}),
+			None => <ReportReserve<T>>::get(),
+		}
+	}
 
-			}
+
+	/// 今天是第几天
+	fn today() -> u32 {
+		(<system::Module<T>>::block_number() / T::BlockNumber::from(DAYS)).saturated_into::<u32>()
+	}
+
+
+	/// 按信誉计算的举报抵押金额(100分是0.5倍, 50分是1倍, 0分是1.5倍)
+	pub fn reputation_reserve(who: &T::AccountId) -> BalanceOf<T> {
+		let score = <ReporterRecords<T>>::get(who).score();
+		<ReportReserve<T>>::get().saturating_mul(<BalanceOf<T>>::from(150u32 - score)) / <BalanceOf<T>>::from(100u32)
+	}
+
+
+	/// 按信誉计算的举报奖励(100分是1.5倍, 50分是1倍, 0分是0.5倍)
+	pub fn reputation_reward(who: &T::AccountId) -> BalanceOf<T> {
+		let score = <ReporterRecords<T>>::get(who).score();
+		<ReportReward<T>>::get().saturating_mul(<BalanceOf<T>>::from(50u32 + score)) / <BalanceOf<T>>::from(100u32)
+	}
+
+
+	/// 每天最多可以举报的次数(按信誉分线性增加, 最少1次)
+	pub fn daily_report_limit(who: &T::AccountId) -> u32 {
+		let score = <ReporterRecords<T>>::get(who).score();
+		cmp::max(<MaxReportsPerDay>::get().saturating_mul(score) / REPUTATION_BASE, 1)
+	}
+
+
+	/// 举报者的信誉(给runtime api用)
+	pub fn reporter_reputation(who: T::AccountId) -> ReporterReputation<BalanceOf<T>> {
+		let record = <ReporterRecords<T>>::get(&who);
+		let (day, count) = <ReportsToday<T>>::get(&who);
+		ReporterReputation{
+			passed: record.passed,
+			rejected: record.rejected,
+			cancelled: record.cancelled,
+			score: record.score(),
+			reserve: Self::reputation_reserve(&who),
+			reward: Self::reputation_reward(&who),
+			daily_limit: Self::daily_report_limit(&who),
+			reported_today: if day == Self::today() { count } else { 0 },
 		}
+	}
 
-		// 议员总奖励金额
-		let mut all_mans =
-			vote.reject_mans.iter().chain(vote.approve_mans.iter());
-
-		for i in 0..all_mans.clone().count() {
-			if let Some(peaple) = all_mans.next() {
-				// 如果已经进入黑名单 则不给奖励
-				if !<BlackList<T>>::contains_key(&peaple){
-					// 如果账户还存在
-					if !(T::DeadOrigin::is_dead_account(&peaple)){
-						negative += <CouncilReward<T>>::get();
-						if is_oprate{
-							T::Currency1::deposit_creating(&peaple, <CouncilReward<T>>::get());
-						}
-					}
 
-					// 如果账户已经不存在
-					else{
-						if <ReportReward<T>>::get() >= T::Currency1::minimum_balance(){
-							negative += <CouncilReward<T>>::get();
-							if is_oprate{
-								T::Currency1::deposit_creating(&peaple, <CouncilReward<T>>::get());
-							}
-						}
-					}
+	/// 举报的概况(给runtime api用)
+	fn report_summary(report_id: ReportIndex, vote_info: VoteInfoOf<T>) -> ReportSummary<T::AccountId, T::BlockNumber> {
+		let expire_block = vote_info.start_vote_block + <ProposalExpire<T>>::get() + T::BlockNumber::from(1u32);
+		let (vote_result, is_punished) = Self::vote_result(vote_info.clone());
+		let result = if vote_result == VoteResult::PASS { Some(is_punished == IsPunished::YES) } else { None };
+		let remaining_blocks = if result.is_some() {
+			Zero::zero()
+		} else {
+			expire_block.saturating_sub(<system::Module<T>>::block_number())
+		};
+		ReportSummary{
+			report_id,
+			tx: vote_info.tx,
+			symbol: vote_info.symbol,
+			reporter: vote_info.reporter,
+			illegal_man: vote_info.illegal_man,
+			start_block: vote_info.start_vote_block,
+			expire_block,
+			remaining_blocks,
+			result,
+		}
+	}
+
+
+	/// 所有还没有处理完的举报(给runtime api用)
+	pub fn open_reports() -> Vec<ReportSummary<T::AccountId, T::BlockNumber>> {
+		let mut reports: Vec<_> = <Reports<T>>::iter()
+			.map(|(report_id, vote_info)| Self::report_summary(report_id, vote_info))
+			.collect();
+		reports.sort_by_key(|r| r.report_id);
+		reports
+	}
+
+
+	/// 举报的票数(给runtime api用)
+	pub fn report_tally(report_id: ReportIndex) -> Option<ReportTally<T::AccountId, BalanceOf<T>>> {
+		let vote_info = <Reports<T>>::get(report_id)?;
+		let (stake_ayes, stake_nays, stake_voters) = <StakeTally<T>>::get(report_id);
+		let council_size = Self::get_members_count();
+		let (vote_result, is_punished) = Self::vote_result(vote_info.clone());
+		Some(ReportTally{
+			report_id,
+			approve_mans: vote_info.approve_mans,
+			reject_mans: vote_info.reject_mans,
+			council_size,
+			council_threshold: T::JudgementPolicy::council_threshold(council_size),
+			stake_ayes,
+			stake_nays,
+			stake_voters,
+			min_turnout: T::JudgementPolicy::min_turnout(),
+			fast_tracked: <FastTracked>::get(report_id),
+			result: if vote_result == VoteResult::PASS { Some(is_punished == IsPunished::YES) } else { None },
+		})
+	}
+
+
+	/// 与某个账户有关的举报(给runtime api用)
+	pub fn reports_of(who: T::AccountId) -> Vec<ReportSummary<T::AccountId, T::BlockNumber>> {
+		<ReportsOf<T>>::get(&who).into_iter()
+			.filter_map(|report_id| <Reports<T>>::get(report_id).map(|v| Self::report_summary(report_id, v)))
+			.collect()
+	}
+
+
+	/// 矿工被惩罚的历史(给runtime api用)
+	pub fn punishment_history(who: T::AccountId) -> Vec<PunishmentRecord<T::AccountId, T::BlockNumber, BalanceOf<T>>> {
+		<PunishedTxsOf<T>>::get(&who).into_iter()
+			.filter(|tx| <AllPunishmentInfo<T>>::contains_key(tx))
+			.map(|tx| {
+				let info = <AllPunishmentInfo<T>>::get(&tx);
+				PunishmentRecord{
+					tx,
+					reporter: info.vote_info.reporter,
+					punish_block: info.punish_block,
+					slashed: info.slashed,
+					appeal: info.appeal.map(|a| a.status),
 				}
+			})
+			.collect()
+	}
 
-			};
+
+	/// 是否奖励举报者(已经在黑名单或是系统发起的举报不奖励)
+	fn should_reward_reporter(reporter: &T::AccountId) -> bool {
+		!<register::Module<T>>::is_blacklisted(reporter) && !Self::is_system_reporter(reporter)
+	}
+
+
+	/// 系统发起的举报中可以拿到奖励的验证人(进入黑名单的不给奖励) 以及每个人的奖励金额
+	fn upheld_validators(vote: &VoteInfo<T::BlockNumber, T::AccountId, T::Balance>) -> (Vec<T::AccountId>, BalanceOf<T>) {
+		if !Self::is_system_reporter(&vote.reporter) {
+			return (vec![], <BalanceOf<T>>::from(0));
+		}
+		let validators: Vec<T::AccountId> = <ReportIdOf>::get(&vote.tx)
+			.map(|id| <SystemReportValidators<T>>::get(id))
+			.unwrap_or_default()
+			.into_iter()
+			.filter(|who| !<register::Module<T>>::is_blacklisted(who))
+			.collect();
+		if validators.is_empty() {
+			return (validators, <BalanceOf<T>>::from(0));
 		}
+		let each = <ReportReward<T>>::get() / <BalanceOf<T>>::from(validators.len() as u32);
+		(validators, each)
+	}
 
-		// 惩罚金额如果还有 那就直接给国库
-		if postive > negative {
-			(TreasuryNeed::ADD, postive - negative)
 
-		// 惩罚金额没有剩余 从国库扣除
-		} else {
-			(TreasuryNeed::SUB, negative - postive)
+	/// 可以拿到奖励的议员(进入黑名单的不给奖励)
+	fn rewarded_council(vote: &VoteInfo<T::BlockNumber, T::AccountId, T::Balance>) -> Vec<T::AccountId> {
+		vote.reject_mans.iter().chain(vote.approve_mans.iter())
+			.filter(|who| !<register::Module<T>>::is_blacklisted(who))
+			.cloned()
+			.collect()
+	}
+
+
+	/// 从国库转账 返回实际转了多少(失败是0)
+	fn pay_from_treasury(who: &T::AccountId, amount: BalanceOf<T>) -> BalanceOf<T> {
+		if amount.is_zero() {
+			return amount;
+		}
+		match T::Currency1::transfer(&Self::get_treasury_id(), who, amount, ExistenceRequirement::KeepAlive) {
+			Ok(()) => amount,
+			Err(e) => {
+				debug::warn!("-----report pay from treasury failed, account:{:?}, err:{:?}------", who, e);
+				Zero::zero()
+			},
 		}
 	}
 
@@ -876,5 +2245,893 @@ impl<T: Trait> ReportedTxs<T::AccountId> for Module<T>{
 }
 
 
+impl<T: Trait> SuspiciousTxs<T::AccountId, MineTag> for Module<T>{
+	fn on_suspicious(who: T::AccountId, tx: Vec<u8>, tag: MineTag, mut reason: Vec<u8>){
+		reason.truncate(MAX_EVIDENCE_DESCRIPTION_LEN);
+		let evidence = Evidence::new(ReportCategory::WashTrade, reason, None);
+		// 以国库账号的名义发起举报, 交给议会裁决
+		if let Err(e) = Self::system_report(tx, tag, evidence, vec![]){
+			debug::warn!("-----auto report failed, account:{:?}, err:{:?}------", who, e);
+		}
+	}
+}
+
+
 
 
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_support::{assert_ok, assert_noop, storage::{migration, unhashed}, traits::{OnFinalize, UnfilteredDispatchable}};
+	use pallet_staking::StakingLedger;
+	use crate::{Runtime, System, Report, Register, Balances, Origin, AccountId, BlockNumber};
+
+	type Members = pallet_collective::Members<Runtime, pallet_collective::Instance1>;
+
+	fn account(i: u8) -> AccountId {
+		AccountId::from([i; 32])
+	}
+
+	fn treasury() -> AccountId {
+		MODULE_ID.into_account()
+	}
+
+	fn reporter() -> AccountId { account(1) }
+	fn illegal_man() -> AccountId { account(2) }
+	fn council() -> Vec<AccountId> { vec![account(10), account(11), account(12)] }
+
+	fn new_test_ext(treasury_balance: Balance) -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+		let mut balances = vec![
+			(treasury(), treasury_balance),
+			(reporter(), 1000 * DOLLARS),
+			(illegal_man(), 1000 * DOLLARS),
+		];
+		balances.extend(council().into_iter().map(|who| (who, 100 * DOLLARS)));
+		pallet_balances::GenesisConfig::<Runtime> { balances }.assimilate_storage(&mut t).unwrap();
+		let mut t: sp_io::TestExternalities = t.into();
+		t.execute_with(|| {
+			System::set_block_number(1);
+			Members::put(council());
+		});
+		t
+	}
+
+	/// 添加一个已经投票结束的举报
+	fn judged_report(tx: &[u8], approve_mans: Vec<AccountId>, reject_mans: Vec<AccountId>) -> ReportIndex {
+		assert!(<Runtime as register::Trait>::Currency1::reserve(&reporter(), Report::report_reserve()).is_ok());
+		let report_id = Report::insert_report(VoteInfo {
+			start_vote_block: 1,
+			tx: tx.to_vec(),
+			reporter: reporter(),
+			illegal_man: illegal_man(),
+			approve_mans,
+			reject_mans,
+			..Default::default()
+		});
+		<RewardQueue>::mutate(|q| q.push(report_id));
+		report_id
+	}
+
+	#[test]
+	fn settlement_keeps_total_issuance() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let c = council();
+			// 作弊成立
+			judged_report(b"tx1", vec![c[0].clone(), c[1].clone()], vec![]);
+			// 虚假举报
+			judged_report(b"tx2", vec![], vec![c[1].clone(), c[2].clone()]);
+
+			let issuance = Balances::total_issuance();
+			let treasury_before = Balances::free_balance(treasury());
+			assert_ok!(Report::reward());
+
+			assert_eq!(Balances::total_issuance(), issuance);
+			assert!(Report::reward_queue().is_empty());
+			assert_eq!(Report::reports(0), None);
+			assert_eq!(Report::reports(1), None);
+
+			// 作弊者被扣500 举报者拿到250的奖励 被没收10的抵押
+			assert_eq!(Balances::total_balance(&illegal_man()), 500 * DOLLARS);
+			assert_eq!(Balances::total_balance(&reporter()), 1240 * DOLLARS);
+			assert_eq!(Balances::reserved_balance(&reporter()), 0);
+			// 每次投票奖励议员10
+			assert_eq!(Balances::free_balance(&c[0]), 110 * DOLLARS);
+			assert_eq!(Balances::free_balance(&c[1]), 120 * DOLLARS);
+			assert_eq!(Balances::free_balance(&c[2]), 110 * DOLLARS);
+			// 国库: +500 +10 -250 -40
+			assert_eq!(Balances::free_balance(treasury()), treasury_before + 220 * DOLLARS);
+		});
+	}
+
+	#[test]
+	fn short_treasury_queues_report() {
+		new_test_ext(10 * DOLLARS).execute_with(|| {
+			let c = council();
+			// 虚假举报: 国库收入10 需要支付20
+			let report_id = judged_report(b"tx1", vec![], vec![c[0].clone(), c[1].clone()]);
+
+			let issuance = Balances::total_issuance();
+			assert_ok!(Report::reward());
+
+			// 国库不够 留在队列里 没有任何金额变化
+			assert_eq!(Balances::total_issuance(), issuance);
+			assert_eq!(Report::reward_queue(), vec![report_id]);
+			assert!(Report::reports(report_id).is_some());
+			assert_eq!(Balances::reserved_balance(&reporter()), 10 * DOLLARS);
+			assert_eq!(Balances::free_balance(&c[0]), 100 * DOLLARS);
+			assert!(!Report::settling());
+
+			// 国库有钱了 下一轮处理
+			assert_ok!(<Balances as Currency<_>>::transfer(&illegal_man(), &treasury(), 100 * DOLLARS, ExistenceRequirement::AllowDeath));
+			<Settling>::put(true);
+			assert_ok!(Report::reward());
+
+			assert_eq!(Balances::total_issuance(), issuance);
+			assert!(Report::reward_queue().is_empty());
+			assert_eq!(Balances::reserved_balance(&reporter()), 0);
+			assert_eq!(Balances::total_balance(&reporter()), 990 * DOLLARS);
+			assert_eq!(Balances::free_balance(&c[0]), 110 * DOLLARS);
+			assert_eq!(Balances::free_balance(&c[1]), 110 * DOLLARS);
+		});
+	}
+
+	/// 议员投票通过的举报(还没有扣款)
+	fn upheld_report(tx: &[u8]) -> ReportIndex {
+		let c = council();
+		// 作弊者是注册过的矿工
+		<AllMiners<Runtime>>::insert(illegal_man(), register::MinerInfo::default());
+		register::MinersCount::mutate(|n| *n += 1);
+		assert!(<Runtime as register::Trait>::Currency1::reserve(&reporter(), Report::report_reserve()).is_ok());
+		let vote_info = VoteInfo {
+			start_vote_block: 1,
+			tx: tx.to_vec(),
+			reporter: reporter(),
+			illegal_man: illegal_man(),
+			approve_mans: vec![c[0].clone(), c[1].clone()],
+			..Default::default()
+		};
+		let report_id = Report::insert_report(vote_info.clone());
+		Report::judge(report_id, vote_info);
+		report_id
+	}
+
+	/// 议员投票通过并且已经扣款的举报
+	fn punished(tx: &[u8]) -> PunishmentInfoOf<Runtime> {
+		upheld_report(tx);
+		assert_ok!(Report::reward());
+		Report::allpunishmentinfo(tx.to_vec())
+	}
+
+	/// 信誉分55(成立一次)的举报奖励
+	fn reward_after_one_pass() -> Balance {
+		Report::report_reward() * 105 / 100
+	}
+
+	fn appeal_status(tx: &[u8]) -> Option<AppealStatus> {
+		Report::allpunishmentinfo(tx.to_vec()).appeal.map(|a| a.status)
+	}
+
+	fn report_events() -> Vec<RawEvent<AccountId, BlockNumber, Balance>> {
+		System::events().into_iter().filter_map(|r| match r.event {
+			crate::Event::report(e) => Some(e),
+			_ => None,
+		}).collect()
+	}
+
+	#[test]
+	fn accepted_appeal_refunds_and_claws_back_reward() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let issuance = Balances::total_issuance();
+			let info = punished(b"tx1");
+			assert_eq!(info.punish_block, Some(1));
+			assert_eq!(info.slashed, 500 * DOLLARS);
+			assert_eq!(info.rewarded, reward_after_one_pass());
+			assert_eq!(Balances::total_balance(&reporter()), 1000 * DOLLARS + reward_after_one_pass());
+			assert!(Register::is_blacklisted(&illegal_man()));
+
+			assert_ok!(Report::appeal(Origin::signed(illegal_man()), b"tx1".to_vec(), b"not me".to_vec()));
+			assert_eq!(Balances::reserved_balance(&illegal_man()), 100 * DOLLARS);
+			assert_eq!(appeal_status(b"tx1"), Some(AppealStatus::Voting));
+			assert_noop!(
+				Report::appeal(Origin::signed(illegal_man()), b"tx1".to_vec(), b"again".to_vec()),
+				Error::<Runtime>::AppealExists
+			);
+			assert_noop!(
+				Report::judge_appeal(Origin::signed(council()[0].clone()), b"tx1".to_vec(), true),
+				DispatchError::BadOrigin
+			);
+
+			assert_ok!(Report::judge_appeal(Origin::root(), b"tx1".to_vec(), true));
+			// 退还扣款与申诉抵押 移出黑名单
+			assert_eq!(Balances::total_balance(&illegal_man()), 1000 * DOLLARS);
+			assert_eq!(Balances::reserved_balance(&illegal_man()), 0);
+			assert!(!Register::is_blacklisted(&illegal_man()));
+			// 没收举报奖励 这次举报改记为不成立
+			assert_eq!(Balances::total_balance(&reporter()), 1000 * DOLLARS);
+			assert_eq!(Report::reporter_records(reporter()), ReporterRecord { passed: 0, rejected: 1, cancelled: 0 });
+			assert_eq!(appeal_status(b"tx1"), Some(AppealStatus::Accepted));
+			assert!(report_events().contains(&RawEvent::AppealAccepted(illegal_man(), b"tx1".to_vec())));
+			assert_eq!(Balances::total_issuance(), issuance);
+
+			assert_noop!(Report::judge_appeal(Origin::root(), b"tx1".to_vec(), false), Error::<Runtime>::NotAppealing);
+		});
+	}
+
+	#[test]
+	fn rejected_appeal_slashes_recorded_bond() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let issuance = Balances::total_issuance();
+			punished(b"tx1");
+			assert_ok!(Report::appeal(Origin::signed(illegal_man()), b"tx1".to_vec(), b"not me".to_vec()));
+			// 申诉以后修改抵押金额 按申诉时的算
+			<AppealBond<Runtime>>::put(50 * DOLLARS);
+
+			assert_ok!(Report::judge_appeal(Origin::root(), b"tx1".to_vec(), false));
+			assert_eq!(Balances::total_balance(&illegal_man()), 400 * DOLLARS);
+			assert_eq!(Balances::reserved_balance(&illegal_man()), 0);
+			assert!(Register::is_blacklisted(&illegal_man()));
+			assert_eq!(Balances::total_balance(&reporter()), 1000 * DOLLARS + reward_after_one_pass());
+			assert_eq!(Report::reporter_records(reporter()).passed, 1);
+			assert_eq!(appeal_status(b"tx1"), Some(AppealStatus::Rejected));
+			assert!(report_events().contains(&RawEvent::AppealRejected(illegal_man(), b"tx1".to_vec())));
+			assert_eq!(Balances::total_issuance(), issuance);
+		});
+	}
+
+	#[test]
+	fn unjudged_appeal_expires_and_returns_bond() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			<AppealJudgePeriod<Runtime>>::put(10);
+			punished(b"tx1");
+			assert_ok!(Report::appeal(Origin::signed(illegal_man()), b"tx1".to_vec(), b"not me".to_vec()));
+			assert_eq!(Report::appeal_expiry(11), vec![b"tx1".to_vec()]);
+
+			<Report as OnFinalize<BlockNumber>>::on_finalize(10);
+			assert_eq!(appeal_status(b"tx1"), Some(AppealStatus::Voting));
+			assert_eq!(Balances::reserved_balance(&illegal_man()), 100 * DOLLARS);
+
+			<Report as OnFinalize<BlockNumber>>::on_finalize(11);
+			assert_eq!(appeal_status(b"tx1"), Some(AppealStatus::Expired));
+			assert_eq!(Balances::reserved_balance(&illegal_man()), 0);
+			assert_eq!(Balances::total_balance(&illegal_man()), 500 * DOLLARS);
+			// 过期不等于申诉成功
+			assert!(Register::is_blacklisted(&illegal_man()));
+			assert!(Report::appeal_expiry(11).is_empty());
+			assert!(report_events().contains(&RawEvent::AppealExpired(illegal_man(), b"tx1".to_vec())));
+
+			assert_noop!(Report::judge_appeal(Origin::root(), b"tx1".to_vec(), true), Error::<Runtime>::NotAppealing);
+			assert_noop!(
+				Report::appeal(Origin::signed(illegal_man()), b"tx1".to_vec(), b"again".to_vec()),
+				Error::<Runtime>::AppealExists
+			);
+		});
+	}
+
+	#[test]
+	fn judged_appeal_is_skipped_at_expiry() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			<AppealJudgePeriod<Runtime>>::put(10);
+			punished(b"tx1");
+			assert_ok!(Report::appeal(Origin::signed(illegal_man()), b"tx1".to_vec(), b"not me".to_vec()));
+			assert_ok!(Report::judge_appeal(Origin::root(), b"tx1".to_vec(), false));
+
+			<Report as OnFinalize<BlockNumber>>::on_finalize(11);
+			assert_eq!(appeal_status(b"tx1"), Some(AppealStatus::Rejected));
+			assert!(!report_events().iter().any(|e| match e {
+				RawEvent::AppealExpired(..) => true,
+				_ => false,
+			}));
+		});
+	}
+
+	#[test]
+	fn appeal_needs_executed_punishment_within_window() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let appeal = |who: AccountId, tx: &[u8], evidence: Vec<u8>| Report::appeal(Origin::signed(who), tx.to_vec(), evidence);
+
+			assert_noop!(appeal(illegal_man(), b"tx1", vec![]), Error::<Runtime>::NotPunished);
+			upheld_report(b"tx1");
+			assert_noop!(appeal(illegal_man(), b"tx1", vec![]), Error::<Runtime>::PunishmentNotExecuted);
+			assert_ok!(Report::reward());
+
+			assert_noop!(appeal(reporter(), b"tx1", vec![]), Error::<Runtime>::NotSelf);
+			assert_noop!(
+				appeal(illegal_man(), b"tx1", vec![0; MAX_APPEAL_EVIDENCE_LEN + 1]),
+				Error::<Runtime>::EvidenceTooLong
+			);
+			System::set_block_number(1 + Report::appeal_window() + 1);
+			assert_noop!(appeal(illegal_man(), b"tx1", vec![]), Error::<Runtime>::AppealExpired);
+			System::set_block_number(1 + Report::appeal_window());
+			assert_ok!(appeal(illegal_man(), b"tx1", vec![]));
+		});
+	}
+
+	fn member_origin(who: &AccountId) -> Origin {
+		pallet_collective::RawOrigin::<AccountId, pallet_collective::Instance1>::Member(who.clone()).into()
+	}
+
+	fn evidence(description: &[u8]) -> Evidence {
+		Evidence::new(ReportCategory::FakeTx, description.to_vec(), None)
+	}
+
+	#[test]
+	fn evidence_description_is_bounded() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let too_long = Evidence::new(ReportCategory::Other, vec![0; MAX_EVIDENCE_DESCRIPTION_LEN + 1], None);
+			assert_noop!(
+				Report::report(Origin::signed(reporter()), b"tx1".to_vec(), MineTag::WALLET, too_long.clone()),
+				Error::<Runtime>::EvidenceTooLong
+			);
+			assert_noop!(
+				Report::counter_evidence(member_origin(&council()[0]), 0, too_long),
+				Error::<Runtime>::EvidenceTooLong
+			);
+		});
+	}
+
+	#[test]
+	fn council_can_counter_an_open_report() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let c = council();
+			let report_id = Report::insert_report(VoteInfo {
+				start_vote_block: 1,
+				tx: b"tx1".to_vec(),
+				reporter: reporter(),
+				illegal_man: illegal_man(),
+				evidence: evidence(b"fake"),
+				..Default::default()
+			});
+
+			assert_noop!(
+				Report::counter_evidence(Origin::signed(c[0].clone()), report_id, evidence(b"no")),
+				DispatchError::BadOrigin
+			);
+			assert_noop!(
+				Report::counter_evidence(member_origin(&c[0]), report_id + 1, evidence(b"no")),
+				Error::<Runtime>::NotInVoteList
+			);
+
+			assert_ok!(Report::counter_evidence(member_origin(&c[0]), report_id, evidence(b"no")));
+			// 再次提交会覆盖自己之前的证据
+			assert_ok!(Report::counter_evidence(member_origin(&c[0]), report_id, evidence(b"real tx")));
+			assert_ok!(Report::counter_evidence(member_origin(&c[1]), report_id, evidence(b"no")));
+			let expected = vec![(c[0].clone(), evidence(b"real tx")), (c[1].clone(), evidence(b"no"))];
+			assert_eq!(Report::reports(report_id).map(|v| v.counter_evidences), Some(expected));
+			assert!(report_events().contains(&RawEvent::CounterEvidenceEvent(c[1].clone(), b"tx1".to_vec())));
+
+			// 投票结束后不能再提交
+			<Reports<Runtime>>::mutate(report_id, |v| if let Some(v) = v {
+				v.approve_mans = vec![c[0].clone(), c[1].clone()];
+			});
+			assert_noop!(
+				Report::counter_evidence(member_origin(&c[2]), report_id, evidence(b"no")),
+				Error::<Runtime>::PassedProposal
+			);
+		});
+	}
+
+	#[test]
+	fn expired_reports_are_processed_in_batches() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			<MaxExpirePerBlock>::put(2);
+			<ProposalExpire<Runtime>>::put(10);
+			for i in 0..5u8 {
+				assert!(<Runtime as register::Trait>::Currency1::reserve(&reporter(), Report::report_reserve()).is_ok());
+				let report_id = Report::insert_report(VoteInfo {
+					start_vote_block: 1,
+					tx: vec![i],
+					reporter: reporter(),
+					illegal_man: illegal_man(),
+					..Default::default()
+				});
+				Report::schedule_expiry(report_id, 12);
+			}
+			let open = || <Reports<Runtime>>::iter().count();
+
+			Report::remove_expire_voting(12);
+			assert_eq!(open(), 3);
+			assert_eq!(Report::expiry_queue(13).len(), 3);
+			assert_eq!(Balances::reserved_balance(&reporter()), 30 * DOLLARS);
+
+			// 过期时间被调大了 处理到的重新排队 剩下的继续留到下一个区块
+			<ProposalExpire<Runtime>>::put(20);
+			Report::remove_expire_voting(13);
+			assert_eq!(open(), 3);
+			assert_eq!(Report::expiry_queue(14).len(), 1);
+			assert_eq!(Report::expiry_queue(22).len(), 2);
+
+			<ProposalExpire<Runtime>>::put(10);
+			Report::remove_expire_voting(14);
+			assert_eq!(open(), 2);
+			Report::remove_expire_voting(22);
+			assert_eq!(open(), 0);
+			assert_eq!(Balances::reserved_balance(&reporter()), 0);
+			assert!(Report::reports_of_account(reporter()).is_empty());
+		});
+	}
+
+
+	#[test]
+	fn vote_lock_holds_until_unlock_block() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let who = reporter();
+			let transfer = |amount: Balance| <Balances as Currency<_>>::transfer(
+				&who, &treasury(), amount, ExistenceRequirement::AllowDeath);
+
+			// 简单多数的规则不接受抵押投票
+			assert_noop!(
+				Report::stake_vote(Origin::signed(who.clone()), 0, true, Conviction::Locked2x, 10 * DOLLARS),
+				Error::<Runtime>::StakeVoteDisabled
+			);
+
+			Report::extend_vote_lock(&who, 100 * DOLLARS, 20);
+			// 金额和解锁时间都取较大的
+			Report::extend_vote_lock(&who, 50 * DOLLARS, 30);
+			assert_eq!(Report::vote_lock_of(&who), (100 * DOLLARS, 30));
+			assert!(transfer(950 * DOLLARS).is_err());
+
+			System::set_block_number(29);
+			assert_noop!(Report::unlock_vote(Origin::signed(who.clone())), Error::<Runtime>::VoteLocked);
+
+			System::set_block_number(30);
+			assert_ok!(Report::unlock_vote(Origin::signed(who.clone())));
+			assert!(!<VoteLockOf<Runtime>>::contains_key(&who));
+			assert!(report_events().contains(&RawEvent::VoteUnlocked(who.clone())));
+			assert_ok!(transfer(950 * DOLLARS));
+		});
+	}
+
+	/// 作弊者挖矿的交易记录
+	fn mined_tx(tx: &[u8]) {
+		<AllMiners<Runtime>>::insert(illegal_man(), register::MinerInfo::default());
+		register::MinersCount::mutate(|n| *n += 1);
+		<OwnerMineRecord<Runtime>>::insert(tx.to_vec(), MineTag::WALLET, crate::mine_linked::PersonMineRecord {
+			mine_tag: MineTag::WALLET,
+			mine_count: 1,
+			timestamp: 0,
+			blocknum: 1,
+			miner_address: illegal_man(),
+			from_address: vec![],
+			to_address: vec![],
+			symbol: b"btc".to_vec(),
+			blockchain: b"btc".to_vec(),
+			tx: tx.to_vec(),
+			usdt_amount: 100,
+			sym_amount: b"1".to_vec(),
+			decimal: 8,
+			pcount_workforce: 0,
+			pamount_workforce: 0,
+			reward: 0,
+			grandpa_reward: 0,
+			father_reward: 0,
+		});
+	}
+
+	#[test]
+	fn fast_tracked_system_report_rewards_validators() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let validators = vec![account(20), account(21)];
+			mined_tx(b"tx1");
+			assert_ok!(Report::system_report(b"tx1".to_vec(), MineTag::WALLET, evidence(b"verify failed"), validators.clone()));
+
+			// 系统发起的举报不需要抵押
+			let report_id = Report::report_id_of(b"tx1".to_vec()).unwrap();
+			assert_eq!(Report::reports(report_id).map(|v| v.reporter), Some(treasury()));
+			assert_eq!(Report::system_report_validators(report_id), validators);
+			assert!(report_events().contains(&RawEvent::SystemReportEvent(report_id, 2)));
+			assert_noop!(
+				Report::system_report(b"tx1".to_vec(), MineTag::WALLET, evidence(b"again"), vec![]),
+				Error::<Runtime>::BeingReported
+			);
+
+			// 只有议会可以直接裁决
+			assert_noop!(
+				Report::fast_track(Origin::signed(council()[0].clone()), report_id, true),
+				DispatchError::BadOrigin
+			);
+			assert_ok!(Report::fast_track(Origin::root(), report_id, true));
+			assert_eq!(Report::fast_tracked(report_id), Some(true));
+			assert_eq!(Report::vote_result(Report::reports(report_id).unwrap()), (VoteResult::PASS, IsPunished::YES));
+			assert!(report_events().contains(&RawEvent::FastTrackEvent(report_id, true)));
+			assert_noop!(Report::fast_track(Origin::root(), report_id, false), Error::<Runtime>::PassedProposal);
+
+			// 作弊成立 验证人平分举报奖励
+			assert_ok!(Report::reward());
+			assert_eq!(Balances::total_balance(&illegal_man()), 500 * DOLLARS);
+			assert_eq!(Balances::free_balance(&validators[0]), Report::report_reward() / 2);
+			assert_eq!(Balances::free_balance(&validators[1]), Report::report_reward() / 2);
+			assert!(Report::system_report_validators(report_id).is_empty());
+			assert_eq!(Report::fast_tracked(report_id), None);
+		});
+	}
+
+	#[test]
+	fn fast_track_only_applies_to_system_reports() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let report_id = Report::insert_report(VoteInfo {
+				start_vote_block: 1,
+				tx: b"tx1".to_vec(),
+				reporter: reporter(),
+				illegal_man: illegal_man(),
+				..Default::default()
+			});
+			assert_noop!(Report::fast_track(Origin::root(), report_id, true), Error::<Runtime>::NotSystemReport);
+			assert_noop!(Report::fast_track(Origin::root(), report_id + 1, true), Error::<Runtime>::NotInVoteList);
+		});
+	}
+
+	#[test]
+	fn reputation_scales_reserve_reward_and_limit() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let who = reporter();
+			let set_record = |passed, rejected, cancelled| <ReporterRecords<Runtime>>::insert(&who, ReporterRecord { passed, rejected, cancelled });
+			let scaled = || (Report::reputation_reserve(&who), Report::reputation_reward(&who), Report::daily_report_limit(&who));
+
+			// 50分
+			assert_eq!(scaled(), (10 * DOLLARS, 250 * DOLLARS, 5));
+			// 100分(最高)
+			set_record(10, 0, 0);
+			assert_eq!(scaled(), (5 * DOLLARS, 375 * DOLLARS, 10));
+			set_record(20, 0, 0);
+			assert_eq!(Report::reporter_records(&who).score(), REPUTATION_MAX);
+			assert_eq!(scaled(), (5 * DOLLARS, 375 * DOLLARS, 10));
+			// 0分 每天至少可以举报一次
+			set_record(0, 5, 0);
+			assert_eq!(scaled(), (15 * DOLLARS, 125 * DOLLARS, 1));
+			// 47分
+			set_record(0, 0, 1);
+			assert_eq!(scaled(), (103 * DOLLARS / 10, 2425 * DOLLARS / 10, 4));
+
+			let reputation = Report::reporter_reputation(who.clone());
+			assert_eq!(reputation.score, 47);
+			assert_eq!(reputation.daily_limit, 4);
+		});
+	}
+
+
+	#[test]
+	fn blacklist_expires_after_duration() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			<register::BlacklistDuration<Runtime>>::put(10);
+			upheld_report(b"tx1");
+			assert_eq!(Register::blacklist(illegal_man()).and_then(|info| info.expire_at), Some(11));
+
+			System::set_block_number(10);
+			assert!(Register::is_blacklisted(&illegal_man()));
+			// 到期了还没有清理的已经不算在黑名单里
+			System::set_block_number(11);
+			assert!(!Register::is_blacklisted(&illegal_man()));
+
+			<Register as OnFinalize<BlockNumber>>::on_finalize(11);
+			assert!(Register::blacklist(illegal_man()).is_none());
+			assert!(System::events().iter().any(|r| r.event ==
+				crate::Event::register(register::RawEvent::BlacklistExpired(illegal_man()))));
+		});
+	}
+
+	#[test]
+	fn unblacklist_needs_governance_and_skips_stale_expiry() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			<register::BlacklistDuration<Runtime>>::put(10);
+			Register::add_to_blacklist(illegal_man(), b"tx1".to_vec(), vec![], None);
+
+			assert_noop!(Register::unblacklist(Origin::signed(reporter()), illegal_man()), DispatchError::BadOrigin);
+			assert_noop!(Register::unblacklist(Origin::root(), reporter()), register::Error::<Runtime>::NotInBlackList);
+			assert_ok!(Register::unblacklist(Origin::root(), illegal_man()));
+			assert!(!Register::is_blacklisted(&illegal_man()));
+
+			// 重新永久进入黑名单 以前的到期不再生效
+			<register::BlacklistDuration<Runtime>>::kill();
+			Register::add_to_blacklist(illegal_man(), b"tx2".to_vec(), vec![], None);
+			<Register as OnFinalize<BlockNumber>>::on_finalize(11);
+			assert!(Register::is_blacklisted(&illegal_man()));
+			assert_eq!(Register::blacklist(illegal_man()).map(|info| info.tx), Some(b"tx2".to_vec()));
+		});
+	}
+
+	#[test]
+	fn report_api_follows_a_report_until_punished() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let c = council();
+			mined_tx(b"tx1");
+			assert_ok!(Report::report(Origin::signed(reporter()), b"tx1".to_vec(), MineTag::WALLET, evidence(b"fake")));
+			let report_id = Report::report_id_of(b"tx1".to_vec()).unwrap();
+			let expire_block = 1 + Report::proposal_expire() + 1;
+
+			let open = Report::open_reports();
+			assert_eq!(open.len(), 1);
+			assert_eq!(open[0].report_id, report_id);
+			assert_eq!(open[0].expire_block, expire_block);
+			assert_eq!(open[0].remaining_blocks, expire_block - 1);
+			assert_eq!(open[0].result, None);
+			assert_eq!(Report::reports_of(reporter()), open);
+			assert_eq!(Report::reports_of(illegal_man()), open);
+			assert!(Report::reports_of(c[0].clone()).is_empty());
+
+			assert_ok!(Report::vote(member_origin(&c[0]), report_id, true));
+			let tally = Report::report_tally(report_id).unwrap();
+			assert_eq!(tally.approve_mans, vec![c[0].clone()]);
+			assert_eq!((tally.council_size, tally.council_threshold), (3, 2));
+			assert_eq!(tally.result, None);
+
+			// 投票结束 等待统一处理金额
+			assert_ok!(Report::vote(member_origin(&c[1]), report_id, true));
+			assert_eq!(Report::report_tally(report_id).and_then(|t| t.result), Some(true));
+			assert_eq!(Report::open_reports()[0].remaining_blocks, 0);
+
+			// 扣款之后举报处理完 只留下惩罚记录
+			assert_ok!(Report::reward());
+			assert!(Report::open_reports().is_empty());
+			assert!(Report::reports_of(reporter()).is_empty());
+			assert_eq!(Report::report_tally(report_id), None);
+			let history = Report::punishment_history(illegal_man());
+			assert_eq!(history.len(), 1);
+			assert_eq!(history[0].tx, b"tx1".to_vec());
+			assert_eq!(history[0].reporter, reporter());
+			assert_eq!(history[0].punish_block, Some(1));
+			assert_eq!(history[0].slashed, 500 * DOLLARS);
+			assert_eq!(history[0].appeal, None);
+		});
+	}
+
+
+	#[test]
+	fn misbehaviour_is_punished_at_threshold() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let c = council();
+			let report = |who: &AccountId, evidence: &[u8]| Report::report_misbehaviour(
+				OffenceKind::FakeAddress, who.clone(), illegal_man(), evidence.to_vec());
+
+			assert_eq!(report(&c[0], b"addr"), Ok(false));
+			// 同一个举报者的同一条证据只算一次
+			assert_noop!(report(&c[0], b"addr"), Error::<Runtime>::DuplicateEvidence);
+			assert_noop!(report(&illegal_man(), b"addr"), Error::<Runtime>::IllegalMan);
+			assert_noop!(report(&c[1], &[0; MAX_EVIDENCE_DESCRIPTION_LEN + 1]), Error::<Runtime>::EvidenceTooLong);
+			assert_eq!(report(&c[0], b"addr2"), Ok(false));
+			assert_eq!(Report::pending_offences(OffenceKind::FakeAddress, illegal_man()).len(), 2);
+			assert_eq!(Balances::total_balance(&illegal_man()), 1000 * DOLLARS);
+
+			// 默认3条证据成立 扣100
+			assert_eq!(report(&c[1], b"addr"), Ok(true));
+			assert_eq!(Balances::total_balance(&illegal_man()), 900 * DOLLARS);
+			assert!(Report::pending_offences(OffenceKind::FakeAddress, illegal_man()).is_empty());
+			let events = report_events();
+			assert!(events.contains(&RawEvent::OffenceReported(OffenceKind::FakeAddress, illegal_man(), 3)));
+			assert!(events.contains(&RawEvent::OffencePunished(OffenceKind::FakeAddress, illegal_man())));
+		});
+	}
+
+	#[test]
+	fn offence_config_is_validated() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let set_config = |kind, config| Call::<Runtime>::set_offence_config(kind, config)
+				.dispatch_bypass_filter(system::RawOrigin::Root.into()).map(|_| ()).map_err(|e| e.error);
+			let validator_slash = OffencePenalty::ValidatorSlash(Perbill::from_percent(1));
+
+			assert_noop!(
+				set_config(OffenceKind::FakeAddress, OffenceConfig { threshold: 0, penalty: OffencePenalty::Slash(DOLLARS) }),
+				Error::<Runtime>::InvalidOffenceConfig
+			);
+			assert_noop!(
+				set_config(OffenceKind::FakeAddress, OffenceConfig { threshold: 1, penalty: validator_slash.clone() }),
+				Error::<Runtime>::InvalidOffenceConfig
+			);
+			assert_ok!(set_config(OffenceKind::VerifierOffline, OffenceConfig { threshold: 1, penalty: validator_slash }));
+			assert!(OffenceKind::VerifierOffline.system_only());
+			assert!(!OffenceKind::FakeAddress.system_only());
+		});
+	}
+
+	#[test]
+	fn validator_slash_is_reported_only_for_validators() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			<OffenceConfigs<Runtime>>::insert(OffenceKind::VerifierOffline, OffenceConfig {
+				threshold: 1,
+				penalty: OffencePenalty::ValidatorSlash(Perbill::from_percent(1)),
+			});
+			let report = |evidence: &[u8]| Report::report_misbehaviour(
+				OffenceKind::VerifierOffline, treasury(), illegal_man(), evidence.to_vec());
+			let offences = || System::events().into_iter().filter(|r| match r.event {
+				crate::Event::pallet_offences(pallet_offences::Event::Offence(kind, ..)) => kind == VerifierOffence::<Runtime>::ID,
+				_ => false,
+			}).count();
+
+			// 不是验证人 不处罚
+			assert_eq!(report(b"1"), Ok(true));
+			assert_eq!(offences(), 0);
+			assert_eq!(Balances::total_balance(&illegal_man()), 1000 * DOLLARS);
+
+			// 当前的验证人(自己是自己的controller)
+			<pallet_staking::Ledger<Runtime>>::insert(&illegal_man(), StakingLedger {
+				stash: illegal_man(),
+				total: 100 * DOLLARS,
+				active: 100 * DOLLARS,
+				unlocking: vec![],
+				claimed_rewards: vec![],
+			});
+			migration::put_storage_value(b"Session", b"Validators", &[], vec![illegal_man()]);
+			migration::put_storage_value(b"Staking", b"ActiveEra", &[], (0u32, None::<u64>));
+			assert_eq!(report(b"2"), Ok(true));
+			assert_eq!(offences(), 1);
+		});
+	}
+
+	fn old_vote(tx: &[u8]) -> VoteInfoV0<BlockNumber, AccountId, Balance> {
+		VoteInfoV0 {
+			start_vote_block: 1,
+			tx: tx.to_vec(),
+			reporter: reporter(),
+			report_reason: vec![b'x'; MAX_EVIDENCE_DESCRIPTION_LEN + 1],
+			illegal_man: illegal_man(),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn migrate_from_baseline() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let module = b"ReportModule";
+			// 版本0的惩罚记录只保存了投票信息
+			unhashed::put(&<AllPunishmentInfo<Runtime>>::hashed_key_for(b"punished".to_vec()), &old_vote(b"punished"));
+			migration::put_storage_value(module, b"Votes", b"voting", old_vote(b"voting"));
+			migration::put_storage_value(module, b"Votes", b"judged", old_vote(b"judged"));
+			migration::put_storage_value(module, b"Voting", &[], vec![(b"voting".to_vec(), old_vote(b"voting"))]);
+			migration::put_storage_value(module, b"RewardList", &[], vec![old_vote(b"judged")]);
+			migration::put_storage_value(module, b"ManTxHashs", &reporter().encode(), vec![b"voting".to_vec(), b"judged".to_vec()]);
+			System::set_block_number(5);
+
+			assert!(Report::migrate() > 0);
+			assert_eq!(Report::storage_version(), 1);
+
+			let info = Report::allpunishmentinfo(b"punished".to_vec());
+			assert_eq!(info.punish_block, Some(1));
+			assert_eq!(info.slashed, Report::illegal_punishment());
+			assert_eq!(info.rewarded, 0);
+			assert!(info.appeal.is_none());
+			assert_eq!(info.vote_info.evidence.description.len(), MAX_EVIDENCE_DESCRIPTION_LEN);
+			assert_eq!(Report::punished_txs_of(illegal_man()), vec![b"punished".to_vec()]);
+
+			let voting = Report::report_id_of(b"voting".to_vec()).unwrap();
+			let judged = Report::report_id_of(b"judged".to_vec()).unwrap();
+			assert_eq!(Report::expiry_queue(1 + Report::proposal_expire() + 1), vec![voting]);
+			assert_eq!(Report::reward_queue(), vec![judged]);
+			let mut of_reporter = Report::reports_of_account(reporter());
+			of_reporter.sort();
+			let mut ids = vec![voting, judged];
+			ids.sort();
+			assert_eq!(of_reporter, ids);
+			assert_eq!(Report::reports_of_account(illegal_man()).len(), 2);
+
+			assert!(migration::get_storage_value::<Vec<u8>>(module, b"RewardList", &[]).is_none());
+			assert!(migration::get_storage_value::<Vec<u8>>(module, b"Voting", &[]).is_none());
+			assert_eq!(StorageIterator::<Vec<u8>>::new(module, b"Votes").count(), 0);
+			assert_eq!(StorageIterator::<Vec<u8>>::new(module, b"ManTxHashs").count(), 0);
+
+			// 已经迁移过的不会再迁移
+			assert_eq!(Report::migrate(), <Runtime as system::Trait>::DbWeight::get().reads(1));
+		});
+	}
+
+	fn tally(council_ayes: u32, council_nays: u32, council_size: u32, stake_ayes: Balance, stake_nays: Balance) -> JudgementTally<Balance> {
+		JudgementTally{ council_ayes, council_nays, council_size, stake_ayes, stake_nays }
+	}
+
+	frame_support::parameter_types! {
+		pub const ThreeFifths: Permill = Permill::from_percent(60);
+		pub const MinTurnout: Balance = 100;
+	}
+
+	#[test]
+	fn simple_majority_ends_on_a_two_vote_lead_or_half_the_council() {
+		type P = SimpleMajority;
+		assert!(!<P as JudgementPolicy<Balance>>::accepts_stake_votes());
+		assert_eq!(<P as JudgementPolicy<Balance>>::council_threshold(3), 2);
+		// 没有议员时没有结果
+		assert_eq!(P::judge(&tally(0, 0, 0, 0, 0)), None);
+		assert_eq!(P::judge(&tally(1, 0, 3, 0, 0)), None);
+		assert_eq!(P::judge(&tally(1, 1, 3, 0, 0)), None);
+		assert_eq!(P::judge(&tally(2, 0, 3, 0, 0)), Some(true));
+		assert_eq!(P::judge(&tally(1, 2, 3, 0, 0)), Some(false));
+		// 胜出两票就结束
+		assert_eq!(P::judge(&tally(0, 2, 9, 0, 0)), Some(false));
+		// 抵押投票不算数
+		assert_eq!(P::judge(&tally(1, 0, 3, 1_000, 0)), None);
+	}
+
+	#[test]
+	fn super_majority_needs_the_share_of_the_council() {
+		type P = SuperMajority<ThreeFifths>;
+		assert!(!<P as JudgementPolicy<Balance>>::accepts_stake_votes());
+		assert_eq!(<P as JudgementPolicy<Balance>>::council_threshold(5), 3);
+		assert_eq!(<P as JudgementPolicy<Balance>>::council_threshold(1), 1);
+		assert_eq!(P::judge(&tally(0, 0, 0, 0, 0)), None);
+		assert_eq!(P::judge(&tally(2, 0, 5, 0, 0)), None);
+		assert_eq!(P::judge(&tally(2, 2, 5, 0, 0)), None);
+		assert_eq!(P::judge(&tally(3, 0, 5, 0, 0)), Some(true));
+		// 剩下的议员全投赞成也不够
+		assert_eq!(P::judge(&tally(0, 3, 5, 0, 0)), Some(false));
+	}
+
+	#[test]
+	fn stake_weighted_needs_the_min_turnout() {
+		type P = StakeWeighted<MinTurnout>;
+		assert!(<P as JudgementPolicy<Balance>>::accepts_stake_votes());
+		assert_eq!(<P as JudgementPolicy<Balance>>::min_turnout(), 100);
+		assert_eq!(P::judge(&tally(0, 0, 3, 0, 0)), None);
+		// 议员票不算数
+		assert_eq!(P::judge(&tally(3, 0, 3, 60, 30)), None);
+		assert_eq!(P::judge(&tally(0, 0, 3, 60, 40)), Some(true));
+		assert_eq!(P::judge(&tally(0, 0, 3, 50, 50)), Some(true));
+		assert_eq!(P::judge(&tally(0, 0, 3, 40, 70)), Some(false));
+	}
+
+	#[test]
+	fn judge_settles_voters_and_slashes_the_ones_often_against_the_result() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let c = council();
+			<VoterSlashMinVotes>::put(3);
+			<VoterSlashThreshold>::put(Permill::from_percent(60));
+			// 之前两次裁决里有一次与结果相反
+			<VoterRecords<Runtime>>::insert(&c[1], (2, 1));
+			assert!(<Runtime as register::Trait>::Currency1::reserve(&reporter(), Report::report_reserve()).is_ok());
+			let report_id = Report::insert_report(VoteInfo {
+				start_vote_block: 1,
+				tx: b"tx1".to_vec(),
+				reporter: reporter(),
+				illegal_man: illegal_man(),
+				..Default::default()
+			});
+
+			assert_ok!(Report::vote(member_origin(&c[0]), report_id, true));
+			assert_ok!(Report::vote(member_origin(&c[1]), report_id, false));
+			// 一比一 还没有结果
+			assert!(Report::reward_queue().is_empty());
+			assert_eq!(Report::voter_records(&c[0]), (0, 0));
+
+			// 赞成过半 作弊成立
+			assert_ok!(Report::vote(member_origin(&c[2]), report_id, true));
+			assert_eq!(Report::reward_queue(), vec![report_id]);
+			assert!(Register::is_blacklisted(&illegal_man()));
+			assert_eq!(Report::allpunishmentinfo(b"tx1".to_vec()).vote_info.approve_mans, vec![c[0].clone(), c[2].clone()]);
+			assert_noop!(Report::vote(member_origin(&c[1]), report_id, true), Error::<Runtime>::PassedProposal);
+
+			assert_eq!(Report::voter_records(&c[0]), (1, 0));
+			assert_eq!(Report::voter_records(&c[2]), (1, 0));
+			// 三次里两次与结果相反 被惩罚后重新计数
+			assert_eq!(Report::voter_records(&c[1]), (0, 0));
+			assert_eq!(Balances::free_balance(&c[1]), 100 * DOLLARS - Report::voter_slash());
+			assert_eq!(Balances::free_balance(&c[0]), 100 * DOLLARS);
+			assert!(report_events().contains(&RawEvent::VoterSlashed(c[1].clone(), 2, 3)));
+		});
+	}
+
+	#[test]
+	fn votes_after_the_voting_period_expire_the_report() {
+		new_test_ext(10_000 * DOLLARS).execute_with(|| {
+			let c = council();
+			<ProposalExpire<Runtime>>::put(10);
+			assert!(<Runtime as register::Trait>::Currency1::reserve(&reporter(), Report::report_reserve()).is_ok());
+			let report_id = Report::insert_report(VoteInfo {
+				start_vote_block: 1,
+				tx: b"tx1".to_vec(),
+				reporter: reporter(),
+				illegal_man: illegal_man(),
+				..Default::default()
+			});
+
+			System::set_block_number(11);
+			assert!(!Report::is_vote_expired(&Report::reports(report_id).unwrap()));
+			System::set_block_number(12);
+			assert!(Report::is_vote_expired(&Report::reports(report_id).unwrap()));
+
+			// 过期的投票不算数 举报被删除 抵押归还
+			assert_ok!(Report::vote(member_origin(&c[0]), report_id, true));
+			assert_eq!(Report::reports(report_id), None);
+			assert_eq!(Balances::reserved_balance(&reporter()), 0);
+			assert_eq!(Report::voter_records(&c[0]), (0, 0));
+		});
+	}
+}
@@ -14,7 +14,7 @@ use sp_core::{crypto::KeyTypeId,offchain::Timestamp};
 
 use frame_support::{print,Parameter,decl_module, decl_storage, decl_event, dispatch, debug, traits::Get,IterableStorageMap,
                     StorageDoubleMap, IterableStorageDoubleMap, ensure,weights::Weight};
-use frame_system::{self as system,RawOrigin,Origin, ensure_signed,ensure_none, offchain,
+use frame_system::{self as system,Origin, ensure_signed,ensure_none, offchain,
                    offchain::{SubmitTransaction,SendTransactionTypes}};
 //use simple_json::{ self, json::JsonValue };
 
@@ -35,10 +35,12 @@ use sp_runtime::{
 use app_crypto::{sr25519};
 //use crate::price_fetch::crypto::AuthorityId;
 
-use crate::mine::{self,TxVerifyMap,LenOfTxVerify,OwnerMineRecord,OwnerMineRecordItem, Trait as MineTrait};
+use crate::mine::{self,TxVerifyMap,LenOfTxVerify,TxVerifyPending,TxVerifyExpiry,OwnerMineRecord,OwnerMineRecordItem, Trait as MineTrait};
 use crate::mine_linked::MineTag;
 use crate::report::{self,Trait as ReportTrait};
+use crate::register::{self, TimeoutPolicy};
 use crate::offchain_common::*;
+use crate::chain_verifier;
 
 
 type BlockNumberOf<T> = <T as system::Trait>::BlockNumber;  // u32
@@ -105,7 +107,7 @@ pub mod tx_crypto {
 }
 
 
-// 请求的查询接口
+// 请求的查询接口(链下存储里没有设置验证服务地址时用这里的默认地址)
 pub const TX_FETCHED_CRYPTS: [(&[u8], &[u8]); 1] = [
     (b"localhost",b"http://localhost:8421/v1/tx/verify"),
 ];
@@ -140,6 +142,8 @@ decl_event!(
     FetchedSuc(AccountId,BlockNumber, Vec<u8>, u64), // 当前tx 状态记录事件
 
     FailedEvent(AccountId,BlockNumber,Vec<u8>), // 记录返回错误的情况
+
+    TxVerifyTimeout(Vec<u8>, MineTag, BlockNumber, TimeoutPolicy), // 验证超时(tx, MineTag, 加入队列的区块, 处理方式)
   }
 );
 
@@ -151,6 +155,7 @@ decl_storage! {
 
        // 记录失败的,定期全部清除. Vec<FetchFailedOf<T>> 最多保持50个的长度.原本是 linked_map
        pub TxFetchFailed get(fn fetch_failed): map hasher(blake2_128_concat) T::AccountId => Vec<FetchFailedOf<T>>;
+
   }
 }
 
@@ -171,7 +176,7 @@ decl_module! {
              <TxFetchFailed<T>>::remove(&key);
             }
         }
-        0
+        Self::handle_timeout(block)
     }
 
     #[weight = 0]
@@ -191,54 +196,18 @@ decl_module! {
       let block_num = <system::Module<T>>::block_number();
       let duration = block_num / T::Duration::get();
       debug::info!("-------record_tx--------");
-      ensure!(<TxVerifyMap>::contains_key((tx.clone(),mine_tag.clone())), "不需要再操作了,tx 已经从TxVerifyMap队列移除");
-//      let tx_vec = tx.to_vec();
-//
-//        1000: 表示初始值
-//        1001: 表示验证，但是请求失败了
-//        120x: 终止，pass
-//        1x1x: 终止，举报,有1个节点验证失败
-//        1009: 终止，网络全部失败   todo: 怎么处理？
-//        1109: 终止，pass
-      if status == 0{  // 20000
-        // 成功
-       <FetchRecord<T>>::mutate(
-        duration,account_id.clone(),
-        |val|{
-            val.0 = val.0.checked_add(1).unwrap();
-//            val
-        });
-       <TxVerifyMap>::mutate(&(tx.clone(),mine_tag.clone()),|num|*num = num.checked_add(100).unwrap());//  通过次数加1
-      }else if status == 255{ // query  ./token-query 没有收到返回的消息
-         <FetchRecord<T>>::mutate(
-            duration,account_id.clone(),
-            |val|{
-                val.2 = val.2.checked_add(1).unwrap();
-//                val
-            });
-            <TxVerifyMap>::mutate(&(tx.clone(),mine_tag.clone()),|num|*num = num.checked_add(1).unwrap());// 仅仅对总次数加1
-      }else{
-        <FetchRecord<T>>::mutate( // 可能是200x 400x
-            duration,account_id.clone(),
-            |val|{
-                val.1 = val.1.checked_add(1).unwrap();
-//                val
-            });
-            <TxVerifyMap>::mutate(&(tx.clone(),mine_tag.clone()),|num|*num = num.checked_add(10).unwrap()); // 失败次数加1,总次数加1
-      }
-      if let ReportStatus::Report = Self::tx_verify_map_handle(&tx,mine_tag.clone())?{
-        // 举报
-        debug::warn!("调用举报举报");
-        let origin = T::Origin::from(RawOrigin::Signed(account_id));
-        <report::Module<T>>::report(origin,tx,mine_tag.clone(),"".as_bytes().to_vec());  //
-
-        // Signed tx
-//         let local_accts = T::SubmitSignedTransaction::find_all_local_keys();
-//         let (local_acct, local_key) = &local_accts[0];
-//         debug::info!("acct: {:?}", local_acct);
-//         let call = Call::record_fail_fetch(block_num,account.clone(),tx_vec[0].clone(), e.as_bytes().to_vec());
-//         let call = report::Call::<T>::report(sym);
-//        <T::SubmitSignedTransaction as SubmitSignedTransaction<T, <T as Trait>::Call>>::SignAndSubmit::sign_and_submit(call, local_key.clone());
+      let key = (tx.clone(),mine_tag.clone());
+      ensure!(<TxVerifyMap<T>>::contains_key(&key), "不需要再操作了,tx 已经从TxVerifyMap队列移除");
+      let vote = register::VerifyVote::from_status(status);
+      // 同一个验证人只算一次(validate_unsigned 已经拒绝了重复的投票)
+      ensure!(<TxVerifyMap<T>>::mutate(&key, |tally| tally.vote(&account_id, vote)), "已经投过票了");
+      <FetchRecord<T>>::mutate(duration, account_id.clone(), |val| match vote {
+          register::VerifyVote::Pass => val.0 = val.0.saturating_add(1),
+          register::VerifyVote::Fail => val.1 = val.1.saturating_add(1),
+          register::VerifyVote::Error => val.2 = val.2.saturating_add(1),
+      });
+      if let (ReportStatus::Report, tally) = Self::tx_verify_map_handle(&tx,mine_tag.clone()){
+        Self::report_tx(tx, mine_tag.clone(), b"tx verify failed".to_vec(), tally.fails);
       }
 
       debug::info!("----上链成功: record_tx-----: {:?}", duration);
@@ -259,7 +228,11 @@ decl_module! {
             // 记录获取fetch失败的信息
             ensure_none(_origin)?;
             debug::info!("--------record_fail_fetch--------");
-            ensure!(<TxVerifyMap>::contains_key((tx.clone(),mine_tag.clone())), "不需要再操作了,tx 已经从TxVerifyMap队列移除");
+            let key = (tx.clone(),mine_tag.clone());
+            ensure!(<TxVerifyMap<T>>::contains_key(&key), "不需要再操作了,tx 已经从TxVerifyMap队列移除");
+            // 没有结果 不算票, 同一个验证人只记一次; 本地服务没开的之后每次分到都再记一次没有应答
+            let voted = <TxVerifyMap<T>>::mutate(&key, |tally| tally.vote(&account, register::VerifyVote::Error));
+            ensure!(voted || Self::is_repeated_downtime(&key, &account, &err), "已经记录过了");
             let now = <timestamp::Module<T>>::get();
             let failed_struct = FetchFailedOf::<T> {
                     timestamp: now,
@@ -267,15 +240,6 @@ decl_module! {
                     err: err.clone()
                 };
 
-            <TxVerifyMap>::mutate(&(tx.clone(),mine_tag.clone()),|num|*num = num.checked_add(1).unwrap());// 仅仅对总次数加1
-            let status:u64 = <TxVerifyMap>::get((tx.clone(),mine_tag.clone()));
-            debug::info!("链上 修改后的tx 状态码={:?}",status);
-            if let ReportStatus::Report = Self::tx_verify_map_handle(&tx,mine_tag.clone())?{
-                // 举报
-                 debug::warn!("调用举报举报");
-                let origin = T::Origin::from(RawOrigin::Signed(account.clone()));
-                <report::Module<T>>::report(origin,tx.clone(),mine_tag.clone(),"".as_bytes().to_vec());  //
-            }
             <TxFetchFailed<T>>::mutate(&account, |fetch_failed| {
             if fetch_failed.len()>50{  // 最多保留50个的长度
                 fetch_failed.pop();
@@ -284,7 +248,10 @@ decl_module! {
             });
 
             if err == WAIT_HTTP_CONVER_REPONSE.as_bytes().to_vec(){ // 本地服务没开起来
-               T::slash_validtor(account.clone());
+               T::note_verifier_downtime(account.clone());
+            }
+            if err == INVALID_RESPONSE_SIGNATURE.as_bytes().to_vec(){ // 验证服务的结果被篡改
+               T::report_verifier_fault(account.clone(), tx.clone());
             }
 
             Self::deposit_event(RawEvent::FailedEvent(account.clone(),block,tx));
@@ -313,44 +280,46 @@ decl_module! {
 
 impl<T: Trait> Module<T> {
     fn offchain(block_num:T::BlockNumber,key: <T as BaseLocalAuthorityTrait>::AuthorityId, account: &T::AccountId) -> DispatchResult {
-        for (remote_src, remote_url) in TX_FETCHED_CRYPTS.iter() {
-            // 最多推进去10个, 取最后一个
-            let mut tx_vec = vec![];
-//            let seed_num = seed % 10;  // 产生 10以内的数字
-//            debug::info!("产生随机数:seed={},seed_num={}",seed,seed_num);
-            for key_value in <TxVerifyMap>::iter().into_iter() {
-                let ((tx,mine_tag), status) = key_value;
-//                if <TxVerifyMap>::get(&tx) < 1000 {
-//                    debug::error!("=====挖矿验证失败:当前的{:?},状态为 {:?}=====", hex::encode(&tx),<TxVerifyMap>::get(&tx));
-//                    Self::call_record_fail_fetch(block_num, account.clone(), tx.clone(), b"".to_vec())?;
-//                    return Err(DispatchError::Other("status less than 1000"));
-//                }
-                tx_vec.push((tx, mine_tag));
+        // 只验证这个区块分给自己的tx
+        let assignment = <register::TxVerifyAssignment>::get();
+        let tx_vec = T::assigned_items(block_num, account, assignment, <TxVerifyMap<T>>::iter());
+        for (tx, mine_tag) in tx_vec.iter() {
+            if let Err(e) = Self::verify_tx(block_num, key.clone(), account, tx, mine_tag.clone()) {
+                debug::error!("------verify tx {:?} failed: {:?}------", hex::encode(tx), e);
             }
-            if tx_vec.is_empty() {
-                return Ok(());
-            }
-//            let tx_str = core::str::from_utf8(&tx_vec[0]).map_err(|e|{
-//                debug::info!("解析错误:{:?}",e);
-//                "from_utf8 error" })?;
+        }
+        Ok(())
+    }
 
-            let (tx,mine_tag) = &tx_vec[0];
+    fn verify_tx(block_num:T::BlockNumber,key: <T as BaseLocalAuthorityTrait>::AuthorityId, account: &T::AccountId, tx: &[u8], mine_tag: MineTag) -> DispatchResult {
+        for (remote_src, remote_url) in TX_FETCHED_CRYPTS.iter() {
             debug::info!("*****tx={:?}******",hex::encode(&tx));   // core::str::from_utf8(remote_src).unwrap()
             let body = Self::from_mine_item(&tx,mine_tag.clone()).ok_or("from_mine_item error"); // 获取请求的post body
             let body = match body {
                 Ok(body) => body,
                 Err(e) => {
                     debug::error!("---------from_mine_item error {:?}---------",e);
-                    Self::call_record_fail_fetch(block_num,key.clone(), account.clone(),  mine_tag.clone(),tx.clone(),e.as_bytes().to_vec())?;
-                    return Err(DispatchError::Other("from_mine_item error"));
+                    Self::call_record_fail_fetch(block_num,key.clone(), account.clone(),  mine_tag.clone(),tx.to_vec(),e.as_bytes().to_vec())?;
+                    return Ok(());
                 }
             };
 
-            match T::fetch_status(*remote_src, *remote_url, body) {
+            // 设置了这条链的RPC节点时直接请求节点验证, 否则请求token-query
+            let urls = T::verifier_urls(TX_VERIFIER_URLS_KEY, remote_url);
+            let result = match Self::native_verify(&tx, mine_tag.clone()) {
+                Some(result) => result,
+                None => {
+                    // 登记了验证服务公钥的, 结果要有正确的签名
+                    let symbol = <OwnerMineRecord<T>>::get(tx, mine_tag.clone()).map(|r| r.symbol).unwrap_or_default();
+                    let auth = T::response_auth(account, tx, &symbol);
+                    T::fetch_status_failover(*remote_src, &urls, body, auth.as_ref())
+                },
+            };
+            match result {
                 Ok(status) => {
                     debug::info!("*** fetch ***: {:?}:{:?},{:?}",
                             core::str::from_utf8(remote_src).unwrap(),
-                            core::str::from_utf8(remote_url).unwrap(),
+                            urls.len(),
                             hex::encode(&tx),
                         );
                     Self::call_record_tx(block_num, key.clone(), account, mine_tag.clone(),&tx, status)?;
@@ -362,7 +331,7 @@ impl<T: Trait> Module<T> {
                     hex::encode(&tx),
                     );
                     // 实现错误信息上报记录
-                    Self::call_record_fail_fetch(block_num, key.clone(), account.clone(), mine_tag.clone(),tx.clone(), e.as_bytes().to_vec())?;
+                    Self::call_record_fail_fetch(block_num, key.clone(), account.clone(), mine_tag.clone(),tx.to_vec(), e.as_bytes().to_vec())?;
                 }
             }
             break;
@@ -405,7 +374,7 @@ impl<T: Trait> Module<T> {
         tx:&'a [u8],  //tx
         status: u64
     )-> DispatchResult{
-        let signature = key.sign(&(block_num,account_id,tx.to_vec(),status).encode()).ok_or("Offchain error: signing failed!")?;
+        let signature = key.sign(&(block_num,account_id,mine_tag.clone(),tx.to_vec(),status).encode()).ok_or("Offchain error: signing failed!")?;
         debug::info!("完成签名,block_num = {:?}",block_num);
         let call = Call::record_tx(
             block_num,
@@ -435,7 +404,7 @@ impl<T: Trait> Module<T> {
         tx: Vec<u8>,
         err: Vec<u8>
     )->DispatchResult{
-        let signature = key.sign(&(block_num,account.clone(),tx.to_vec()).encode()).ok_or("Offchain error: signing failed!")?;
+        let signature = key.sign(&(block_num,account.clone(),mine_tag.clone(),tx.to_vec(),err.clone()).encode()).ok_or("Offchain error: signing failed!")?;
         let call = Call::record_fail_fetch(block_num, key, account, mine_tag.clone(), tx, err, signature);
         SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
             .map_err(|_| {
@@ -449,6 +418,33 @@ impl<T: Trait> Module<T> {
 
 
 
+    /// 用链的RPC节点验证(chain_verifier), 这条链没有设置节点时返回None
+    fn native_verify(tx: &[u8], mine_tag: MineTag) -> Option<StdResult<u64>> {
+        let mine_struct = <OwnerMineRecord<T>>::get(tx, mine_tag)?;
+        let timestamp: u64 = mine_struct.timestamp.try_into().ok()?.try_into().ok()?;
+        let chain = if mine_struct.blockchain.is_empty() { mine_struct.symbol.clone() } else { mine_struct.blockchain };
+        if !chain_verifier::is_configured(&chain) {
+            return None;
+        }
+        let amount = match core::str::from_utf8(&mine_struct.sym_amount).ok().and_then(|a| a.parse::<u128>().ok()) {
+            Some(amount) => amount,
+            None => return Some(Ok(chain_verifier::VERIFY_AMOUNT_MISMATCH)),
+        };
+        let contract = <register::Module<T>>::token_contract(mine_struct.symbol.to_ascii_lowercase());
+        let claim = chain_verifier::TxClaim {
+            tx: mine_struct.tx,
+            chain,
+            symbol: mine_struct.symbol,
+            from: mine_struct.from_address,
+            to: mine_struct.to_address,
+            amount,
+            decimal: mine_struct.decimal,
+            timestamp: timestamp / 1000,  // 毫秒转秒
+            contract,
+        };
+        chain_verifier::verify_claim(&claim)
+    }
+
     fn from_mine_item(tx: &[u8],mine_tag: MineTag) -> Option<Vec<u8>> {
         //*
 //         let mine_struct = <OwnerMineRecord<T>>::get(tx)?;//PersonMineRecord todo: 如果没有获取到
@@ -478,39 +474,20 @@ impl<T: Trait> Module<T> {
         let decimal = 4; // 上面的 decimal 需要转换为 u64
         */
 
-        let keys:[&[u8];8] = [b"tx",b"symbol",b"from",b"to",b"quantity",b"amount_usd",b"timestamp",b"decimal"];
-        let timestamp = int_covert_str(timestamp);
-//        let quantity = int_covert_str(quantity as u64);
-        let decimal = int_covert_str(decimal.into());
-        let amount_usd = int_covert_str(amount_usd);
-
-        let tx_vec = hex_to_u8(&tx);  // 先编码为对应的hex字符串
-        let from_vec = hex_to_u8(&from);
-        let to_vec = hex_to_u8(&to);
-        let vals:[&[u8];8] = [&tx_vec,&symbol,&from_vec,&to_vec,&quantity,&amount_usd,&timestamp,&decimal];
-
-        let mut json_val = vec![POST_KEYWORD[0]];
-        for (i,key) in keys.iter().enumerate(){
-            json_val.push(POST_KEYWORD[1]); //json_val.push("\"");
-            json_val.push(key);
-            json_val.push(POST_KEYWORD[2]);  //json_val.push("\":");
-            if i <= 5 {
-                json_val.push(POST_KEYWORD[1]);  // json_val.push("\"");
-                json_val.push(vals[i]);
-                json_val.push(POST_KEYWORD[1]);   //json_val.push("\"");
-            }else{
-//                let val = int_covert_str(vals_int[i]);  放在外层,生命周期问题
-//                let s = sp_std::str::from_utf8(&vals_u8[i]).ok()?;
-                json_val.push(vals[i]);
-            }
-            json_val.push(POST_KEYWORD[3]);    //json_val.push(",");
-        }
-
-        json_val.pop();
-        json_val.push(POST_KEYWORD[4]);    //json_val.push("}");
-        let json_vec = json_val.concat().to_vec();
+        let request = TxVerifyRequest {
+            version: VERIFY_SCHEMA_VERSION,
+            tx: &tx,
+            symbol: core::str::from_utf8(&symbol).ok()?,
+            from: &from,
+            to: &to,
+            quantity: core::str::from_utf8(&quantity).ok()?,
+            amount_usd,
+            timestamp,
+            decimal,
+        };
+        let json_vec = to_json_body(&request).ok()?;
         debug::info!("mine验证,请求的json:{:?}",core::str::from_utf8(&json_vec).ok()?);
-        Some(json_val.concat().to_vec())
+        Some(json_vec)
     }
 
 //    fn fetch_price_from_localhost(json_val: JsonValue) -> StdResult<u64> {
@@ -527,40 +504,114 @@ impl<T: Trait> Module<T> {
 //        Ok(val)
 //    }
 
-    fn tx_verify_map_handle(tx: &[u8],mine_tag: MineTag) -> StdResult<ReportStatus>{
-        // 是否举报, true 表示举报
-//        判断
-//        1.十位的数字 >=1 report ,举报
-//        2.个位数字 >=8, report
-//        3.百位数字 >=2,pass
+    /// 只有开启了ocw验证的验证人用自己的key才能投票, 并且同一个tx只能投一次
+    fn check_voter(
+        key: &<T as BaseLocalAuthorityTrait>::AuthorityId,
+        account: &T::AccountId,
+        tx: &[u8],
+        mine_tag: &MineTag,
+        vote: register::VerifyVote,
+    ) -> core::result::Result<(), InvalidTransaction> {
+        if key.clone().into_account32() != *account || !T::is_ocw_validator(account) {
+            debug::warn!("-----{:?} 不是开启了ocw验证的验证人-----", account);
+            return Err(InvalidTransaction::BadProof);
+        }
+        let key = (tx.to_vec(), mine_tag.clone());
+        if !<TxVerifyMap<T>>::contains_key(&key) {
+            return Err(InvalidTransaction::Stale);
+        }
+        if !<TxVerifyMap<T>>::get(&key).can_vote(account, vote) {
+            debug::warn!("-----{:?} 已经投过票了-----", account);
+            return Err(InvalidTransaction::Stale);
+        }
+        Ok(())
+    }
+
+    /// 已经记过没有结果的验证人又报本地服务没开(不再记票, 只算一次没有应答, 同一个区块只算一次)
+    fn is_repeated_downtime(key: &(Vec<u8>, MineTag), account: &T::AccountId, err: &[u8]) -> bool {
+        err == WAIT_HTTP_CONVER_REPONSE.as_bytes() && <TxVerifyMap<T>>::get(key).errors.contains(account)
+    }
+
+    /// 按开启了ocw验证的验证人数判断是否结束验证, 结束了就移出队列
+    /// 不通过的票数够了就举报, 通过的票数够了就通过, 与结论一致的验证人记一票(用于分验证奖励)
+    fn tx_verify_map_handle(tx: &[u8],mine_tag: MineTag) -> (ReportStatus, register::VerifyTally<T::AccountId>){
+        let key = (tx.to_vec(),mine_tag);
+        let tally = <TxVerifyMap<T>>::get(&key);
+        let quorum = <register::Module<T>>::tx_verify_quorum();
+        let validators = T::ocw_validator_count();
+
+        let report_status = if quorum.is_failed(&tally, validators) {
+            <register::Module<T>>::note_correct_votes(&tally.fails);
+            ReportStatus::Report
+        } else if quorum.is_passed(&tally, validators) {
+            <register::Module<T>>::note_correct_votes(&tally.passes);
+            ReportStatus::Pass
+        } else {
+            return (ReportStatus::Continue, tally);
+        };
+
+        Self::remove_tx(&key);
+        (report_status, tally)
+    }
 
-        let mut report_status:ReportStatus = ReportStatus::Continue;//初始化一个值
-        let status:u64 = <TxVerifyMap>::get((tx.clone(),mine_tag.clone()));
+    /// 移出验证队列
+    fn remove_tx(key: &(Vec<u8>, MineTag)) {
         let num = LenOfTxVerify::get();
-        if status < 1000{
-            debug::error!("=====挖矿验证失败:当前的{:?},状态为 {:?}=====", hex::encode(&tx),status);
-            <TxVerifyMap>::remove((tx.clone(),mine_tag.clone()));
-            if num > 0{
-                LenOfTxVerify::mutate(|n|*n -= 1);
-            }
-            return Err("status 小于 1000");
+        debug::warn!("移除 tx={:?},当前队列剩余 {:?} 个",hex::encode(&key.0),num);
+        <TxVerifyMap<T>>::remove(key); // 移除掉
+        <TxVerifyPending<T>>::remove(key);
+        if num >0{
+            LenOfTxVerify::mutate(|n|*n -= 1);
         }
+    }
 
+    /// 以系统的名义举报(不需要抵押), 投不通过的验证人作为证据
+    fn report_tx(tx: Vec<u8>, mine_tag: MineTag, reason: Vec<u8>, validators: Vec<T::AccountId>) {
+        debug::warn!("调用举报举报");
+        let evidence = report::Evidence::new(report::ReportCategory::FakeTx, reason, None);
+        if let Err(e) = <report::Module<T>>::system_report(tx,mine_tag,evidence,validators){
+            debug::error!("-----system report failed: {:?}-----",e);
+        }
+    }
 
-        if status/10%10 >= 2 || status%10 >= 8{  // 第十位数
-            report_status = ReportStatus::Report;
-        }else if status/100%10 >= 2{  // 验证数量超过了10个,或者 验证通过超过2个
-            report_status =  ReportStatus::Pass;
-        }else{
-            return Ok(ReportStatus::Continue);
+    /// 处理这个区块超时的tx 每个区块最多处理MAX_VERIFY_TIMEOUT_PER_BLOCK个 剩下的留到下一个区块
+    /// 返回按处理的个数算的weight
+    fn handle_timeout(block: T::BlockNumber) -> Weight {
+        let db = T::DbWeight::get();
+        let mut expiring = <TxVerifyExpiry<T>>::take(block);
+        if expiring.is_empty() {
+            return db.reads(1);
+        }
+        let mut weight = db.reads_writes(2, 1);
+        if expiring.len() > register::MAX_VERIFY_TIMEOUT_PER_BLOCK {
+            let rest = expiring.split_off(register::MAX_VERIFY_TIMEOUT_PER_BLOCK);
+            <TxVerifyExpiry<T>>::mutate(block + T::BlockNumber::from(1u32), |keys| keys.extend(rest));
+            weight = weight.saturating_add(db.reads_writes(1, 1));
         }
 
-        debug::warn!("移除 tx={:?},当前队列剩余 {:?} 个",hex::encode(tx),num);
-        <TxVerifyMap>::remove((tx.clone(),mine_tag.clone())); // 移除掉
-        if num >0{
-            LenOfTxVerify::mutate(|n|*n -= 1);
+        let policy = <register::Module<T>>::tx_verify_timeout().policy;
+        for key in expiring {
+            // 已经有结论或是重新加入队列的跳过
+            weight = weight.saturating_add(db.reads(2));
+            let inserted = match <TxVerifyPending<T>>::get(&key) {
+                Some((inserted, deadline)) if deadline <= block && <TxVerifyMap<T>>::contains_key(&key) => inserted,
+                _ => continue,
+            };
+            // 读出投票结果 移出队列(TxVerifyMap、TxVerifyPending、LenOfTxVerify)并记录事件
+            weight = weight.saturating_add(db.reads_writes(2, 4));
+            let tally = <TxVerifyMap<T>>::get(&key);
+            Self::remove_tx(&key);
+            let (tx, mine_tag) = key;
+            debug::warn!("tx={:?} 验证超时, 通过:{:?} 不通过:{:?} 没有结果:{:?}",
+                hex::encode(&tx), tally.passes.len(), tally.fails.len(), tally.errors.len());
+            // 挖矿的收益已经发了, 只有不通过的票比通过的多才交给举报投票, 否则按通过处理只是移出队列
+            if policy == TimeoutPolicy::Report && tally.fails.len() > tally.passes.len() {
+                Self::report_tx(tx.clone(), mine_tag.clone(), b"tx verify timeout".to_vec(), tally.fails);
+                weight = weight.saturating_add(<report::Module<T>>::system_report_weight());
+            }
+            Self::deposit_event(RawEvent::TxVerifyTimeout(tx, mine_tag, inserted, policy));
         }
-        return Ok(report_status);
+        weight
     }
 
 }
@@ -581,11 +632,15 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
     ) -> TransactionValidity {
         let now = <timestamp::Module<T>>::get();
         match call {
-            Call::record_tx(block_num,key,account_id,..,tx,status, signature) => {
+            Call::record_tx(block_num,key,account_id,mine_tag,tx,status, signature) => {
                 debug::info!("############## record_tx :now = {:?} block_num = {:?}##############",now,block_num);
 
+                if let Err(e) = Self::check_voter(key, account_id, tx, mine_tag, register::VerifyVote::from_status(*status)) {
+                    return e.into();
+                }
+
                 // check signature (this is expensive so we do it last).
-                let signature_valid = &(block_num,account_id,tx,status).using_encoded(|encoded_sign| {
+                let signature_valid = &(block_num,account_id,mine_tag,tx,status).using_encoded(|encoded_sign| {
                     key.verify(&encoded_sign, &signature)
                 });
 
@@ -597,17 +652,25 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
                 Ok(ValidTransaction {
                     priority: 1000,
                     requires: vec![],
-                    provides: vec![(block_num,account_id,tx,status).encode()],
+                    // 同一个验证人对同一个tx只能有一票(block_num 是验证人自己填的, 不能放进来)
+                    provides: vec![(b"record_tx",account_id,tx,mine_tag).encode()],
                     longevity: TransactionLongevity::max_value(),
                     propagate: true,
                 })
             },
 
-            Call::record_fail_fetch(block, key, account,.., tx, err, signature) => {
+            Call::record_fail_fetch(block, key, account, mine_tag, tx, err, signature) => {
                 debug::info!("############## record_fail_fetch :{:?}##############",now);
 
+                match Self::check_voter(key, account, tx, mine_tag, register::VerifyVote::Error) {
+                    Err(InvalidTransaction::Stale) if Self::is_repeated_downtime(&(tx.clone(), mine_tag.clone()), account, err) => {},
+                    Err(e) => return e.into(),
+                    Ok(()) => {},
+                }
+
                 // check signature (this is expensive so we do it last).
-                let signature_valid = &(block,account,tx).using_encoded(|encoded_sign| {
+                // err 也要签名, 否则转发的节点可以改成本地服务没开或是签名错误来陷害验证人
+                let signature_valid = &(block,account,mine_tag,tx,err).using_encoded(|encoded_sign| {
                     key.verify(&encoded_sign, &signature)
                 });
 
@@ -618,7 +681,7 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
                 Ok(ValidTransaction {
                     priority: 1,
                     requires: vec![],
-                    provides: vec![(block,tx,err,account).encode()], // vec![(now).encode()],
+                    provides: vec![(b"record_fail_fetch",account,tx,mine_tag).encode()],
                     longevity: TransactionLongevity::max_value(),
                     propagate: true,
                 })},
@@ -627,68 +690,499 @@ impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
     }
 }
 
-/// tests for this module
 #[cfg(test)]
 mod tests {
     use super::*;
+    use frame_support::{assert_ok, assert_noop, storage::migration, traits::{Get, OnInitialize, UnfilteredDispatchable},
+                        unsigned::ValidateUnsigned};
+    use sp_core::Pair;
+    use crate::{Runtime, System, TxValid, AccountId, BlockNumber};
+    use crate::address_valid::address_crypto::AuthorityPair;
+
+    const TX: &[u8] = b"0x485615bff2000aa18399a0c8314239a395facf7412ee64cb57a75065f6480c84";
+
+    fn validator(i: u8) -> (AuthorityPair, AccountId) {
+        let pair = AuthorityPair::from_seed(&[i; 32]);
+        let account = pair.public().into_account32();
+        (pair, account)
+    }
 
-    use sp_core::H256;
-    use frame_support::{impl_outer_origin, assert_ok, parameter_types};
-    use sr_primitives::{
-        traits::{BlakeTwo256, IdentityLookup}, testing::Header, weights::Weight, Perbill,
-    };
+    /// validators 个开启了ocw验证的验证人, 一个待验证的tx
+    fn new_test_ext(validators: u8) -> sp_io::TestExternalities {
+        let t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+        let mut t: sp_io::TestExternalities = t.into();
+        t.execute_with(|| {
+            System::set_block_number(1);
+            let accounts = (1..=validators).map(|i| validator(i).1).collect::<Vec<_>>();
+            for account in accounts.iter() {
+                <register::IsValidtorOcw<Runtime>>::insert(account, true);
+            }
+            migration::put_storage_value(b"Session", b"Validators", &[], accounts);
+            <TxVerifyMap<Runtime>>::insert((TX.to_vec(), MineTag::WALLET), register::VerifyTally::default());
+            LenOfTxVerify::put(1);
+        });
+        t
+    }
 
-    impl_outer_origin! {
-    pub enum Origin for Test {}
-  }
+    /// 验证人 signer 用自己的key签名, 以 account 的名义投票
+    fn record_tx_as(signer: u8, account: AccountId, block: BlockNumber, status: u64) -> Call<Runtime> {
+        let (pair, _) = validator(signer);
+        let signature = pair.sign(&(block, &account, MineTag::WALLET, TX.to_vec(), status).encode());
+        Call::record_tx(block, pair.public(), account, MineTag::WALLET, TX.to_vec(), status, signature)
+    }
 
-    // For testing the module, we construct most of a mock runtime. This means
-    // first constructing a configuration type (`Test`) which `impl`s each of the
-    // configuration traits of modules we want to use.
-    #[derive(Clone, Eq, PartialEq)]
-    pub struct Test;
-    parameter_types! {
-    pub const BlockHashCount: u64 = 250;
-    pub const MaximumBlockWeight: Weight = 1024;
-    pub const MaximumBlockLength: u32 = 2 * 1024;
-    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
-  }
-    impl system::Trait for Test {
-        type Origin = Origin;
-        type Call = ();
-        type Index = u64;
-        type BlockNumber = u64;
-        type Hash = H256;
-        type Hashing = BlakeTwo256;
-        type AccountId = u64;
-        type Lookup = IdentityLookup<Self::AccountId>;
-        type Header = Header;
-        type Event = ();
-        type BlockHashCount = BlockHashCount;
-        type MaximumBlockWeight = MaximumBlockWeight;
-        type MaximumBlockLength = MaximumBlockLength;
-        type AvailableBlockRatio = AvailableBlockRatio;
-        type Version = ();
-    }
-    impl Trait for Test {
-        type Event = ();
-    }
-    type TemplateModule = Module<Test>;
-
-    // This function basically just builds a genesis storage key/value store according to
-    // our desired mockup.
-    fn new_test_ext() -> sp_io::TestExternalities {
-        system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+    fn record_tx(i: u8, block: BlockNumber, status: u64) -> Call<Runtime> {
+        record_tx_as(i, validator(i).1, block, status)
+    }
+
+    fn record_fail_fetch(i: u8, block: BlockNumber) -> Call<Runtime> {
+        record_fail_fetch_err(i, block, "timeout")
+    }
+
+    fn record_fail_fetch_err(i: u8, block: BlockNumber, err: &str) -> Call<Runtime> {
+        let (pair, account) = validator(i);
+        let signature = pair.sign(&(block, &account, MineTag::WALLET, TX.to_vec(), err.as_bytes().to_vec()).encode());
+        Call::record_fail_fetch(block, pair.public(), account, MineTag::WALLET, TX.to_vec(), err.as_bytes().to_vec(), signature)
+    }
+
+    fn validate(call: &Call<Runtime>) -> TransactionValidity {
+        <TxValid as ValidateUnsigned>::validate_unsigned(TransactionSource::External, call)
+    }
+
+    /// 和出块一样: 先 validate_unsigned 再执行
+    fn submit(call: Call<Runtime>) -> DispatchResult {
+        validate(&call).map_err(|_| "invalid unsigned transaction")?;
+        call.dispatch_bypass_filter(system::RawOrigin::None.into()).map(|_| ()).map_err(|e| e.error)
+    }
+
+    fn tally() -> register::VerifyTally<AccountId> {
+        <TxVerifyMap<Runtime>>::get((TX.to_vec(), MineTag::WALLET))
     }
 
     #[test]
-    fn it_works_for_default_value() {
-        new_test_ext().execute_with(|| {
-            // Just a dummy test for the dummy funtion `do_something`
-            // calling the `do_something` function with a value 42
-            assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
-            // asserting that the stored value is equal to what we stored
-            assert_eq!(TemplateModule::something(), Some(42));
+    fn repeated_votes_are_rejected() {
+        new_test_ext(6).execute_with(|| {
+            assert_ok!(submit(record_tx(1, 1, 4001)));
+            // 换一个 block_num 再投
+            for block in 2..10 {
+                assert_eq!(validate(&record_tx(1, block, 4001)), InvalidTransaction::Stale.into());
+                assert_eq!(validate(&record_tx(1, block, 0)), InvalidTransaction::Stale.into());
+            }
+            // 绕过交易池直接执行也不算
+            assert_noop!(
+                record_tx(1, 3, 4001).dispatch_bypass_filter(system::RawOrigin::None.into()).map_err(|e| e.error),
+                DispatchError::Other("已经投过票了")
+            );
+            assert_eq!(tally().fails, vec![validator(1).1]);
+            assert!(tally().passes.is_empty());
+        });
+    }
+
+    #[test]
+    fn failed_fetch_error_is_signed() {
+        new_test_ext(3).execute_with(|| {
+            // 转发的节点把err改成签名错误或是本地服务没开
+            for err in [INVALID_RESPONSE_SIGNATURE, WAIT_HTTP_CONVER_REPONSE].iter() {
+                let forged = match record_fail_fetch(1, 1) {
+                    Call::record_fail_fetch(block, key, account, mine_tag, tx, _, signature) =>
+                        Call::record_fail_fetch(block, key, account, mine_tag, tx, err.as_bytes().to_vec(), signature),
+                    _ => unreachable!(),
+                };
+                assert_eq!(validate(&forged), InvalidTransaction::BadProof.into());
+            }
+            assert_ok!(validate(&record_fail_fetch(1, 1)));
         });
     }
+
+    #[test]
+    fn single_malicious_validator_cannot_decide() {
+        // 3个验证人 需要2票
+        new_test_ext(3).execute_with(|| {
+            assert_ok!(submit(record_tx(1, 1, 4001)));
+            assert!(submit(record_tx(1, 2, 4001)).is_err());
+            assert!(submit(record_tx(1, 3, 4001)).is_err());
+            assert_eq!(LenOfTxVerify::get(), 1);
+
+            // 两个诚实的验证人投通过
+            assert_ok!(submit(record_tx(2, 1, 0)));
+            assert_eq!(LenOfTxVerify::get(), 1);
+            assert_ok!(submit(record_tx(3, 1, 0)));
+            assert!(!<TxVerifyMap<Runtime>>::contains_key((TX.to_vec(), MineTag::WALLET)));
+            assert_eq!(LenOfTxVerify::get(), 0);
+        });
+    }
+
+    #[test]
+    fn votes_for_other_validators_are_rejected() {
+        new_test_ext(3).execute_with(|| {
+            // 用自己的key替别的验证人投票
+            assert_eq!(validate(&record_tx_as(1, validator(2).1, 1, 4001)), InvalidTransaction::BadProof.into());
+            // 不是验证人
+            assert_eq!(validate(&record_tx(9, 1, 4001)), InvalidTransaction::BadProof.into());
+            // 没有开启ocw验证
+            <register::IsValidtorOcw<Runtime>>::insert(validator(3).1, false);
+            assert_eq!(validate(&record_tx(3, 1, 4001)), InvalidTransaction::BadProof.into());
+            // 改了签名里的内容
+            let (pair, account) = validator(2);
+            let signature = pair.sign(&(1 as BlockNumber, &account, MineTag::WALLET, TX.to_vec(), 0u64).encode());
+            let forged = Call::record_tx(1, pair.public(), account.clone(), MineTag::WALLET, TX.to_vec(), 4001, signature.clone());
+            assert_eq!(validate(&forged), InvalidTransaction::BadProof.into());
+            <TxVerifyMap<Runtime>>::insert((TX.to_vec(), MineTag::CLIENT), register::VerifyTally::default());
+            let forged = Call::record_tx(1, pair.public(), account, MineTag::CLIENT, TX.to_vec(), 0, signature);
+            assert_eq!(validate(&forged), InvalidTransaction::BadProof.into());
+            assert_eq!(tally(), Default::default());
+        });
+    }
+
+    #[test]
+    fn failed_fetch_is_recorded_once() {
+        new_test_ext(3).execute_with(|| {
+            assert_ok!(submit(record_fail_fetch(1, 1)));
+            assert_eq!(validate(&record_fail_fetch(1, 2)), InvalidTransaction::Stale.into());
+            assert_eq!(validate(&record_tx(1, 2, 255)), InvalidTransaction::Stale.into());
+            assert_eq!(tally().errors, vec![validator(1).1]);
+
+            // 本地服务恢复后还可以投一次通过或是不通过
+            assert_ok!(submit(record_tx(1, 3, 0)));
+            assert_eq!(validate(&record_fail_fetch(1, 4)), InvalidTransaction::Stale.into());
+            assert_eq!(tally().passes, vec![validator(1).1]);
+            assert!(tally().errors.is_empty());
+        });
+    }
+
+    #[test]
+    fn same_vote_is_deduplicated_in_pool() {
+        new_test_ext(3).execute_with(|| {
+            let a = validate(&record_tx(1, 1, 4001)).unwrap();
+            let b = validate(&record_tx(1, 2, 0)).unwrap();
+            assert_eq!(a.provides, b.provides);
+            let c = validate(&record_tx(2, 1, 4001)).unwrap();
+            assert_ne!(a.provides, c.provides);
+        });
+    }
+
+    #[test]
+    fn removed_tx_is_stale() {
+        new_test_ext(3).execute_with(|| {
+            <TxVerifyMap<Runtime>>::remove((TX.to_vec(), MineTag::WALLET));
+            assert_eq!(validate(&record_tx(1, 1, 0)), InvalidTransaction::Stale.into());
+        });
+    }
+
+    /// 每个验证人这个区块分到的tx
+    fn assigned(validators: u8, block: BlockNumber) -> Vec<Vec<Vec<u8>>> {
+        let assignment = <register::TxVerifyAssignment>::get();
+        (1..=validators).map(|i| {
+            <Runtime as BaseLocalAuthorityTrait>::assigned_items(block, &validator(i).1, assignment, <TxVerifyMap<Runtime>>::iter())
+                .into_iter()
+                .map(|(tx, _)| tx)
+                .collect()
+        }).collect()
+    }
+
+    #[test]
+    fn each_tx_is_assigned_to_distinct_verifiers() {
+        new_test_ext(5).execute_with(|| {
+            for i in 0..9u8 {
+                <TxVerifyMap<Runtime>>::insert((vec![i], MineTag::WALLET), register::VerifyTally::default());
+            }
+            let assignment = register::VerifyAssignment { per_block: 10, verifiers: 3, period: 10 };
+            <register::TxVerifyAssignment>::put(assignment);
+
+            let round = assigned(5, 1);
+            // 每个tx分给3个不同的验证人, 10个tx一共30份, 每个验证人6份
+            for ((tx, _), _) in <TxVerifyMap<Runtime>>::iter() {
+                assert_eq!(round.iter().filter(|txs| txs.contains(&tx)).count(), 3);
+            }
+            assert!(round.iter().all(|txs| txs.len() == 6));
+            // 同一轮里分工不变
+            assert_eq!(assigned(5, 9), round);
+
+            // 投过票的不再分给它, 换别的验证人
+            assert_ok!(submit(record_tx(1, 1, 0)));
+            let next = assigned(5, 2);
+            assert!(!next[0].contains(&TX.to_vec()));
+            assert_eq!(next.iter().filter(|txs| txs.contains(&TX.to_vec())).count(), 3);
+
+            // 只是没有结果的还会分到, 之后还可以投票
+            let errored = next.iter().position(|txs| txs.contains(&TX.to_vec())).unwrap() as u8 + 1;
+            assert_ok!(submit(record_fail_fetch(errored, 2)));
+            assert_eq!(assigned(5, 3), next);
+            assert_ok!(submit(record_tx(errored, 3, 0)));
+        });
+    }
+
+    #[test]
+    fn assignment_is_capped_per_block() {
+        new_test_ext(2).execute_with(|| {
+            for i in 0..9u8 {
+                <TxVerifyMap<Runtime>>::insert((vec![i], MineTag::WALLET), register::VerifyTally::default());
+            }
+            // 默认每个区块最多5个
+            assert!(assigned(2, 1).iter().all(|txs| txs.len() == 5));
+            // 不是开启了ocw验证的验证人分不到
+            let outsider = <Runtime as BaseLocalAuthorityTrait>::assigned_items(
+                1, &validator(3).1, <register::TxVerifyAssignment>::get(), <TxVerifyMap<Runtime>>::iter());
+            assert!(outsider.is_empty());
+        });
+    }
+
+    #[test]
+    fn stuck_tx_times_out() {
+        new_test_ext(3).execute_with(|| {
+            let key = (TX.to_vec(), MineTag::WALLET);
+            <TxVerifyPending<Runtime>>::insert(&key, (1, 11));
+            <TxVerifyExpiry<Runtime>>::insert(11, vec![key.clone()]);
+            <register::TxVerifyTimeout>::put(register::VerifyTimeout { blocks: 10, policy: TimeoutPolicy::Pass });
+            // 都没有结果, 不会有结论
+            for i in 1..=3 {
+                assert_ok!(submit(record_fail_fetch(i, 1)));
+            }
+
+            // 没有超时的tx 只读一次超时队列
+            assert_eq!(<TxValid as OnInitialize<BlockNumber>>::on_initialize(10),
+                <Runtime as system::Trait>::DbWeight::get().reads(1));
+            assert!(<TxVerifyMap<Runtime>>::contains_key(&key));
+
+            <TxValid as OnInitialize<BlockNumber>>::on_initialize(11);
+            assert!(!<TxVerifyMap<Runtime>>::contains_key(&key));
+            assert!(<TxVerifyPending<Runtime>>::get(&key).is_none());
+            assert_eq!(LenOfTxVerify::get(), 0);
+            assert!(System::events().iter().any(|r| r.event ==
+                crate::Event::tx_valid(RawEvent::TxVerifyTimeout(TX.to_vec(), MineTag::WALLET, 1, TimeoutPolicy::Pass))));
+        });
+    }
+
+    /// 让 TX 成为一笔已经挖过矿的交易, 超时举报才能成立
+    fn mined_tx() {
+        let miner: AccountId = [9u8; 32].into();
+        <register::AllMiners<Runtime>>::insert(&miner, register::MinerInfo::default());
+        <OwnerMineRecord<Runtime>>::insert(TX.to_vec(), MineTag::WALLET, crate::mine_linked::PersonMineRecord {
+            mine_tag: MineTag::WALLET,
+            mine_count: 1,
+            timestamp: 0,
+            blocknum: 1,
+            miner_address: miner,
+            from_address: vec![0x13],
+            to_address: vec![0x0c],
+            symbol: b"eth".to_vec(),
+            blockchain: b"eth".to_vec(),
+            tx: TX.to_vec(),
+            usdt_amount: 100,
+            sym_amount: b"1".to_vec(),
+            decimal: 4,
+            pcount_workforce: 0,
+            pamount_workforce: 0,
+            reward: 0,
+            grandpa_reward: 0,
+            father_reward: 0,
+        });
+    }
+
+    fn system_reported() -> bool {
+        System::events().iter().any(|r| match r.event {
+            crate::Event::report(report::RawEvent::SystemReportEvent(..)) => true,
+            _ => false,
+        })
+    }
+
+    #[test]
+    fn timeout_reports_only_when_fails_outnumber_passes() {
+        // 6个验证人 3票才有结论
+        for (passes, fails, reported) in vec![(2u8, 1u8, false), (1, 1, false), (1, 2, true)] {
+            new_test_ext(6).execute_with(|| {
+                mined_tx();
+                let key = (TX.to_vec(), MineTag::WALLET);
+                <TxVerifyPending<Runtime>>::insert(&key, (1, 11));
+                <TxVerifyExpiry<Runtime>>::insert(11, vec![key.clone()]);
+                <register::TxVerifyTimeout>::put(register::VerifyTimeout::tx_default());
+                for i in 1..=passes {
+                    assert_ok!(submit(record_tx(i, 1, 0)));
+                }
+                for i in passes + 1..=passes + fails {
+                    assert_ok!(submit(record_tx(i, 1, 4001)));
+                }
+                assert!(<TxVerifyMap<Runtime>>::contains_key(&key));
+
+                let weight = <TxValid as OnInitialize<BlockNumber>>::on_initialize(11);
+                assert!(!<TxVerifyMap<Runtime>>::contains_key(&key));
+                assert_eq!(system_reported(), reported);
+                // 处理了一个tx 举报了的加上举报的weight
+                let db = <Runtime as system::Trait>::DbWeight::get();
+                let report_weight = if reported { report::Module::<Runtime>::system_report_weight() } else { 0 };
+                assert_eq!(weight, db.reads_writes(2, 1) + db.reads(2) + db.reads_writes(2, 4) + report_weight);
+                assert!(System::events().iter().any(|r| r.event ==
+                    crate::Event::tx_valid(RawEvent::TxVerifyTimeout(TX.to_vec(), MineTag::WALLET, 1, TimeoutPolicy::Report))));
+            });
+        }
+    }
+
+    #[test]
+    fn tx_timeout_cannot_be_fail() {
+        new_test_ext(3).execute_with(|| {
+            let set_timeout = |policy| register::Call::<Runtime>::set_tx_verify_timeout(register::VerifyTimeout { blocks: 10, policy })
+                .dispatch_bypass_filter(system::RawOrigin::Root.into()).map(|_| ()).map_err(|e| e.error);
+            assert_noop!(set_timeout(TimeoutPolicy::Fail), register::Error::<Runtime>::InvalidTimeout);
+            assert_ok!(set_timeout(TimeoutPolicy::Pass));
+            assert_ok!(set_timeout(TimeoutPolicy::Report));
+        });
+    }
+
+    #[test]
+    fn decided_tx_does_not_time_out() {
+        new_test_ext(3).execute_with(|| {
+            let key = (TX.to_vec(), MineTag::WALLET);
+            <TxVerifyPending<Runtime>>::insert(&key, (1, 11));
+            <TxVerifyExpiry<Runtime>>::insert(11, vec![key.clone()]);
+            assert_ok!(submit(record_tx(1, 1, 0)));
+            assert_ok!(submit(record_tx(2, 1, 0)));
+            assert!(!<TxVerifyMap<Runtime>>::contains_key(&key));
+            assert!(<TxVerifyPending<Runtime>>::get(&key).is_none());
+
+            <TxValid as OnInitialize<BlockNumber>>::on_initialize(11);
+            assert_eq!(LenOfTxVerify::get(), 0);
+            assert!(!System::events().iter().any(|r| match r.event {
+                crate::Event::tx_valid(RawEvent::TxVerifyTimeout(..)) => true,
+                _ => false,
+            }));
+        });
+    }
+
+    #[test]
+    fn request_body_is_escaped_json() {
+        let request = TxVerifyRequest {
+            version: VERIFY_SCHEMA_VERSION,
+            tx: &[0x48, 0x56],
+            symbol: "e\"th",
+            from: &[0x13],
+            to: &[0x0c],
+            quantity: "58187",
+            amount_usd: 100,
+            timestamp: 1578628445,
+            decimal: 4,
+        };
+        assert_eq!(
+            core::str::from_utf8(&to_json_body(&request).unwrap()).unwrap(),
+            r#"{"version":1,"tx":"0x4856","symbol":"e\"th","from":"0x13","to":"0x0c","quantity":"58187","amount_usd":"100","timestamp":1578628445,"decimal":4}"#
+        );
+    }
+
+    #[test]
+    fn verifier_response_must_be_signed_by_registered_key() {
+        let verifier = sp_core::sr25519::Pair::from_seed(&[7; 32]);
+        let auth = ResponseAuth { key: verifier.public(), tx: TX, symbol: b"eth" };
+        let now = 1_600_000_000;
+        let signed = |status: u64, timestamp: u64, pair: &sp_core::sr25519::Pair| ResponseStatus {
+            verify_status: status,
+            timestamp,
+            signature: pair.sign(&auth.message(status, timestamp)).0.to_vec(),
+        };
+
+        assert_ok!(auth.check(&signed(0, now, &verifier), now));
+        // 没有签名, 别的key签的, 改了状态, 过期的
+        let unsigned = ResponseStatus { verify_status: 0, timestamp: now, signature: vec![] };
+        assert_eq!(auth.check(&unsigned, now), Err(INVALID_RESPONSE_SIGNATURE));
+        let forged = signed(0, now, &sp_core::sr25519::Pair::from_seed(&[8; 32]));
+        assert_eq!(auth.check(&forged, now), Err(INVALID_RESPONSE_SIGNATURE));
+        let mut tampered = signed(4001, now, &verifier);
+        tampered.verify_status = 0;
+        assert_eq!(auth.check(&tampered, now), Err(INVALID_RESPONSE_SIGNATURE));
+        let stale = signed(0, now - RESPONSE_MAX_AGE_SECS - 1, &verifier);
+        assert_eq!(auth.check(&stale, now), Err(INVALID_RESPONSE_SIGNATURE));
+    }
+
+    #[test]
+    fn signed_response_is_parsed() {
+        let json = br#"{"verify_status":4001,"timestamp":1600000000,"signature":"0x0a0b"}"#.to_vec();
+        let resp = <Runtime as BaseLocalAuthorityTrait>::fetch_parse(json).unwrap();
+        assert_eq!((resp.verify_status, resp.timestamp, resp.signature), (4001, 1600000000, vec![0x0a, 0x0b]));
+        // 以前的格式
+        let resp = <Runtime as BaseLocalAuthorityTrait>::fetch_parse(br#"{"verify_status":0}"#.to_vec()).unwrap();
+        assert!(resp.signature.is_empty());
+    }
+
+    fn set_active_era(index: pallet_staking::EraIndex) {
+        migration::put_storage_value(b"Staking", b"ActiveEra", &[], (index, None::<u64>));
+    }
+
+    #[test]
+    fn correct_votes_share_era_reward() {
+        new_test_ext(3).execute_with(|| {
+            set_active_era(1);
+            <register::Module<Runtime> as OnInitialize<BlockNumber>>::on_initialize(1);
+            // 验证人1、2投通过(结论), 3投不通过
+            assert_ok!(submit(record_tx(3, 1, 4001)));
+            assert_ok!(submit(record_tx(1, 1, 0)));
+            assert_ok!(submit(record_tx(2, 1, 0)));
+            assert!(!<TxVerifyMap<Runtime>>::contains_key((TX.to_vec(), MineTag::WALLET)));
+            assert_eq!(<register::VerifyPoints<Runtime>>::get(1, validator(1).1), 1);
+            assert_eq!(<register::VerifyPoints<Runtime>>::get(1, validator(3).1), 0);
+            assert_eq!(<register::EraVerifyPoints>::get(1), 2);
+
+            let pot = <register::Module<Runtime>>::verify_pot_account();
+            let ed = <Runtime as pallet_balances::Trait>::ExistentialDeposit::get();
+            pallet_balances::Module::<Runtime>::make_free_balance_be(&pot, 1000 * ed + ed);
+            // era还没有结束
+            let db = <Runtime as system::Trait>::DbWeight::get();
+            assert_eq!(<register::Module<Runtime> as OnInitialize<BlockNumber>>::on_initialize(2), db.reads(2));
+            assert_eq!(pallet_balances::Module::<Runtime>::free_balance(&pot), 1000 * ed + ed);
+
+            set_active_era(2);
+            // 两个有票的验证人: 取票数、转账和事件按人数算
+            assert_eq!(<register::Module<Runtime> as OnInitialize<BlockNumber>>::on_initialize(3), db.reads_writes(11, 10));
+            let balance = |i| pallet_balances::Module::<Runtime>::free_balance(&validator(i).1);
+            assert_eq!((balance(1), balance(2), balance(3)), (500 * ed, 500 * ed, 0));
+            assert_eq!(<register::EraVerifyPoints>::get(1), 0);
+            assert_eq!(<register::VerifyRewardEra>::get(), Some(2));
+        });
+    }
+
+    #[test]
+    fn unresponsive_verifier_is_warned_then_slashed_then_disabled() {
+        new_test_ext(1).execute_with(|| {
+            let penalty = register::DowntimePenalty { window: 10, warn: 2, slash: 3, disable: 4, slash_fraction: sp_runtime::Perbill::from_percent(1) };
+            let set_penalty = |penalty| register::Call::<Runtime>::set_verifier_downtime_penalty(penalty)
+                .dispatch_bypass_filter(system::RawOrigin::Root.into()).map(|_| ()).map_err(|e| e.error);
+            assert_ok!(set_penalty(penalty));
+            let account = validator(1).1;
+            // 本地服务没开, 每个区块都报一次
+            let offline = |block: BlockNumber| {
+                System::set_block_number(block);
+                assert_ok!(submit(record_fail_fetch_err(1, block, WAIT_HTTP_CONVER_REPONSE)));
+                <register::Module<Runtime>>::verifier_downtime(&account).len()
+            };
+
+            assert_eq!(offline(1), 1);
+            // 同一个区块只算一次
+            assert_eq!(offline(1), 1);
+            assert_eq!(offline(2), 2);
+            // 别的错误不能重复记
+            assert_eq!(validate(&record_fail_fetch(1, 3)), InvalidTransaction::Stale.into());
+            // 窗口外的不算
+            assert_eq!(offline(20), 1);
+            assert_eq!(offline(21), 2);
+            assert_eq!(offline(22), 3);
+            assert!(<register::IsValidtorOcw<Runtime>>::get(&account));
+            assert_eq!(offline(23), 0);
+            assert!(!<register::IsValidtorOcw<Runtime>>::get(&account));
+            // 关闭了之后不能再报
+            System::set_block_number(24);
+            assert_eq!(validate(&record_fail_fetch_err(1, 24, WAIT_HTTP_CONVER_REPONSE)), InvalidTransaction::BadProof.into());
+
+            // 没有结果只算一次, 不影响投票
+            assert_eq!(tally().errors, vec![account.clone()]);
+            let stages = System::events().into_iter().filter_map(|r| match r.event {
+                crate::Event::register(register::RawEvent::VerifierWarned(..)) => Some(register::DowntimeStage::Warn),
+                crate::Event::register(register::RawEvent::VerifierSlashed(..)) => Some(register::DowntimeStage::Slash),
+                crate::Event::register(register::RawEvent::VerifierDisabled(..)) => Some(register::DowntimeStage::Disable),
+                _ => None,
+            }).collect::<Vec<_>>();
+            assert_eq!(stages, vec![register::DowntimeStage::Warn, register::DowntimeStage::Warn,
+                register::DowntimeStage::Slash, register::DowntimeStage::Disable]);
+
+            let invalid = register::DowntimePenalty { slash: 4, ..penalty };
+            assert_noop!(set_penalty(invalid), register::Error::<Runtime>::InvalidDowntimePenalty);
+        });
+    }
+
 }
@@ -522,6 +522,26 @@ pub trait ReportedTxs<AccountId>{
 	fn is_reported(who: AccountId) -> bool;
 }
 
+/// 挖矿模块发现可疑的挖矿交易(例如对敲)时, 通知举报模块自动发起举报
+pub trait SuspiciousTxs<AccountId, Tag>{
+	fn on_suspicious(who: AccountId, tx: Vec<u8>, tag: Tag, reason: Vec<u8>);
+}
+
+impl<AccountId, Tag> SuspiciousTxs<AccountId, Tag> for (){
+	fn on_suspicious(_who: AccountId, _tx: Vec<u8>, _tag: Tag, _reason: Vec<u8>){}
+}
+
+/// 判断账户是否是staking的抵押者(举报模块用来判断谁可以参与抵押投票)
+pub trait IsStaker<AccountId>{
+	fn is_staker(who: &AccountId) -> bool;
+}
+
+impl<AccountId> IsStaker<AccountId> for (){
+	fn is_staker(_who: &AccountId) -> bool{
+		false
+	}
+}
+
 /// Determiner to say whether a given account is unused.
 pub trait IsDeadAccount<AccountId> {
 	/// Is the given account dead?